@@ -7,30 +7,111 @@
 //! * monitor: Entrypoint for running the monitoring process. It orchestrates the probing of endpoints and exporting results.
 //! * cli: Handles command-line argument parsing and configuration setup.
 //! * probes: Contains implementations for probing different types of endpoints, such as OpenAI chat completions and embeddings.
-//! * exporters: Contains implementations for exporting monitoring results to different services, currently only Cronitor.
+//! * exporters: Contains implementations for exporting monitoring results to different services, such as Cronitor.
+//! * registry: Maps probe and exporter names from the command line to their implementations.
+//!
+//! ## Features
+//!
+//! * `cli` (default): command line configuration, the registry and the `ai-vitals` binary.
+//! * `cronitor` (default): the Cronitor exporter.
+//! * `http` (default): the generic HTTP probe, with declarative assertions on the response.
+//! * `newman` (default): the Newman probe, which runs Postman collections with the `newman` CLI,
+//!   and the Postman probe, which runs them natively.
+//! * `openai` (default): the OpenAI-compatible probes.
+//! * `tls` (default): the TLS probe, checking the server's certificate chain, host name and expiry.
+//! * `log`: an exporter that reports results through `tracing`.
+//!
+//! To embed just the OpenAI probe, depend on the crate with `default-features = false` and
+//! `features = ["openai"]`.
 //!
 //! ## Running Tests
 //!
 //! ```bash
 //! cargo test
 //! ```
-use anyhow::Result;
 use tracing::{error, info};
 
+/// Errors returned when setting up or running the monitor
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// A probe was constructed for an endpoint type it doesn't handle
+    #[error("the {probe} probe does not support endpoint type {endpoint_type:?}")]
+    UnsupportedEndpointType {
+        probe: &'static str,
+        endpoint_type: probes::Type,
+    },
+    /// A configuration value could not be used
+    #[error("invalid {field}: {reason}")]
+    InvalidConfig { field: &'static str, reason: String },
+    /// A file referenced by the configuration could not be read
+    #[error("failed to read {path}: {source}")]
+    ReadFile {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    /// The HTTP client for a probe or exporter could not be built
+    #[error("failed to build HTTP client: {0}")]
+    HttpClient(#[source] reqwest::Error),
+    /// No probe is registered under the configured name
+    #[error("unknown probe type {name}, expected one of: {}", available.join(", "))]
+    UnknownProbe {
+        name: String,
+        available: Vec<String>,
+    },
+    /// No exporter is registered under the configured name
+    #[error("unknown exporter {name}, expected one of: {}", available.join(", "))]
+    UnknownExporter {
+        name: String,
+        available: Vec<String>,
+    },
+    /// An exporter failed to deliver a ping
+    #[error("{exporter} export failed: {reason}")]
+    Export {
+        exporter: &'static str,
+        reason: String,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
 /// Result of an LLM endpoint probe
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ProbeResult {
     Success,
     Error(u16),
     Timeout,
     NetworkError(String),
+    /// The endpoint answered successfully, but the response body failed validation
+    InvalidResponse(String),
+    /// Steps of a multi-step probe failed, e.g. assertions in a Postman collection. The message
+    /// names each failing step.
+    TestFailure(String),
+    /// The probe could not run because of its own setup, e.g. a missing executable or an
+    /// unreadable input file, so nothing is known about the endpoint
+    Misconfigured(String),
+}
+
+impl std::fmt::Display for ProbeResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ProbeResult::Success => write!(f, "success"),
+            ProbeResult::Error(status_code) => write!(f, "HTTP {status_code}"),
+            ProbeResult::Timeout => write!(f, "timeout"),
+            ProbeResult::NetworkError(error) => write!(f, "network error: {error}"),
+            ProbeResult::InvalidResponse(reason) => write!(f, "invalid response: {reason}"),
+            ProbeResult::TestFailure(message) => write!(f, "{message}"),
+            ProbeResult::Misconfigured(reason) => write!(f, "misconfigured: {reason}"),
+        }
+    }
 }
 
+/// Exit code when the probe is misconfigured, EX_CONFIG from sysexits.h
+pub const EXIT_MISCONFIGURED: i32 = 78;
+
+/// A check against an endpoint, e.g. one of the implementations in [`probes`]
 #[async_trait::async_trait]
-pub trait Probe {
-    fn new(config: cli::Config) -> Result<Self>
-    where
-        Self: std::marker::Sized;
+pub trait Probe: Send + Sync {
     async fn probe(&self) -> ProbeResult;
 }
 
@@ -52,12 +133,10 @@ impl PingState {
     }
 }
 
+/// A destination for probe results, e.g. one of the implementations in [`exporters`]
 #[async_trait::async_trait]
-pub trait Export {
-    fn new(config: cli::Config) -> Result<Self>
-    where
-        Self: std::marker::Sized;
-    async fn ping(&self, state: PingState, status_code: u16, message: Option<&str>);
+pub trait Export: Send + Sync {
+    async fn ping(&self, state: PingState, status_code: u16, message: Option<&str>) -> Result<()>;
 }
 
 /// Main monitoring orchestrator.
@@ -69,61 +148,361 @@ pub struct Monitor {
 }
 
 impl Monitor {
+    /// Build a monitor from the command line configuration, using the built-in probes and
+    /// exporters
+    #[cfg(feature = "cli")]
     pub fn new(config: cli::Config) -> Result<Self> {
-        Ok(Monitor {
-            exporter: Box::new(exporters::Cronitor::new(config.clone())?),
-            probe: match config.endpoint_type {
-                probes::Type::OpenAIChatCompletion | probes::Type::OpenAIEmbedding => {
-                    Box::new(probes::OpenAI::new(config.clone())?)
-                }
-                probes::Type::Newman => Box::new(probes::Newman::new(config.clone())?),
-            },
-        })
+        Monitor::with_registry(config, &registry::Registry::default())
+    }
+
+    /// Build a monitor from the command line configuration, resolving the configured probe
+    /// type and exporter by name in `registry`
+    #[cfg(feature = "cli")]
+    pub fn with_registry(config: cli::Config, registry: &registry::Registry) -> Result<Self> {
+        let probe: Box<dyn Probe> = if config.probe_all_addresses {
+            // One probe per address, each connecting to it in place of the host name
+            Box::new(probes::AllAddresses::new(
+                &config.server_url,
+                |host, address| {
+                    let mut config = config.clone();
+                    config
+                        .resolve
+                        .retain(|(name, _)| !name.eq_ignore_ascii_case(host));
+                    config.resolve.push((host.to_string(), address));
+                    registry.probe(&config.endpoint_type, &config)
+                },
+            )?)
+        } else {
+            registry.probe(&config.endpoint_type, &config)?
+        };
+        Monitor::builder()
+            .exporter(registry.exporter(&config.exporter, &config)?)
+            .probe(probe)
+            .build()
+    }
+
+    /// Start building a monitor from any probe and exporter implementations
+    pub fn builder() -> MonitorBuilder {
+        MonitorBuilder::default()
+    }
+
+    /// Send a ping, logging rather than propagating failures so the probe result still decides
+    /// the exit code
+    async fn ping(&self, state: PingState, status_code: u16, message: Option<&str>) {
+        if let Err(e) = self.exporter.ping(state, status_code, message).await {
+            error!("{e}");
+        }
     }
 
     pub async fn run(&self) -> i32 {
         // Send start ping
         info!("Sending start ping to Cronitor");
-        self.exporter.ping(PingState::Run, 0, None).await;
+        self.ping(PingState::Run, 0, None).await;
 
         // Probe the endpoint
         match self.probe.probe().await {
             ProbeResult::Success => {
                 info!("Sending success ping to Cronitor");
-                self.exporter.ping(PingState::Complete, 0, None).await;
+                self.ping(PingState::Complete, 0, None).await;
                 info!("SUCCESS: Endpoint responded successfully");
                 0
             }
             ProbeResult::Error(status_code) => {
                 info!("Sending failure ping to Cronitor");
-                self.exporter.ping(PingState::Fail, status_code, None).await;
+                self.ping(PingState::Fail, status_code, None).await;
                 error!("FAILURE: Endpoint failed with HTTP {status_code}");
                 1
             }
             ProbeResult::Timeout => {
                 info!("Sending timeout ping to Cronitor");
-                self.exporter
-                    .ping(PingState::Fail, 124, Some("Request timeout"))
+                self.ping(PingState::Fail, 124, Some("Request timeout"))
                     .await;
                 error!("TIMEOUT: Request timed out");
                 124
             }
             ProbeResult::NetworkError(error) => {
                 info!("Sending failure ping to Cronitor");
-                self.exporter
-                    .ping(PingState::Fail, 1, Some(&format!("Network error: {error}")))
+                self.ping(PingState::Fail, 1, Some(&format!("Network error: {error}")))
                     .await;
                 error!("FAILURE: Network error: {error}");
                 1
             }
+            ProbeResult::InvalidResponse(reason) => {
+                info!("Sending failure ping to Cronitor");
+                self.ping(
+                    PingState::Fail,
+                    1,
+                    Some(&format!("Invalid response: {reason}")),
+                )
+                .await;
+                error!("FAILURE: Invalid response: {reason}");
+                1
+            }
+            ProbeResult::TestFailure(message) => {
+                info!("Sending failure ping to Cronitor");
+                self.ping(PingState::Fail, 1, Some(&message)).await;
+                error!("FAILURE: {message}");
+                1
+            }
+            ProbeResult::Misconfigured(reason) => {
+                // Still a failed run, so a broken deploy doesn't go unnoticed, but with its own
+                // status code and message so it isn't mistaken for the endpoint failing
+                info!("Sending misconfiguration ping to Cronitor");
+                self.ping(
+                    PingState::Fail,
+                    EXIT_MISCONFIGURED as u16,
+                    Some(&format!("Probe misconfigured: {reason}")),
+                )
+                .await;
+                error!("MISCONFIGURED: {reason}");
+                EXIT_MISCONFIGURED
+            }
+        }
+    }
+}
+
+/// Builder for a [`Monitor`], see [`Monitor::builder`]
+#[derive(Default)]
+pub struct MonitorBuilder {
+    exporter: Option<Box<dyn Export>>,
+    probe: Option<Box<dyn Probe>>,
+}
+
+impl MonitorBuilder {
+    /// Set the exporter that probe results are reported to
+    pub fn exporter(mut self, exporter: Box<dyn Export>) -> Self {
+        self.exporter = Some(exporter);
+        self
+    }
+
+    /// Set the probe that is run against the endpoint
+    pub fn probe(mut self, probe: Box<dyn Probe>) -> Self {
+        self.probe = Some(probe);
+        self
+    }
+
+    pub fn build(self) -> Result<Monitor> {
+        Ok(Monitor {
+            exporter: self.exporter.ok_or(Error::InvalidConfig {
+                field: "exporter",
+                reason: "a monitor needs an exporter".to_string(),
+            })?,
+            probe: self.probe.ok_or(Error::InvalidConfig {
+                field: "probe",
+                reason: "a monitor needs a probe".to_string(),
+            })?,
+        })
+    }
+}
+
+#[cfg(feature = "cli")]
+pub mod registry {
+    use std::collections::BTreeMap;
+
+    #[cfg(feature = "cronitor")]
+    use super::exporters::Cronitor;
+    #[cfg(feature = "log")]
+    use super::exporters::Log;
+    #[cfg(feature = "http")]
+    use super::probes::Http;
+    #[cfg(feature = "tls")]
+    use super::probes::Tls;
+    #[cfg(feature = "openai")]
+    use super::probes::{Compare, Determinism, LongContext, OpenAI};
+    #[cfg(feature = "newman")]
+    use super::probes::{Newman, Postman};
+    use super::{Error, Export, Probe, Result, cli::Config, probes::Type as ProbeType};
+
+    /// Builds a probe from the command line configuration
+    pub type ProbeFactory = Box<dyn Fn(&Config) -> Result<Box<dyn Probe>> + Send + Sync>;
+
+    /// Builds an exporter from the command line configuration
+    pub type ExporterFactory = Box<dyn Fn(&Config) -> Result<Box<dyn Export>> + Send + Sync>;
+
+    /// Probe and exporter factories, keyed by the names selected with ENDPOINT_TYPE and EXPORTER.
+    ///
+    /// `Registry::default()` has the built-in probes and exporters registered. Register further
+    /// factories to make custom implementations selectable by name without changing ai-vitals:
+    ///
+    /// ```no_run
+    /// # use ai_vitals::{Monitor, Probe, ProbeResult, cli::Config, registry::Registry};
+    /// struct RpcProbe;
+    ///
+    /// #[async_trait::async_trait]
+    /// impl Probe for RpcProbe {
+    ///     async fn probe(&self) -> ProbeResult {
+    ///         ProbeResult::Success
+    ///     }
+    /// }
+    ///
+    /// let registry = Registry::default().register_probe("rpc", |_config| Ok(Box::new(RpcProbe)));
+    /// let config = Config {
+    ///     endpoint_type: "rpc".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// let monitor = Monitor::with_registry(config, &registry).unwrap();
+    /// ```
+    pub struct Registry {
+        probes: BTreeMap<String, ProbeFactory>,
+        exporters: BTreeMap<String, ExporterFactory>,
+    }
+
+    impl Default for Registry {
+        fn default() -> Self {
+            // Only the probes and exporters enabled by cargo features are registered
+            #[allow(unused_mut)]
+            let mut registry = Registry::empty();
+            for endpoint_type in ProbeType::ALL {
+                registry = match endpoint_type {
+                    #[cfg(feature = "newman")]
+                    ProbeType::Newman => registry
+                        .register_probe(endpoint_type.as_str(), |config| {
+                            Ok(Box::new(Newman::new(config.into())?))
+                        }),
+                    #[cfg(feature = "http")]
+                    ProbeType::Http => registry.register_probe(endpoint_type.as_str(), |config| {
+                        Ok(Box::new(Http::new(config.into())?))
+                    }),
+                    #[cfg(feature = "tls")]
+                    ProbeType::Tls => registry.register_probe(endpoint_type.as_str(), |config| {
+                        Ok(Box::new(Tls::new(config.into())?))
+                    }),
+                    #[cfg(feature = "newman")]
+                    ProbeType::Postman => registry
+                        .register_probe(endpoint_type.as_str(), |config| {
+                            Ok(Box::new(Postman::new(config.into())?))
+                        }),
+                    #[cfg(feature = "openai")]
+                    ProbeType::OpenAIChatCompletion
+                    | ProbeType::OpenAIEmbedding
+                    | ProbeType::OpenAIToolCall
+                    | ProbeType::OpenAIVision
+                    | ProbeType::OpenAITranscription
+                    | ProbeType::OpenAISpeech
+                    | ProbeType::OpenAIImage
+                    | ProbeType::OpenAIModeration => {
+                        registry.register_probe(endpoint_type.as_str(), move |config| {
+                            Ok(Box::new(OpenAI::new(config.openai_config(endpoint_type))?))
+                        })
+                    }
+                    #[cfg(feature = "openai")]
+                    ProbeType::OpenAICompare => registry
+                        .register_probe(endpoint_type.as_str(), |config| {
+                            Ok(Box::new(Compare::new(config.try_into()?)?))
+                        }),
+                    #[cfg(feature = "openai")]
+                    ProbeType::OpenAIDeterminism => registry
+                        .register_probe(endpoint_type.as_str(), |config| {
+                            Ok(Box::new(Determinism::new(config.into())?))
+                        }),
+                    #[cfg(feature = "openai")]
+                    ProbeType::OpenAILongContext => registry
+                        .register_probe(endpoint_type.as_str(), |config| {
+                            Ok(Box::new(LongContext::new(config.into())?))
+                        }),
+                    #[allow(unreachable_patterns)]
+                    _ => registry,
+                };
+            }
+            #[cfg(feature = "cronitor")]
+            {
+                registry = registry.register_exporter("cronitor", |config| {
+                    Ok(Box::new(Cronitor::new(config.try_into()?)?))
+                });
+            }
+            #[cfg(feature = "log")]
+            {
+                registry = registry.register_exporter("log", |_| Ok(Box::new(Log)));
+            }
+            registry
+        }
+    }
+
+    impl Registry {
+        /// A registry without the built-in probes and exporters
+        pub fn empty() -> Self {
+            Registry {
+                probes: BTreeMap::new(),
+                exporters: BTreeMap::new(),
+            }
+        }
+
+        /// Register a probe factory under `name`, replacing any factory already registered there
+        pub fn register_probe<F>(mut self, name: impl Into<String>, factory: F) -> Self
+        where
+            F: Fn(&Config) -> Result<Box<dyn Probe>> + Send + Sync + 'static,
+        {
+            self.probes.insert(name.into(), Box::new(factory));
+            self
+        }
+
+        /// Register an exporter factory under `name`, replacing any factory already registered
+        /// there
+        pub fn register_exporter<F>(mut self, name: impl Into<String>, factory: F) -> Self
+        where
+            F: Fn(&Config) -> Result<Box<dyn Export>> + Send + Sync + 'static,
+        {
+            self.exporters.insert(name.into(), Box::new(factory));
+            self
+        }
+
+        /// Names of the registered probes, in alphabetical order
+        pub fn probe_names(&self) -> impl Iterator<Item = &str> {
+            self.probes.keys().map(String::as_str)
+        }
+
+        /// Names of the registered exporters, in alphabetical order
+        pub fn exporter_names(&self) -> impl Iterator<Item = &str> {
+            self.exporters.keys().map(String::as_str)
+        }
+
+        /// Build the probe registered under `name`
+        pub fn probe(&self, name: &str, config: &Config) -> Result<Box<dyn Probe>> {
+            let factory = self.probes.get(name).ok_or_else(|| Error::UnknownProbe {
+                name: name.to_string(),
+                available: self.probe_names().map(String::from).collect(),
+            })?;
+            factory(config)
+        }
+
+        /// Build the exporter registered under `name`
+        pub fn exporter(&self, name: &str, config: &Config) -> Result<Box<dyn Export>> {
+            let factory = self
+                .exporters
+                .get(name)
+                .ok_or_else(|| Error::UnknownExporter {
+                    name: name.to_string(),
+                    available: self.exporter_names().map(String::from).collect(),
+                })?;
+            factory(config)
         }
     }
 }
 
+#[cfg(feature = "cli")]
 pub mod cli {
-    use clap::Parser;
+    use clap::{Args, Parser, Subcommand};
+    use std::net::{IpAddr, SocketAddr};
+    #[allow(unused_imports)]
+    use std::time::Duration;
 
+    #[cfg(feature = "cronitor")]
+    use super::exporters::CronitorConfig;
+    #[cfg(feature = "newman")]
+    use super::probes::NewmanConfig;
+    #[cfg(feature = "tls")]
+    use super::probes::TlsConfig;
+    #[cfg(feature = "openai")]
     use super::probes::Type as ProbeType;
+    #[cfg(feature = "openai")]
+    use super::probes::{
+        BenchConfig, CompareConfig, DeterminismConfig, LongContextConfig, OpenAIConfig,
+    };
+    #[cfg(feature = "http")]
+    use super::probes::{HttpConfig, join_url};
+    use super::probes::{NetworkOptions, TlsOptions};
+    #[cfg(any(feature = "cronitor", feature = "openai"))]
+    use super::{Error, Result};
 
     /// Configuration for the monitoring tool
     #[derive(Parser, Debug, Clone, PartialEq)]
@@ -131,33 +510,96 @@ pub mod cli {
         author,
         version,
         about,
-        long_about = "Probe an LLM endpoint and report status to Cronitor."
+        long_about = "Probe an LLM endpoint and report status to Cronitor.",
+        subcommand_negates_reqs = true
     )]
     pub struct Config {
+        /// Run a subcommand instead of probing once
+        #[command(subcommand)]
+        pub command: Option<Command>,
+
         /// Base URL for Cronitor, e.g. https://cronitor.link
-        #[arg(long, env = "CRONITOR_BASE_URL")]
-        pub cronitor_base_url: String,
+        #[arg(long, env = "CRONITOR_BASE_URL", required = true)]
+        pub cronitor_base_url: Option<String>,
 
         /// Base URL for Cronitor, e.g. https://cronitor.link
         #[arg(long, env = "CRONITOR_API_KEY")]
         pub cronitor_api_key: Option<String>,
 
         /// Monitor name / code in Cronitor
-        #[arg(long, env = "MONITOR_NAME")]
-        pub monitor_name: String,
+        #[arg(long, env = "MONITOR_NAME", required = true)]
+        pub monitor_name: Option<String>,
 
         /// Base URL of the server to probe, e.g. https://my-openai-proxy
         #[arg(long, env = "SERVER_URL", default_value = "http://localhost:8000/v1")]
         pub server_url: String,
 
-        /// Optional: Probe type to use for the probe. Currently only "llm" is supported.
-        #[arg(long, env = "ENDPOINT_TYPE", default_value = ProbeType::OpenAIChatCompletion)]
-        pub endpoint_type: ProbeType,
+        /// Path the OpenAI-compatible API is mounted at on the server, e.g. /openai/v1.
+        /// Not repeated if SERVER_URL already ends with it.
+        #[arg(long, env = "BASE_PATH", default_value = "/v1")]
+        pub base_path: String,
+
+        /// Optional: override the endpoint path the probe queries, relative to BASE_PATH,
+        /// e.g. /chat/completions
+        #[arg(long, env = "ENDPOINT_PATH")]
+        pub endpoint_path: Option<String>,
+
+        /// Optional: API key sent to the server, as a bearer token or as the `api-key` header
+        /// for Azure OpenAI
+        #[arg(long, env = "API_KEY")]
+        pub api_key: Option<String>,
+
+        /// Optional: Azure OpenAI deployment name. When set, requests go to
+        /// /openai/deployments/{deployment}/... and authenticate with the `api-key` header.
+        #[arg(long, env = "AZURE_DEPLOYMENT")]
+        pub azure_deployment: Option<String>,
+
+        /// Azure OpenAI API version, used when AZURE_DEPLOYMENT is set
+        #[arg(long, env = "AZURE_API_VERSION", default_value = "2024-10-21")]
+        pub azure_api_version: String,
+
+        /// Probe type to use, e.g. openai-chat-completion, openai-embedding, newman or postman.
+        /// Any name registered in the probe registry is accepted.
+        #[arg(long, env = "ENDPOINT_TYPE", default_value = "openai-chat-completion")]
+        pub endpoint_type: String,
+
+        /// Exporter to report results to. Any name registered in the exporter registry is
+        /// accepted.
+        #[arg(long, env = "EXPORTER", default_value = "cronitor")]
+        pub exporter: String,
 
         /// Name of the model to query
         #[arg(long, env = "MODEL_NAME", default_value = "gpt-4")]
         pub model_name: String,
 
+        /// Optional: keyword the model's answer must contain, e.g. a word describing the
+        /// bundled test image for the vision probe. Matched case-insensitively.
+        #[arg(long, env = "EXPECTED_KEYWORD")]
+        pub expected_keyword: Option<String>,
+
+        /// Optional: WAV file to upload with the transcription probe, instead of the bundled tone.
+        /// Use a speech recording together with EXPECTED_KEYWORD to check the transcript.
+        #[arg(long, env = "AUDIO_PATH")]
+        pub audio_path: Option<String>,
+
+        /// Voice to request from the text-to-speech probe
+        #[arg(long, env = "VOICE", default_value = "alloy")]
+        pub voice: String,
+
+        /// Smallest audio body in bytes the text-to-speech probe accepts. The WAV header must
+        /// also report a non-zero duration.
+        #[arg(long, env = "MIN_AUDIO_BYTES", default_value_t = 1024)]
+        pub min_audio_bytes: usize,
+
+        /// Size of the image requested by the image generation probe, as WIDTHxHEIGHT
+        #[arg(long, env = "IMAGE_SIZE", default_value = "256x256")]
+        pub image_size: String,
+
+        /// Require chat and embedding responses to report plausible token usage: prompt tokens
+        /// counted, completion tokens within max_tokens and a total that adds up
+        #[arg(long, env = "CHECK_USAGE")]
+        pub check_usage: bool,
+
         /// Environment descriptor (defaults to "production")
         #[arg(long, env = "APP_ENV", default_value = "production")]
         pub env: String,
@@ -166,6 +608,46 @@ pub mod cli {
         #[arg(long, env = "TIMEOUT_SECONDS", default_value_t = 10)]
         pub timeout_seconds: u64,
 
+        /// Optional: PEM file of CA certificates the probes trust as well as the public roots,
+        /// e.g. for endpoints behind a private CA
+        #[arg(long, env = "CA_CERT_PATH")]
+        pub ca_cert_path: Option<String>,
+
+        /// Optional: PEM file of the client certificate the probes present for mutual TLS. It
+        /// may also hold the private key.
+        #[arg(long, env = "CLIENT_CERT_PATH")]
+        pub client_cert_path: Option<String>,
+
+        /// Optional: PEM file of the client certificate's private key
+        #[arg(long, env = "CLIENT_KEY_PATH")]
+        pub client_key_path: Option<String>,
+
+        /// Skip verifying the server certificate in the probes. Only meant for lab
+        /// environments; the TLS probe then only checks the certificate's expiry.
+        #[arg(long, env = "INSECURE")]
+        pub insecure: bool,
+
+        /// Optional: proxy for the probes' requests, e.g. http://proxy:3128 or
+        /// socks5h://proxy:1080, instead of HTTP_PROXY, HTTPS_PROXY and ALL_PROXY. Exporters
+        /// keep using those.
+        #[arg(long, env = "PROBE_PROXY")]
+        pub probe_proxy: Option<String>,
+
+        /// Optional: comma separated hosts the probes reach without a proxy, instead of
+        /// NO_PROXY. Use * to bypass the proxy for every probe request.
+        #[arg(long, env = "PROBE_NO_PROXY")]
+        pub probe_no_proxy: Option<String>,
+
+        /// Optional: comma separated HOST:PORT:ADDRESS overrides of the probes' DNS lookups,
+        /// e.g. api.example.com:443:10.0.3.7 to probe a single replica
+        #[arg(long, env = "RESOLVE", value_delimiter = ',', value_parser = parse_resolve)]
+        pub resolve: Vec<(String, SocketAddr)>,
+
+        /// Resolve SERVER_URL's host and run the probe against each of its addresses, keeping
+        /// the host name for the Host header and TLS. Fails if any address fails.
+        #[arg(long, env = "PROBE_ALL_ADDRESSES")]
+        pub probe_all_addresses: bool,
+
         /// The below all require an API key to be set to take effect.
 
         /// minFreqRequiredMins catches inactive alerts - if an alert starts but never completes,
@@ -209,19 +691,209 @@ pub mod cli {
         // Delay request by N milliseconds to avoid hitting rate limits
         #[arg(long, env = "REQUEST_DELAY_MILLISECONDS", default_value = None)]
         pub request_delay_milliseconds: Option<u64>,
+
+        // Deadline in seconds for the whole newman run, after which it is killed and the
+        // probe reports a timeout
+        #[arg(long, env = "NEWMAN_TIMEOUT_SECONDS", default_value_t = 120)]
+        pub newman_timeout_seconds: u64,
+
+        // newman executable to run
+        #[arg(long, env = "NEWMAN_PATH", default_value = "newman")]
+        pub newman_path: String,
+
+        // CSV or JSON data file, running the collection once per row
+        #[arg(long, env = "ITERATION_DATA_PATH", default_value = None)]
+        pub iteration_data_path: Option<String>,
+
+        // Comma separated folders or requests to run instead of the whole collection
+        #[arg(long, env = "NEWMAN_FOLDERS", value_delimiter = ',')]
+        pub newman_folders: Vec<String>,
+
+        // Comma separated KEY=VALUE overrides of the environment file, so that secrets can come
+        // from the process environment
+        #[arg(long, env = "NEWMAN_ENV_VARS", value_delimiter = ',', value_parser = parse_key_value)]
+        pub newman_env_vars: Vec<(String, String)>,
+
+        // Comma separated KEY=VALUE global variables
+        #[arg(long, env = "NEWMAN_GLOBAL_VARS", value_delimiter = ',', value_parser = parse_key_value)]
+        pub newman_global_vars: Vec<(String, String)>,
+
+        // Stop the run at the first failed request or test
+        #[arg(long, env = "NEWMAN_BAIL")]
+        pub newman_bail: bool,
+
+        /// HTTP-specific options, requesting SERVER_URL joined with ENDPOINT_PATH
+        // Method of the request
+        #[arg(long, env = "HTTP_METHOD", default_value = "GET")]
+        pub http_method: String,
+
+        // Comma separated NAME=VALUE headers sent with the request. API_KEY is sent as a
+        // bearer token
+        #[arg(long, env = "HTTP_HEADERS", value_delimiter = ',', value_parser = parse_key_value)]
+        pub http_headers: Vec<(String, String)>,
+
+        // Body sent with the request
+        #[arg(long, env = "HTTP_BODY", default_value = None)]
+        pub http_body: Option<String>,
+
+        // Comma separated status codes accepted, any 2xx if unset
+        #[arg(long, env = "EXPECTED_STATUS", value_delimiter = ',')]
+        pub expected_status: Vec<u16>,
+
+        // Comma separated NAME=REGEX response headers that must be present and match
+        #[arg(long, env = "EXPECTED_HEADERS", value_delimiter = ',', value_parser = parse_key_value)]
+        pub expected_headers: Vec<(String, String)>,
+
+        // Slowest response accepted in milliseconds
+        #[arg(long, env = "MAX_LATENCY_MILLISECONDS", default_value = None)]
+        pub max_latency_milliseconds: Option<u64>,
+
+        // Regex the response body must match
+        #[arg(long, env = "BODY_REGEX", default_value = None)]
+        pub body_regex: Option<String>,
+
+        // Comma separated JSONPATH=VALUE pairs the JSON response body must contain, e.g.
+        // $.status=ok. Values are parsed as JSON, or compared as strings if they aren't JSON
+        #[arg(long, env = "EXPECTED_JSON", value_delimiter = ',', value_parser = parse_key_value)]
+        pub expected_json: Vec<(String, String)>,
+
+        /// TLS-specific options
+        // Fail when SERVER_URL's certificate expires within this many days
+        #[arg(long, env = "TLS_EXPIRY_WARNING_DAYS", default_value_t = 14)]
+        pub tls_expiry_warning_days: u32,
+
+        /// Comparison-specific options, sending the same request to SERVER_URL and
+        /// CANDIDATE_SERVER_URL
+        // Base URL of the endpoint compared with SERVER_URL, e.g. a canary
+        #[arg(long, env = "CANDIDATE_SERVER_URL", default_value = None)]
+        pub candidate_server_url: Option<String>,
+
+        // Request compared, openai-chat-completion or openai-embedding
+        #[arg(
+            long,
+            env = "COMPARE_ENDPOINT_TYPE",
+            default_value = "openai-chat-completion"
+        )]
+        pub compare_endpoint_type: String,
+
+        // Largest ratio of the candidate's latency to SERVER_URL's
+        #[arg(long, env = "MAX_LATENCY_RATIO", default_value_t = 2.0)]
+        pub max_latency_ratio: f64,
+
+        // Smallest cosine similarity between the two embeddings
+        #[arg(long, env = "MIN_EMBEDDING_SIMILARITY", default_value_t = 0.99)]
+        pub min_embedding_similarity: f64,
+
+        // Seed sent with the deterministic chat completion requests of the comparison and
+        // determinism probes
+        #[arg(long, env = "SEED", default_value_t = 42)]
+        pub seed: u64,
+
+        /// Determinism-specific options, sending the same chat completion request several times
+        // Number of identical requests sent
+        #[arg(long, env = "DETERMINISM_RUNS", default_value_t = 5)]
+        pub determinism_runs: usize,
+
+        // Smallest share of the requests that must return the most common output, 1 requiring
+        // identical outputs
+        #[arg(long, env = "MIN_AGREEMENT", default_value_t = 1.0)]
+        pub min_agreement: f64,
+
+        /// Long-context-specific options, hiding a passphrase in a prompt filling the context
+        /// window
+        // Context window the model should accept, in tokens
+        #[arg(long, env = "CONTEXT_TOKENS", default_value_t = 8192)]
+        pub context_tokens: usize,
+
+        // Path of a tokenizer endpoint on SERVER_URL's host, e.g. /tokenize on vLLM, to size
+        // the prompt exactly and check the server's max_model_len. Estimated without it
+        #[arg(long, env = "TOKENIZE_PATH", default_value = None)]
+        pub tokenize_path: Option<String>,
+
+        // Position of the passphrase in the prompt, from 0 (start) to 1 (end)
+        #[arg(long, env = "NEEDLE_DEPTH", default_value_t = 0.5)]
+        pub needle_depth: f64,
+    }
+
+    /// Subcommands of the monitoring tool. The probe options above still apply, and must come
+    /// before the subcommand when given as flags.
+    #[derive(Subcommand, Debug, Clone, PartialEq)]
+    pub enum Command {
+        /// Drive concurrent clients through the OpenAI probe's request, with its payload,
+        /// authentication and checks, and report throughput, latency percentiles and errors
+        Bench(BenchArgs),
+    }
+
+    /// Options of the `bench` subcommand
+    #[derive(Args, Debug, Clone, PartialEq)]
+    pub struct BenchArgs {
+        /// Number of clients sending requests back to back
+        #[arg(long, env = "BENCH_CONCURRENCY", default_value_t = 8)]
+        pub concurrency: usize,
+
+        /// How long the clients keep sending requests, in seconds
+        #[arg(long, env = "BENCH_DURATION_SECONDS", default_value_t = 30)]
+        pub duration_seconds: u64,
+
+        /// Optional: replace the probe request's max_tokens, e.g. to measure generation
+        /// throughput
+        #[arg(long, env = "BENCH_MAX_TOKENS")]
+        pub max_tokens: Option<u32>,
+    }
+
+    /// Parse a HOST:PORT:ADDRESS DNS override, with IPv6 addresses optionally in brackets
+    fn parse_resolve(entry: &str) -> Result<(String, SocketAddr), String> {
+        let invalid = || format!("expected HOST:PORT:ADDRESS, got {entry}");
+        let (host, rest) = entry.split_once(':').ok_or_else(invalid)?;
+        let (port, address) = rest.split_once(':').ok_or_else(invalid)?;
+        let port = port.parse().map_err(|_| invalid())?;
+        let address: IpAddr = address
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse()
+            .map_err(|_| invalid())?;
+        Ok((host.to_string(), SocketAddr::new(address, port)))
+    }
+
+    /// Parse a KEY=VALUE pair, splitting at the first `=`
+    fn parse_key_value(pair: &str) -> Result<(String, String), String> {
+        pair.split_once('=')
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .ok_or_else(|| format!("expected KEY=VALUE, got {pair}"))
     }
 
     impl Default for Config {
         fn default() -> Self {
             Config {
-                cronitor_base_url: "https://cronitor.link".to_string(),
+                command: None,
+                cronitor_base_url: Some("https://cronitor.link".to_string()),
                 cronitor_api_key: None,
-                monitor_name: "test-monitor".to_string(),
+                monitor_name: Some("test-monitor".to_string()),
                 server_url: "https://api.openai.com".to_string(),
-                endpoint_type: ProbeType::OpenAIChatCompletion,
+                base_path: "/v1".to_string(),
+                endpoint_path: None,
+                api_key: None,
+                azure_deployment: None,
+                azure_api_version: "2024-10-21".to_string(),
+                endpoint_type: "openai-chat-completion".to_string(),
+                exporter: "cronitor".to_string(),
                 model_name: "gpt-4".to_string(),
+                expected_keyword: None,
+                audio_path: None,
+                voice: "alloy".to_string(),
+                min_audio_bytes: 1024,
+                image_size: "256x256".to_string(),
+                check_usage: false,
                 env: "test".to_string(),
                 timeout_seconds: 10,
+                ca_cert_path: None,
+                client_cert_path: None,
+                client_key_path: None,
+                insecure: false,
+                probe_proxy: None,
+                probe_no_proxy: None,
+                resolve: Vec::new(),
+                probe_all_addresses: false,
                 schedule: None,
                 realert_interval: Some(9999),
                 consecutive_failures: Some(1),
@@ -231,78 +903,467 @@ pub mod cli {
                 collection_path: "collection.json".to_string(),
                 environment_path: None,
                 request_delay_milliseconds: None,
+                newman_timeout_seconds: 120,
+                newman_path: "newman".to_string(),
+                iteration_data_path: None,
+                newman_folders: Vec::new(),
+                newman_env_vars: Vec::new(),
+                newman_global_vars: Vec::new(),
+                newman_bail: false,
+                http_method: "GET".to_string(),
+                http_headers: Vec::new(),
+                http_body: None,
+                expected_status: Vec::new(),
+                expected_headers: Vec::new(),
+                max_latency_milliseconds: None,
+                body_regex: None,
+                expected_json: Vec::new(),
+                tls_expiry_warning_days: 14,
+                candidate_server_url: None,
+                compare_endpoint_type: "openai-chat-completion".to_string(),
+                max_latency_ratio: 2.0,
+                min_embedding_similarity: 0.99,
+                seed: 42,
+                determinism_runs: 5,
+                min_agreement: 1.0,
+                context_tokens: 8192,
+                tokenize_path: None,
+                needle_depth: 0.5,
             }
         }
     }
-}
 
-pub mod exporters {
-    use anyhow::{Context, Result};
-    use chrono::Utc;
-    use hostname::get;
-    use reqwest::Client;
-    use serde_json::json;
-    use std::time::Duration;
-    use tracing::{error, info};
+    impl From<&Config> for TlsOptions {
+        fn from(config: &Config) -> Self {
+            TlsOptions {
+                ca_cert_path: config.ca_cert_path.clone(),
+                client_cert_path: config.client_cert_path.clone(),
+                client_key_path: config.client_key_path.clone(),
+                insecure: config.insecure,
+            }
+        }
+    }
+
+    impl From<&Config> for NetworkOptions {
+        fn from(config: &Config) -> Self {
+            NetworkOptions {
+                proxy: config.probe_proxy.clone(),
+                no_proxy: config.probe_no_proxy.clone(),
+                resolve: config.resolve.clone(),
+            }
+        }
+    }
+
+    /// Fails without CRONITOR_BASE_URL or MONITOR_NAME, which are only optional for subcommands
+    #[cfg(feature = "cronitor")]
+    impl TryFrom<&Config> for CronitorConfig {
+        type Error = Error;
+
+        fn try_from(config: &Config) -> Result<Self> {
+            let required = |value: &Option<String>, field, name| {
+                value.clone().ok_or_else(|| Error::InvalidConfig {
+                    field,
+                    reason: format!("{name} is required by the cronitor exporter"),
+                })
+            };
+            Ok(CronitorConfig {
+                base_url: required(
+                    &config.cronitor_base_url,
+                    "cronitor base URL",
+                    "CRONITOR_BASE_URL",
+                )?,
+                api_key: config.cronitor_api_key.clone(),
+                monitor_name: required(&config.monitor_name, "monitor name", "MONITOR_NAME")?,
+                env: config.env.clone(),
+                timeout: Duration::from_secs(config.timeout_seconds),
+                min_success_freq: config.min_success_freq,
+                schedule: config.schedule.clone(),
+                realert_interval: config.realert_interval,
+                consecutive_failures: config.consecutive_failures,
+                consecutive_missing: config.consecutive_missing,
+                monitor_group: config.monitor_group.clone(),
+            })
+        }
+    }
 
-    use crate::Export;
+    #[cfg(feature = "openai")]
+    impl Config {
+        /// Settings of an OpenAI probe for the given endpoint type, whatever ENDPOINT_TYPE is
+        pub(crate) fn openai_config(&self, endpoint_type: ProbeType) -> OpenAIConfig {
+            OpenAIConfig {
+                server_url: self.server_url.clone(),
+                base_path: self.base_path.clone(),
+                endpoint_path: self.endpoint_path.clone(),
+                api_key: self.api_key.clone(),
+                azure_deployment: self.azure_deployment.clone(),
+                azure_api_version: self.azure_api_version.clone(),
+                endpoint_type,
+                model_name: self.model_name.clone(),
+                timeout: Duration::from_secs(self.timeout_seconds),
+                expected_keyword: self.expected_keyword.clone(),
+                audio_path: self.audio_path.clone(),
+                voice: self.voice.clone(),
+                min_audio_bytes: self.min_audio_bytes,
+                image_size: self.image_size.clone(),
+                check_usage: self.check_usage,
+                tls: self.into(),
+                network: self.into(),
+            }
+        }
+    }
 
-    use super::{PingState, cli::Config};
+    /// Fails unless ENDPOINT_TYPE names a built-in probe type
+    #[cfg(feature = "openai")]
+    impl TryFrom<&Config> for OpenAIConfig {
+        type Error = Error;
 
-    /// Cronitor client to send pings
-    pub struct Cronitor {
-        config: Config,
-        client: Client,
-        host: String,
-        series_id: String,
+        fn try_from(config: &Config) -> Result<Self> {
+            Ok(config.openai_config(config.endpoint_type.parse()?))
+        }
     }
 
-    /// Cronitor exporter implementation
-    #[async_trait::async_trait]
-    impl Export for Cronitor {
-        fn new(config: Config) -> Result<Self> {
-            let client = Client::builder()
-                .timeout(Duration::from_secs(config.timeout_seconds))
-                .build()
-                .context("building reqwest client")?;
-
-            let host = get().unwrap_or_default().to_string_lossy().into_owned();
-            let series_id = format!("{}-{}", Utc::now().timestamp(), std::process::id());
-
-            info!("Starting job with series ID: {series_id}");
-
-            Ok(Cronitor {
-                config,
-                client,
-                host,
-                series_id,
+    /// Fails unless COMPARE_ENDPOINT_TYPE names a built-in probe type
+    #[cfg(feature = "openai")]
+    impl TryFrom<&Config> for CompareConfig {
+        type Error = Error;
+
+        fn try_from(config: &Config) -> Result<Self> {
+            let baseline = config.openai_config(config.compare_endpoint_type.parse()?);
+            Ok(CompareConfig {
+                baseline,
+                candidate_url: config.candidate_server_url.clone().unwrap_or_default(),
+                seed: config.seed,
+                max_latency_ratio: config.max_latency_ratio,
+                min_similarity: config.min_embedding_similarity,
             })
         }
+    }
 
-        async fn ping(&self, state: PingState, status_code: u16, message: Option<&str>) {
-            let url = self.build_ping_url(state, status_code, message);
+    /// Fails unless ENDPOINT_TYPE names a built-in probe type
+    #[cfg(feature = "openai")]
+    impl TryFrom<&Config> for BenchConfig {
+        type Error = Error;
+
+        fn try_from(config: &Config) -> Result<Self> {
+            let defaults = BenchConfig::new(config.try_into()?);
+            Ok(match &config.command {
+                Some(Command::Bench(args)) => BenchConfig {
+                    concurrency: args.concurrency,
+                    duration: Duration::from_secs(args.duration_seconds),
+                    max_tokens: args.max_tokens,
+                    ..defaults
+                },
+                None => defaults,
+            })
+        }
+    }
 
-            match self.client.get(&url).send().await {
-                Ok(resp) if resp.status().is_success() => {
-                    // success: optionally peek at body for debugging
-                    info!("Cronitor ping OK");
-                }
-                Ok(resp) => {
-                    // non-2xx: log status + response body (often has the reason)
-                    let status = resp.status();
-                    let body = resp.text().await.unwrap_or_default(); // consumes resp
-                    error!("Cronitor ping non-2xx {status}: {body}");
+    #[cfg(feature = "openai")]
+    impl From<&Config> for DeterminismConfig {
+        fn from(config: &Config) -> Self {
+            DeterminismConfig {
+                chat: config.openai_config(ProbeType::OpenAIChatCompletion),
+                runs: config.determinism_runs,
+                seed: config.seed,
+                min_agreement: config.min_agreement,
+            }
+        }
+    }
+
+    #[cfg(feature = "openai")]
+    impl From<&Config> for LongContextConfig {
+        fn from(config: &Config) -> Self {
+            LongContextConfig {
+                chat: config.openai_config(ProbeType::OpenAIChatCompletion),
+                context_tokens: config.context_tokens,
+                tokenize_path: config.tokenize_path.clone(),
+                needle_depth: config.needle_depth,
+            }
+        }
+    }
+
+    #[cfg(feature = "http")]
+    impl From<&Config> for HttpConfig {
+        fn from(config: &Config) -> Self {
+            // Health and metrics endpoints usually sit outside the API's base path
+            let url = join_url(
+                &config.server_url,
+                "",
+                config.endpoint_path.as_deref().unwrap_or_default(),
+            );
+            let api_key = config
+                .api_key
+                .iter()
+                .map(|api_key| ("Authorization".to_string(), format!("Bearer {api_key}")));
+
+            HttpConfig {
+                url,
+                method: config.http_method.clone(),
+                headers: api_key.chain(config.http_headers.clone()).collect(),
+                body: config.http_body.clone(),
+                timeout: Duration::from_secs(config.timeout_seconds),
+                expected_status: config.expected_status.clone(),
+                expected_headers: config.expected_headers.clone(),
+                max_latency: config.max_latency_milliseconds.map(Duration::from_millis),
+                body_regex: config.body_regex.clone(),
+                expected_json: config
+                    .expected_json
+                    .iter()
+                    .map(|(path, value)| {
+                        let value = serde_json::from_str(value)
+                            .unwrap_or_else(|_| serde_json::Value::String(value.clone()));
+                        (path.clone(), value)
+                    })
+                    .collect(),
+                tls: config.into(),
+                network: config.into(),
+            }
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    impl From<&Config> for TlsConfig {
+        fn from(config: &Config) -> Self {
+            TlsConfig {
+                server_url: config.server_url.clone(),
+                expiry_warning_days: config.tls_expiry_warning_days,
+                timeout: Duration::from_secs(config.timeout_seconds),
+                tls: config.into(),
+                network: config.into(),
+            }
+        }
+    }
+
+    #[cfg(feature = "newman")]
+    impl From<&Config> for NewmanConfig {
+        fn from(config: &Config) -> Self {
+            NewmanConfig {
+                collection_path: config.collection_path.clone(),
+                environment_path: config.environment_path.clone(),
+                request_delay_milliseconds: config.request_delay_milliseconds,
+                timeout: Duration::from_secs(config.timeout_seconds),
+                run_timeout: Duration::from_secs(config.newman_timeout_seconds),
+                newman_path: config.newman_path.clone(),
+                iteration_data_path: config.iteration_data_path.clone(),
+                folders: config.newman_folders.clone(),
+                env_vars: config.newman_env_vars.clone(),
+                global_vars: config.newman_global_vars.clone(),
+                bail: config.newman_bail,
+                tls: config.into(),
+                network: config.into(),
+            }
+        }
+    }
+}
+
+pub mod exporters {
+    #[cfg(feature = "cronitor")]
+    pub use cronitor::{Cronitor, CronitorConfig};
+    #[cfg(feature = "log")]
+    pub use log::Log;
+
+    #[cfg(feature = "cronitor")]
+    mod cronitor {
+        use chrono::Utc;
+        use hostname::get;
+        use reqwest::Client;
+        use serde_json::json;
+        use std::time::Duration;
+        use tracing::{error, info};
+
+        use crate::{Error, Export, PingState, Result};
+
+        /// Configuration for the [`Cronitor`] exporter
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct CronitorConfig {
+            /// Base URL for Cronitor pings, e.g. https://cronitor.link/p/your-key
+            pub base_url: String,
+            /// API key used to update the monitor definition. The options below need it to take effect.
+            pub api_key: Option<String>,
+            /// Monitor name / code in Cronitor
+            pub monitor_name: String,
+            /// Environment descriptor sent with each ping
+            pub env: String,
+            /// Request timeout, also used to derive the monitor's duration assertion
+            pub timeout: Duration,
+            /// Require a successful ping at least once per this many minutes
+            pub min_success_freq: Option<u8>,
+            /// Schedule to display in Cronitor
+            pub schedule: Option<String>,
+            /// Hours between repeated alerts for a failing monitor
+            pub realert_interval: Option<u16>,
+            /// Failed pings needed to trigger an alert
+            pub consecutive_failures: Option<u8>,
+            /// Missing pings needed to trigger an alert, requires a schedule
+            pub consecutive_missing: Option<u8>,
+            /// Group to put the monitor in
+            pub monitor_group: Option<String>,
+        }
+
+        impl Default for CronitorConfig {
+            fn default() -> Self {
+                CronitorConfig {
+                    base_url: "https://cronitor.link".to_string(),
+                    api_key: None,
+                    monitor_name: "ai-vitals".to_string(),
+                    env: "production".to_string(),
+                    timeout: Duration::from_secs(10),
+                    min_success_freq: None,
+                    schedule: None,
+                    realert_interval: None,
+                    consecutive_failures: None,
+                    consecutive_missing: None,
+                    monitor_group: None,
                 }
-                Err(e) => {
-                    // request failed before a response was received
-                    error!("Failed to send ping to Cronitor: {e}");
+            }
+        }
+
+        impl CronitorConfig {
+            pub fn new(base_url: impl Into<String>, monitor_name: impl Into<String>) -> Self {
+                CronitorConfig {
+                    base_url: base_url.into(),
+                    monitor_name: monitor_name.into(),
+                    ..Default::default()
                 }
             }
 
-            if state == PingState::Run {
-                // The above handles the ping. We also want to update the created monitor if we can.
+            pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+                self.api_key = Some(api_key.into());
+                self
+            }
+
+            pub fn env(mut self, env: impl Into<String>) -> Self {
+                self.env = env.into();
+                self
+            }
+
+            pub fn timeout(mut self, timeout: Duration) -> Self {
+                self.timeout = timeout;
+                self
+            }
+
+            pub fn min_success_freq(mut self, minutes: u8) -> Self {
+                self.min_success_freq = Some(minutes);
+                self
+            }
+
+            pub fn schedule(mut self, schedule: impl Into<String>) -> Self {
+                self.schedule = Some(schedule.into());
+                self
+            }
+
+            pub fn realert_interval(mut self, hours: u16) -> Self {
+                self.realert_interval = Some(hours);
+                self
+            }
+
+            pub fn consecutive_failures(mut self, failures: u8) -> Self {
+                self.consecutive_failures = Some(failures);
+                self
+            }
+
+            pub fn consecutive_missing(mut self, missing: u8) -> Self {
+                self.consecutive_missing = Some(missing);
+                self
+            }
+
+            pub fn monitor_group(mut self, group: impl Into<String>) -> Self {
+                self.monitor_group = Some(group.into());
+                self
+            }
+
+            pub fn build(self) -> Result<Cronitor> {
+                Cronitor::new(self)
+            }
+        }
+
+        /// Cronitor client to send pings
+        pub struct Cronitor {
+            config: CronitorConfig,
+            client: Client,
+            host: String,
+            series_id: String,
+        }
+
+        impl Cronitor {
+            pub fn new(config: CronitorConfig) -> Result<Self> {
+                let client = Client::builder()
+                    .timeout(config.timeout)
+                    .build()
+                    .map_err(Error::HttpClient)?;
+
+                let host = get().unwrap_or_default().to_string_lossy().into_owned();
+                let series_id = format!("{}-{}", Utc::now().timestamp(), std::process::id());
+
+                info!("Starting job with series ID: {series_id}");
+
+                Ok(Cronitor {
+                    config,
+                    client,
+                    host,
+                    series_id,
+                })
+            }
+
+            /// Start building a Cronitor exporter, see [`CronitorConfig`]
+            pub fn builder(
+                base_url: impl Into<String>,
+                monitor_name: impl Into<String>,
+            ) -> CronitorConfig {
+                CronitorConfig::new(base_url, monitor_name)
+            }
+        }
+
+        /// Cronitor exporter implementation
+        #[async_trait::async_trait]
+        impl Export for Cronitor {
+            async fn ping(
+                &self,
+                state: PingState,
+                status_code: u16,
+                message: Option<&str>,
+            ) -> Result<()> {
+                let url = self.build_ping_url(state, status_code, message);
+
+                let result = match self.client.get(&url).send().await {
+                    Ok(resp) if resp.status().is_success() => {
+                        // success: optionally peek at body for debugging
+                        info!("Cronitor ping OK");
+                        Ok(())
+                    }
+                    Ok(resp) => {
+                        // non-2xx: report status + response body (often has the reason)
+                        let status = resp.status();
+                        let body = resp.text().await.unwrap_or_default(); // consumes resp
+                        Err(Error::Export {
+                            exporter: "Cronitor",
+                            reason: format!("ping non-2xx {status}: {body}"),
+                        })
+                    }
+                    Err(e) => {
+                        // request failed before a response was received
+                        Err(Error::Export {
+                            exporter: "Cronitor",
+                            reason: format!("failed to send ping: {e}"),
+                        })
+                    }
+                };
+
+                if state == PingState::Run {
+                    // The above handles the ping. We also want to update the created monitor if we can.
+                    self.enrich_monitor().await;
+                }
+
+                result
+            }
+        }
 
-                let Some(api_key) = self.config.cronitor_api_key.as_deref() else {
+        /// Internal methods for Cronitor
+        impl Cronitor {
+            /// Update the monitor definition in Cronitor. This is best effort, so failures are only
+            /// logged.
+            async fn enrich_monitor(&self) {
+                let Some(api_key) = self.config.api_key.as_deref() else {
                     info!("No api key, skipping monitor enrichment");
                     return; // no key => skip update
                 };
@@ -332,705 +1393,8762 @@ pub mod exporters {
                     }
                 }
             }
-        }
-    }
 
-    /// Internal methods for Cronitor
-    impl Cronitor {
-        pub fn build_ping_url(
-            &self,
-            state: PingState,
-            status_code: u16,
-            message: Option<&str>,
-        ) -> String {
-            let mut url = format!(
-                "{}/{}?state={}&series={}&status_code={}&env={}&host={}",
-                self.config.cronitor_base_url,
-                self.config.monitor_name,
-                state.as_str(),
-                self.series_id,
-                status_code,
-                self.config.env,
-                self.host
-            );
-            if let Some(msg) = message {
-                url.push_str("&message=");
-                url.push_str(&urlencoding::encode(msg));
+            pub fn build_ping_url(
+                &self,
+                state: PingState,
+                status_code: u16,
+                message: Option<&str>,
+            ) -> String {
+                let mut url = format!(
+                    "{}/{}?state={}&series={}&status_code={}&env={}&host={}",
+                    self.config.base_url,
+                    self.config.monitor_name,
+                    state.as_str(),
+                    self.series_id,
+                    status_code,
+                    self.config.env,
+                    self.host
+                );
+                if let Some(msg) = message {
+                    url.push_str("&message=");
+                    url.push_str(&urlencoding::encode(msg));
+                }
+                url
             }
-            url
-        }
 
-        pub fn get_monitor_update_payload(&self) -> serde_json::Value {
-            let mut monitor = serde_json::Map::new();
-            monitor.insert("type".into(), json!("job"));
-            monitor.insert("key".into(), json!(self.config.monitor_name));
+            pub fn get_monitor_update_payload(&self) -> serde_json::Value {
+                let mut monitor = serde_json::Map::new();
+                monitor.insert("type".into(), json!("job"));
+                monitor.insert("key".into(), json!(self.config.monitor_name));
 
-            if let Some(consecutive_failures) = self.config.consecutive_failures {
-                monitor.insert("failure_tolerance".into(), json!(consecutive_failures));
-            }
+                if let Some(consecutive_failures) = self.config.consecutive_failures {
+                    monitor.insert("failure_tolerance".into(), json!(consecutive_failures));
+                }
 
-            if let Some(schedule) = self.config.schedule.clone() {
-                monitor.insert("schedule".into(), json!(schedule));
-            }
+                if let Some(schedule) = self.config.schedule.clone() {
+                    monitor.insert("schedule".into(), json!(schedule));
+                }
 
-            if let Some(realert_interval) = self.config.realert_interval {
-                monitor.insert("realert_interval".into(), json!(realert_interval));
-            }
+                if let Some(realert_interval) = self.config.realert_interval {
+                    monitor.insert("realert_interval".into(), json!(realert_interval));
+                }
 
-            if let (Some(consecutive_missing), Some(_)) = (
-                self.config.consecutive_missing,
-                self.config.schedule.clone(),
-            ) {
-                monitor.insert("schedule_tolerance".into(), json!(consecutive_missing));
-            }
+                if let (Some(consecutive_missing), Some(_)) = (
+                    self.config.consecutive_missing,
+                    self.config.schedule.clone(),
+                ) {
+                    monitor.insert("schedule_tolerance".into(), json!(consecutive_missing));
+                }
+
+                if let Some(group) = self.config.monitor_group.clone() {
+                    monitor.insert("group".into(), json!(group));
+                }
+
+                // always include the duration assertion
+                let mut assertions: Vec<String> = vec![format!(
+                    "metric.duration < {}s",
+                    self.config.timeout.as_secs() * 2
+                )];
+
+                if let Some(min_success_freq) = self.config.min_success_freq {
+                    assertions.push(format!("job.completes < {min_success_freq} minute"));
+                }
+                monitor.insert("assertions".into(), json!(assertions));
 
-            if let Some(group) = self.config.monitor_group.clone() {
-                monitor.insert("group".into(), json!(group));
+                json!({ "monitors": [serde_json::Value::Object(monitor)] })
             }
+        }
 
-            // always include the duration assertion
-            let mut assertions: Vec<String> = vec![format!(
-                "metric.duration < {}s",
-                self.config.timeout_seconds * 2
-            )];
+        #[cfg(test)]
+        mod tests {
+            use super::*;
 
-            if let Some(min_success_freq) = self.config.min_success_freq {
-                assertions.push(format!("job.completes < {min_success_freq} minute"));
+            fn test_config() -> CronitorConfig {
+                CronitorConfig::new("https://cronitor.link", "test-monitor").env("test")
             }
-            monitor.insert("assertions".into(), json!(assertions));
 
-            json!({ "monitors": [serde_json::Value::Object(monitor)] })
-        }
-    }
+            #[test]
+            fn test_cronitor_client_creation() {
+                let config = test_config();
+                let client = Cronitor::new(config);
+                assert!(client.is_ok());
+            }
 
-    #[cfg(test)]
-    mod tests {
-        use super::*;
+            #[test]
+            fn test_cronitor_ping_url_construction_without_message() {
+                let config = test_config();
+                let client = Cronitor::new(config).unwrap();
 
-        #[test]
-        fn test_cronitor_client_creation() {
-            let config = Config::default();
-            let client = Cronitor::new(config);
-            assert!(client.is_ok());
-        }
+                let url = client.build_ping_url(PingState::Run, 0, None);
+
+                assert!(url.contains("https://cronitor.link/test-monitor"));
+                assert!(url.contains("state=run"));
+                assert!(url.contains("status_code=0"));
+                assert!(url.contains("env=test"));
+                assert!(url.contains("series="));
+                assert!(url.contains("host="));
+                assert!(!url.contains("message="));
+            }
 
-        #[test]
-        fn test_cronitor_ping_url_construction_without_message() {
-            let config = Config::default();
-            let client = Cronitor::new(config).unwrap();
+            #[test]
+            fn test_cronitor_ping_url_construction_with_message() {
+                let config = test_config();
+                let client = Cronitor::new(config).unwrap();
 
-            let url = client.build_ping_url(PingState::Run, 0, None);
+                let url = client.build_ping_url(PingState::Fail, 500, Some("Test error"));
 
-            assert!(url.contains("https://cronitor.link/test-monitor"));
-            assert!(url.contains("state=run"));
-            assert!(url.contains("status_code=0"));
-            assert!(url.contains("env=test"));
-            assert!(url.contains("series="));
-            assert!(url.contains("host="));
-            assert!(!url.contains("message="));
-        }
+                assert!(url.contains("https://cronitor.link/test-monitor"));
+                assert!(url.contains("state=fail"));
+                assert!(url.contains("status_code=500"));
+                assert!(url.contains("env=test"));
+                assert!(url.contains("message=Test%20error")); // URL encoded
+            }
+
+            #[test]
+            fn test_cronitor_ping_url_special_characters() {
+                let config = test_config();
+                let client = Cronitor::new(config).unwrap();
 
-        #[test]
-        fn test_cronitor_ping_url_construction_with_message() {
-            let config = Config::default();
-            let client = Cronitor::new(config).unwrap();
+                let url =
+                    client.build_ping_url(PingState::Fail, 500, Some("Error: 500 & timeout!"));
 
-            let url = client.build_ping_url(PingState::Fail, 500, Some("Test error"));
+                assert!(url.contains("message=Error%3A%20500%20%26%20timeout%21"));
+            }
 
-            assert!(url.contains("https://cronitor.link/test-monitor"));
-            assert!(url.contains("state=fail"));
-            assert!(url.contains("status_code=500"));
-            assert!(url.contains("env=test"));
-            assert!(url.contains("message=Test%20error")); // URL encoded
+            #[tokio::test]
+            async fn test_cronitor_ping_non_2xx_is_an_error() {
+                let server = httpmock::MockServer::start();
+                let mock = server.mock(|when, then| {
+                    when.method(httpmock::Method::GET).path("/test-monitor");
+                    then.status(403).body("forbidden");
+                });
+
+                let config = CronitorConfig {
+                    base_url: server.base_url(),
+                    ..test_config()
+                };
+                let client = Cronitor::new(config).unwrap();
+
+                let err = client.ping(PingState::Complete, 0, None).await.unwrap_err();
+                assert!(matches!(
+                    err,
+                    Error::Export {
+                        exporter: "Cronitor",
+                        ..
+                    }
+                ));
+                assert!(err.to_string().contains("403 Forbidden: forbidden"));
+                mock.assert();
+            }
         }
+    }
 
-        #[test]
-        fn test_cronitor_ping_url_special_characters() {
-            let config = Config::default();
-            let client = Cronitor::new(config).unwrap();
+    #[cfg(feature = "log")]
+    mod log {
+        use tracing::{error, info};
+
+        use crate::{Export, PingState, Result};
+
+        /// Exporter that reports probe results through `tracing` instead of an external service,
+        /// for embedding the probes in an application that already collects its logs
+        #[derive(Debug, Clone, Copy, Default)]
+        pub struct Log;
+
+        #[async_trait::async_trait]
+        impl Export for Log {
+            async fn ping(
+                &self,
+                state: PingState,
+                status_code: u16,
+                message: Option<&str>,
+            ) -> Result<()> {
+                match state {
+                    PingState::Run => info!("Probe started"),
+                    PingState::Complete => info!("Probe completed"),
+                    PingState::Fail => error!(
+                        "Probe failed with status {status_code}: {}",
+                        message.unwrap_or("no message")
+                    ),
+                }
+                Ok(())
+            }
+        }
 
-            let url = client.build_ping_url(PingState::Fail, 500, Some("Error: 500 & timeout!"));
+        #[cfg(test)]
+        mod tests {
+            use super::*;
 
-            assert!(url.contains("message=Error%3A%20500%20%26%20timeout%21"));
+            #[tokio::test]
+            async fn test_log_ping_never_fails() {
+                for state in [PingState::Run, PingState::Complete, PingState::Fail] {
+                    assert!(Log.ping(state, 1, Some("boom")).await.is_ok());
+                }
+            }
         }
     }
 }
 
 pub mod probes {
-    use anyhow::{Context, Result};
-    use reqwest::Client;
-    use serde_json::json;
-    use std::{
-        process::{Command, Stdio},
-        time::Duration,
+    use super::{Error, Result};
+
+    pub use addresses::AllAddresses;
+    #[cfg(feature = "http")]
+    pub use http::{Http, HttpConfig};
+    #[cfg(feature = "newman")]
+    pub use newman::{Newman, NewmanConfig, Postman};
+    #[cfg(feature = "openai")]
+    pub use openai::{
+        Bench, BenchConfig, BenchReport, Compare, CompareConfig, Determinism, DeterminismConfig,
+        LongContext, LongContextConfig, OpenAI, OpenAIConfig,
     };
-    use tracing::{error, info};
-
-    use super::{ProbeResult, cli::Config};
-
-    // Type of LLM endpoint to probe
-    #[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
-    pub enum Type {
-        #[value(name = "openai-chat-completion")]
-        OpenAIChatCompletion,
-        #[value(name = "openai-embedding")]
-        OpenAIEmbedding,
-        #[value(name = "newman")]
-        Newman,
+    #[cfg(feature = "tls")]
+    pub use tls::{Tls, TlsConfig};
+
+    /// TLS settings for the connections probes make: extra CA certificates to trust, a client
+    /// certificate for mutual TLS, and skipping verification for lab environments
+    #[derive(Debug, Clone, Default, PartialEq)]
+    pub struct TlsOptions {
+        /// PEM file of CA certificates to trust as well as the public roots
+        pub ca_cert_path: Option<String>,
+        /// PEM file of the client certificate presented to the server, which may also hold its
+        /// private key
+        pub client_cert_path: Option<String>,
+        /// PEM file of the client certificate's private key
+        pub client_key_path: Option<String>,
+        /// Skip verifying the server certificate. Only meant for lab environments.
+        pub insecure: bool,
     }
 
-    impl From<Type> for clap::builder::OsStr {
-        fn from(value: Type) -> Self {
-            match value {
-                Type::OpenAIChatCompletion => "openai-chat-completion".into(),
-                Type::OpenAIEmbedding => "openai-embedding".into(),
-                Type::Newman => "newman".into(),
-            }
+    impl TlsOptions {
+        pub fn new() -> Self {
+            Default::default()
         }
-    }
-
-    /// LLM endpoint probe functionality
-    pub struct OpenAI {
-        client: Client,
-        config: Config,
-    }
 
-    /// LLM probe implementation
-    #[async_trait::async_trait]
-    impl super::Probe for OpenAI {
-        fn new(config: Config) -> Result<Self> {
-            let client = Client::builder()
-                .timeout(Duration::from_secs(config.timeout_seconds))
-                .build()
-                .context("building reqwest client")?;
+        pub fn ca_cert_path(mut self, ca_cert_path: impl Into<String>) -> Self {
+            self.ca_cert_path = Some(ca_cert_path.into());
+            self
+        }
 
-            Ok(OpenAI { client, config })
+        pub fn client_cert_path(mut self, client_cert_path: impl Into<String>) -> Self {
+            self.client_cert_path = Some(client_cert_path.into());
+            self
         }
 
-        async fn probe(&self) -> ProbeResult {
-            let endpoint = self.build_endpoint_url();
-            let payload = self.build_payload();
+        pub fn client_key_path(mut self, client_key_path: impl Into<String>) -> Self {
+            self.client_key_path = Some(client_key_path.into());
+            self
+        }
 
-            info!("Querying {endpoint}");
+        pub fn insecure(mut self, insecure: bool) -> Self {
+            self.insecure = insecure;
+            self
+        }
 
-            match self.client.post(&endpoint).json(&payload).send().await {
-                Ok(resp) => {
-                    let status = resp.status();
-                    let body = resp.text().await.unwrap_or_default();
-                    info!("Response body: {body}");
+        /// Read the CA certificates to trust, if any
+        #[cfg(any(
+            feature = "http",
+            feature = "newman",
+            feature = "openai",
+            feature = "tls"
+        ))]
+        pub(crate) fn ca_pem(&self) -> Result<Option<Vec<u8>>> {
+            self.ca_cert_path.as_deref().map(read_file).transpose()
+        }
 
-                    if status.is_success() {
-                        ProbeResult::Success
-                    } else {
-                        ProbeResult::Error(status.as_u16())
+        /// Read the client certificate and its private key as a single PEM, if any
+        #[cfg(any(
+            feature = "http",
+            feature = "newman",
+            feature = "openai",
+            feature = "tls"
+        ))]
+        pub(crate) fn identity_pem(&self) -> Result<Option<Vec<u8>>> {
+            match (&self.client_cert_path, &self.client_key_path) {
+                (None, None) => Ok(None),
+                (None, Some(key_path)) => Err(Error::InvalidConfig {
+                    field: "client certificate",
+                    reason: format!("the client key {key_path} was given without a certificate"),
+                }),
+                (Some(cert_path), key_path) => {
+                    let mut pem = read_file(cert_path)?;
+                    if let Some(key_path) = key_path {
+                        pem.push(b'\n');
+                        pem.extend(read_file(key_path)?);
                     }
+                    Ok(Some(pem))
                 }
-                Err(e) if e.is_timeout() => ProbeResult::Timeout,
-                Err(e) => ProbeResult::NetworkError(e.to_string()),
             }
         }
     }
 
-    /// Internal methods for OpenAI probe
-    impl OpenAI {
-        pub fn build_endpoint_url(&self) -> String {
-            match self.config.endpoint_type {
-                Type::OpenAIChatCompletion => {
-                    format!("{}/v1/chat/completions", self.config.server_url)
+    /// Network settings for the connections probes make, kept apart from the exporters' so
+    /// that probes and reports can take different routes
+    #[derive(Debug, Clone, Default, PartialEq)]
+    pub struct NetworkOptions {
+        /// Proxy for every request, e.g. http://proxy:3128 or socks5h://proxy:1080, instead of
+        /// the HTTP_PROXY, HTTPS_PROXY and ALL_PROXY environment variables
+        pub proxy: Option<String>,
+        /// Hosts reached without a proxy, in NO_PROXY syntax, instead of the NO_PROXY
+        /// environment variable. `*` bypasses the proxy for every host.
+        pub no_proxy: Option<String>,
+        /// Addresses connected to instead of resolving a host name, keeping the host name for
+        /// the Host header and TLS
+        pub resolve: Vec<(String, std::net::SocketAddr)>,
+    }
+
+    impl NetworkOptions {
+        pub fn new() -> Self {
+            Default::default()
+        }
+
+        pub fn proxy(mut self, proxy: impl Into<String>) -> Self {
+            self.proxy = Some(proxy.into());
+            self
+        }
+
+        pub fn no_proxy(mut self, no_proxy: impl Into<String>) -> Self {
+            self.no_proxy = Some(no_proxy.into());
+            self
+        }
+
+        /// Connect to an address instead of resolving a host name, can be repeated
+        pub fn resolve(mut self, host: impl Into<String>, address: std::net::SocketAddr) -> Self {
+            self.resolve.push((host.into(), address));
+            self
+        }
+
+        /// Address a host name is overridden to, if any
+        #[cfg(feature = "tls")]
+        pub(crate) fn resolved(&self, host: &str) -> Option<std::net::SocketAddr> {
+            self.resolve
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(host))
+                .map(|(_, address)| *address)
+        }
+
+        /// Proxies replacing those from the environment, or `None` to keep the environment's
+        #[cfg(any(feature = "http", feature = "newman", feature = "openai"))]
+        fn proxies(&self) -> Result<Option<Vec<reqwest::Proxy>>> {
+            use reqwest::{NoProxy, Proxy};
+
+            if self.proxy.is_none() && self.no_proxy.is_none() {
+                return Ok(None);
+            }
+            let invalid = |e: reqwest::Error| Error::InvalidConfig {
+                field: "proxy",
+                reason: e.to_string(),
+            };
+            let no_proxy = match &self.no_proxy {
+                Some(no_proxy) => NoProxy::from_string(no_proxy),
+                None => NoProxy::from_env(),
+            };
+
+            let proxies = match &self.proxy {
+                Some(proxy) => vec![Proxy::all(proxy).map_err(invalid)?],
+                // Only the no proxy list is overridden, so rebuild the environment's proxies
+                None => {
+                    let env = |names: [&str; 2]| {
+                        names
+                            .into_iter()
+                            .find_map(|name| std::env::var(name).ok().filter(|v| !v.is_empty()))
+                    };
+                    let mut proxies = Vec::new();
+                    if let Some(proxy) = env(["HTTP_PROXY", "http_proxy"]) {
+                        proxies.push(Proxy::http(proxy).map_err(invalid)?);
+                    }
+                    if let Some(proxy) = env(["HTTPS_PROXY", "https_proxy"]) {
+                        proxies.push(Proxy::https(proxy).map_err(invalid)?);
+                    }
+                    if let Some(proxy) = env(["ALL_PROXY", "all_proxy"]) {
+                        proxies.push(Proxy::all(proxy).map_err(invalid)?);
+                    }
+                    proxies
                 }
-                Type::OpenAIEmbedding => format!("{}/v1/embeddings", self.config.server_url),
-                _ => panic!("Unsupported endpoint type"),
+            };
+            Ok(Some(
+                proxies
+                    .into_iter()
+                    .map(|proxy| proxy.no_proxy(no_proxy.clone()))
+                    .collect(),
+            ))
+        }
+    }
+
+    /// Start building a probe's HTTP client with its TLS and network settings
+    #[cfg(any(feature = "http", feature = "newman", feature = "openai"))]
+    pub(crate) fn client_builder(
+        tls: &TlsOptions,
+        network: &NetworkOptions,
+    ) -> Result<reqwest::ClientBuilder> {
+        let mut builder = reqwest::Client::builder().danger_accept_invalid_certs(tls.insecure);
+        if let Some(pem) = tls.ca_pem()? {
+            for certificate in
+                reqwest::Certificate::from_pem_bundle(&pem).map_err(Error::HttpClient)?
+            {
+                builder = builder.add_root_certificate(certificate);
             }
         }
+        if let Some(pem) = tls.identity_pem()? {
+            // PEM identities are only supported by the rustls backend
+            let identity = reqwest::Identity::from_pem(&pem).map_err(Error::HttpClient)?;
+            builder = builder.use_rustls_tls().identity(identity);
+        }
 
-        pub fn build_payload(&self) -> serde_json::Value {
-            match self.config.endpoint_type {
-                Type::OpenAIChatCompletion => json!({
-                    "model": self.config.model_name,
-                    "messages": [{ "role": "user", "content": "test" }],
-                    "max_tokens": 1,
-                    "priority": -100
-                }),
-                Type::OpenAIEmbedding => json!({
-                    "model": self.config.model_name,
-                    "input": "test",
-                    "priority": -100
-                }),
-                _ => panic!("Unsupported endpoint type"),
+        if let Some(proxies) = network.proxies()? {
+            builder = builder.no_proxy();
+            for proxy in proxies {
+                builder = builder.proxy(proxy);
             }
         }
+        for (host, address) in &network.resolve {
+            builder = builder.resolve(host, *address);
+        }
+        Ok(builder)
     }
 
-    /// Newman probe functionality
-    pub struct Newman {
-        config: Config,
+    #[cfg(any(
+        feature = "http",
+        feature = "newman",
+        feature = "openai",
+        feature = "tls"
+    ))]
+    fn read_file(path: &str) -> Result<Vec<u8>> {
+        std::fs::read(path).map_err(|source| Error::ReadFile {
+            path: path.to_string(),
+            source,
+        })
     }
 
-    /// LLM probe implementation
-    #[async_trait::async_trait]
-    impl super::Probe for Newman {
-        fn new(config: Config) -> Result<Self> {
-            Ok(Newman { config })
+    /// Compare JSON values, treating numbers as equal when their values are, e.g. 1 and 1.0
+    #[cfg(any(feature = "http", feature = "newman"))]
+    fn json_equal(actual: &serde_json::Value, expected: &serde_json::Value) -> bool {
+        match (actual, expected) {
+            (serde_json::Value::Number(a), serde_json::Value::Number(b)) => {
+                a.as_f64() == b.as_f64()
+            }
+            _ => actual == expected,
+        }
+    }
+
+    /// Join a server URL, API base path and endpoint path, normalising slashes.
+    ///
+    /// The base path is skipped when the server URL already ends with it, and when the endpoint
+    /// path already starts with it, so `http://host/v1` and `/v1/chat/completions` both work
+    /// with the default `/v1` base path.
+    #[cfg(any(all(feature = "cli", feature = "http"), feature = "openai"))]
+    pub(crate) fn join_url(server_url: &str, base_path: &str, path: &str) -> String {
+        let server_url = server_url.trim_end_matches('/');
+        let base_path = base_path.trim_matches('/');
+        let path = path.trim_start_matches('/');
+
+        let path = match path.strip_prefix(base_path) {
+            Some(rest) if !base_path.is_empty() && (rest.is_empty() || rest.starts_with('/')) => {
+                rest.trim_start_matches('/')
+            }
+            _ => path,
+        };
+
+        let prefix = if base_path.is_empty() || server_url.ends_with(&format!("/{base_path}")) {
+            server_url.to_string()
+        } else {
+            format!("{server_url}/{base_path}")
+        };
+
+        if path.is_empty() {
+            prefix
+        } else {
+            format!("{prefix}/{path}")
+        }
+    }
+
+    // Type of LLM endpoint to probe
+    #[derive(Debug, Clone, Copy, Default, PartialEq)]
+    pub enum Type {
+        #[default]
+        OpenAIChatCompletion,
+        OpenAIEmbedding,
+        OpenAIToolCall,
+        OpenAIVision,
+        OpenAITranscription,
+        OpenAISpeech,
+        OpenAIImage,
+        OpenAIModeration,
+        OpenAICompare,
+        OpenAIDeterminism,
+        OpenAILongContext,
+        Newman,
+        Postman,
+        Http,
+        Tls,
+    }
+
+    impl Type {
+        /// Every built-in probe type
+        pub const ALL: [Type; 15] = [
+            Type::OpenAIChatCompletion,
+            Type::OpenAIEmbedding,
+            Type::OpenAIToolCall,
+            Type::OpenAIVision,
+            Type::OpenAITranscription,
+            Type::OpenAISpeech,
+            Type::OpenAIImage,
+            Type::OpenAIModeration,
+            Type::OpenAICompare,
+            Type::OpenAIDeterminism,
+            Type::OpenAILongContext,
+            Type::Newman,
+            Type::Postman,
+            Type::Http,
+            Type::Tls,
+        ];
+
+        /// Name the probe type is registered under and selected by with ENDPOINT_TYPE
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                Type::OpenAIChatCompletion => "openai-chat-completion",
+                Type::OpenAIEmbedding => "openai-embedding",
+                Type::OpenAIToolCall => "openai-tool-call",
+                Type::OpenAIVision => "openai-vision",
+                Type::OpenAITranscription => "openai-transcription",
+                Type::OpenAISpeech => "openai-speech",
+                Type::OpenAIImage => "openai-image",
+                Type::OpenAIModeration => "openai-moderation",
+                Type::OpenAICompare => "openai-compare",
+                Type::OpenAIDeterminism => "openai-determinism",
+                Type::OpenAILongContext => "openai-long-context",
+                Type::Newman => "newman",
+                Type::Postman => "postman",
+                Type::Http => "http",
+                Type::Tls => "tls",
+            }
+        }
+    }
+
+    impl std::str::FromStr for Type {
+        type Err = Error;
+
+        fn from_str(name: &str) -> Result<Self> {
+            Type::ALL
+                .into_iter()
+                .find(|endpoint_type| endpoint_type.as_str() == name)
+                .ok_or_else(|| Error::UnknownProbe {
+                    name: name.to_string(),
+                    available: Type::ALL.iter().map(|t| t.as_str().to_string()).collect(),
+                })
+        }
+    }
+
+    mod addresses {
+        use reqwest::Url;
+        use std::{
+            net::{SocketAddr, ToSocketAddrs},
+            sync::Arc,
+        };
+        use tracing::{info, warn};
+
+        use crate::{Error, Probe, ProbeResult, Result};
+
+        /// Probe running a probe against each address a host name resolves to, e.g. every pod
+        /// behind a headless service or every region behind DNS round-robin, so that a single
+        /// bad backend fails the check every time rather than only when it happens to be picked.
+        ///
+        /// The host name is resolved once, when the probe is built. Each address is probed
+        /// concurrently, and the probe fails if any address fails.
+        pub struct AllAddresses {
+            host: String,
+            probes: AddressProbes,
+        }
+
+        /// Probe of each address, or why the host name couldn't be resolved
+        type AddressProbes = std::result::Result<Vec<(SocketAddr, Arc<dyn Probe>)>, String>;
+
+        impl AllAddresses {
+            /// Resolve the host of `server_url` and build a probe for each of its addresses with
+            /// `probe`, which is given the host name and the address to connect to in its place.
+            /// The probe should keep the host name for the `Host` header and TLS, e.g. with
+            /// [`NetworkOptions::resolve`](super::NetworkOptions::resolve).
+            ///
+            /// A host name that can't be resolved fails the probe with a network error, rather
+            /// than failing here.
+            pub fn new<F>(server_url: &str, mut probe: F) -> Result<Self>
+            where
+                F: FnMut(&str, SocketAddr) -> Result<Box<dyn Probe>>,
+            {
+                let invalid_url = |reason: String| Error::InvalidConfig {
+                    field: "server url",
+                    reason,
+                };
+                let url = Url::parse(server_url)
+                    .map_err(|e| invalid_url(format!("{server_url}: {e}")))?;
+                let host = url
+                    .host_str()
+                    .ok_or_else(|| invalid_url(format!("{server_url} has no host")))?
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .to_string();
+                let port = url.port_or_known_default().unwrap_or(443);
+
+                let addresses = match (host.as_str(), port).to_socket_addrs() {
+                    Ok(addresses) => addresses,
+                    Err(e) => {
+                        return Ok(AllAddresses {
+                            probes: Err(format!("failed to resolve {host}: {e}")),
+                            host,
+                        });
+                    }
+                };
+                let mut probes = Vec::new();
+                for address in addresses {
+                    if probes.iter().all(|(probed, _)| *probed != address) {
+                        probes.push((address, probe(&host, address)?));
+                    }
+                }
+                Ok(AllAddresses::from_probes(host, probes))
+            }
+
+            /// Aggregate probes that were already built for each address of `host`
+            pub fn from_probes(
+                host: impl Into<String>,
+                probes: Vec<(SocketAddr, Box<dyn Probe>)>,
+            ) -> Self {
+                AllAddresses {
+                    host: host.into(),
+                    probes: Ok(probes
+                        .into_iter()
+                        .map(|(address, probe)| (address, Arc::from(probe)))
+                        .collect()),
+                }
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl Probe for AllAddresses {
+            async fn probe(&self) -> ProbeResult {
+                let probes = match &self.probes {
+                    Ok(probes) if probes.is_empty() => {
+                        return ProbeResult::NetworkError(format!(
+                            "{} has no addresses",
+                            self.host
+                        ));
+                    }
+                    Ok(probes) => probes,
+                    Err(reason) => return ProbeResult::NetworkError(reason.clone()),
+                };
+                info!("Probing {} addresses of {}", probes.len(), self.host);
+
+                let tasks: Vec<_> = probes
+                    .iter()
+                    .map(|(address, probe)| {
+                        let probe = probe.clone();
+                        (address, tokio::spawn(async move { probe.probe().await }))
+                    })
+                    .collect();
+                let mut results = Vec::with_capacity(tasks.len());
+                for (address, task) in tasks {
+                    // A panicking probe is a bug of the probe, not a fault of the address
+                    let result = task.await.unwrap_or_else(|e| {
+                        ProbeResult::Misconfigured(format!("probe of {address} failed: {e}"))
+                    });
+                    match &result {
+                        ProbeResult::Success => info!("{address}: success"),
+                        result => warn!("{address}: {result}"),
+                    }
+                    results.push((address, result));
+                }
+
+                let failures: Vec<_> = results
+                    .iter()
+                    .filter(|(_, result)| *result != ProbeResult::Success)
+                    .collect();
+                if failures.is_empty() {
+                    return ProbeResult::Success;
+                }
+                // The same configuration is used for every address, so report it as is
+                if let Some((_, result)) = failures
+                    .iter()
+                    .find(|(_, result)| matches!(result, ProbeResult::Misconfigured(_)))
+                {
+                    return result.clone();
+                }
+                // Every address failing the same way is reported like a single probe would be
+                let (_, first) = failures[0];
+                if failures.len() == results.len()
+                    && failures.iter().all(|(_, result)| result == first)
+                {
+                    return first.clone();
+                }
+
+                ProbeResult::TestFailure(format!(
+                    "{} of {} addresses of {} failed: {}",
+                    failures.len(),
+                    results.len(),
+                    self.host,
+                    failures
+                        .iter()
+                        .map(|(address, result)| format!("{address}: {result}"))
+                        .collect::<Vec<_>>()
+                        .join("; ")
+                ))
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+
+            /// Probe answering with a fixed result
+            struct FixedProbe(ProbeResult);
+
+            #[async_trait::async_trait]
+            impl Probe for FixedProbe {
+                async fn probe(&self) -> ProbeResult {
+                    self.0.clone()
+                }
+            }
+
+            fn all_addresses(results: Vec<ProbeResult>) -> AllAddresses {
+                AllAddresses::from_probes(
+                    "api.internal",
+                    results
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, result)| {
+                            let address = SocketAddr::from(([10, 0, 0, i as u8 + 1], 443));
+                            (address, Box::new(FixedProbe(result)) as Box<dyn Probe>)
+                        })
+                        .collect(),
+                )
+            }
+
+            #[tokio::test]
+            async fn test_all_addresses_aggregates_results() {
+                let healthy = all_addresses(vec![ProbeResult::Success, ProbeResult::Success]);
+                assert_eq!(healthy.probe().await, ProbeResult::Success);
+
+                let one_bad = all_addresses(vec![
+                    ProbeResult::Success,
+                    ProbeResult::Error(503),
+                    ProbeResult::Timeout,
+                ]);
+                assert_eq!(
+                    one_bad.probe().await,
+                    ProbeResult::TestFailure(
+                        "2 of 3 addresses of api.internal failed: 10.0.0.2:443: HTTP 503; \
+                         10.0.0.3:443: timeout"
+                            .to_string()
+                    )
+                );
+
+                let all_down = all_addresses(vec![ProbeResult::Timeout, ProbeResult::Timeout]);
+                assert_eq!(all_down.probe().await, ProbeResult::Timeout);
+            }
+
+            #[tokio::test]
+            async fn test_all_addresses_survives_panicking_probe() {
+                struct PanickingProbe;
+
+                #[async_trait::async_trait]
+                impl Probe for PanickingProbe {
+                    async fn probe(&self) -> ProbeResult {
+                        panic!("probe bug")
+                    }
+                }
+
+                let address = SocketAddr::from(([10, 0, 0, 1], 443));
+                let probe = AllAddresses::from_probes(
+                    "api.internal",
+                    vec![(address, Box::new(PanickingProbe) as Box<dyn Probe>)],
+                );
+
+                match probe.probe().await {
+                    ProbeResult::Misconfigured(reason) => assert!(
+                        reason.starts_with("probe of 10.0.0.1:443 failed: task")
+                            && reason.contains("probe bug"),
+                        "{reason}"
+                    ),
+                    result => panic!("expected a misconfiguration, got {result:?}"),
+                }
+            }
+
+            #[tokio::test]
+            async fn test_all_addresses_resolves_host() {
+                let mut probed = Vec::new();
+                let probe = AllAddresses::new("http://localhost:8080/health", |host, address| {
+                    probed.push((host.to_string(), address));
+                    Ok(Box::new(FixedProbe(ProbeResult::Success)))
+                })
+                .unwrap();
+
+                assert!(!probed.is_empty());
+                for (host, address) in &probed {
+                    assert_eq!(host, "localhost");
+                    assert!(address.ip().is_loopback(), "{address}");
+                    assert_eq!(address.port(), 8080);
+                }
+                assert_eq!(probe.probe().await, ProbeResult::Success);
+
+                let unresolved =
+                    AllAddresses::new("https://missing.invalid", |_, _| unreachable!()).unwrap();
+                assert!(matches!(
+                    unresolved.probe().await,
+                    ProbeResult::NetworkError(_)
+                ));
+            }
+        }
+    }
+
+    #[cfg(feature = "openai")]
+    mod openai {
+        use base64::{Engine, engine::general_purpose::STANDARD};
+        use reqwest::{
+            Client, RequestBuilder,
+            header::CONTENT_TYPE,
+            multipart::{Form, Part},
+        };
+        use serde_json::json;
+        use std::{
+            borrow::Cow,
+            time::{Duration, Instant},
+        };
+        use tracing::info;
+
+        use super::{NetworkOptions, TlsOptions, Type, client_builder, join_url};
+        use crate::{Error, ProbeResult, Result};
+
+        pub use bench::{Bench, BenchConfig, BenchReport};
+        pub use compare::{Compare, CompareConfig};
+        pub use determinism::{Determinism, DeterminismConfig};
+        pub use long_context::{LongContext, LongContextConfig};
+
+        /// Endpoint queried by the OpenAI probe, resolved from the probe type on construction
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        enum Endpoint {
+            ChatCompletion,
+            Embedding,
+            ToolCall,
+            Vision,
+            Transcription,
+            Speech,
+            Image,
+            Moderation,
+        }
+
+        impl TryFrom<Type> for Endpoint {
+            type Error = Error;
+
+            fn try_from(endpoint_type: Type) -> Result<Self> {
+                match endpoint_type {
+                    Type::OpenAIChatCompletion => Ok(Endpoint::ChatCompletion),
+                    Type::OpenAIEmbedding => Ok(Endpoint::Embedding),
+                    Type::OpenAIToolCall => Ok(Endpoint::ToolCall),
+                    Type::OpenAIVision => Ok(Endpoint::Vision),
+                    Type::OpenAITranscription => Ok(Endpoint::Transcription),
+                    Type::OpenAISpeech => Ok(Endpoint::Speech),
+                    Type::OpenAIImage => Ok(Endpoint::Image),
+                    Type::OpenAIModeration => Ok(Endpoint::Moderation),
+                    Type::OpenAICompare
+                    | Type::OpenAIDeterminism
+                    | Type::OpenAILongContext
+                    | Type::Newman
+                    | Type::Postman
+                    | Type::Http
+                    | Type::Tls => Err(Error::UnsupportedEndpointType {
+                        probe: "OpenAI",
+                        endpoint_type,
+                    }),
+                }
+            }
+        }
+
+        /// Configuration for the [`OpenAI`] probe
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct OpenAIConfig {
+            /// Base URL of the server to probe, e.g. https://my-openai-proxy
+            pub server_url: String,
+            /// Path the OpenAI-compatible API is mounted at, not repeated if `server_url` ends with it
+            pub base_path: String,
+            /// Override of the endpoint path queried, relative to `base_path`
+            pub endpoint_path: Option<String>,
+            /// API key sent as a bearer token, or as the `api-key` header for Azure OpenAI
+            pub api_key: Option<String>,
+            /// Azure OpenAI deployment name, switching to Azure style URLs
+            pub azure_deployment: Option<String>,
+            /// Azure OpenAI API version
+            pub azure_api_version: String,
+            /// Type of endpoint to probe, one of the `openai-*` types
+            pub endpoint_type: Type,
+            /// Name of the model to query
+            pub model_name: String,
+            /// Request timeout
+            pub timeout: Duration,
+            /// Keyword the model's answer or transcript must contain
+            pub expected_keyword: Option<String>,
+            /// WAV file uploaded by the transcription probe, instead of the bundled tone
+            pub audio_path: Option<String>,
+            /// Voice requested from the text-to-speech probe
+            pub voice: String,
+            /// Smallest audio body in bytes the text-to-speech probe accepts. The WAV header must
+            /// also report a non-zero duration.
+            pub min_audio_bytes: usize,
+            /// Size of the image requested by the image generation probe, as WIDTHxHEIGHT
+            pub image_size: String,
+            /// Require chat and embedding responses to report plausible token usage
+            pub check_usage: bool,
+            /// CA certificates, client certificate and verification of the connection
+            pub tls: TlsOptions,
+            /// Proxy and DNS overrides for the connection
+            pub network: NetworkOptions,
+        }
+
+        impl Default for OpenAIConfig {
+            fn default() -> Self {
+                OpenAIConfig {
+                    server_url: "https://api.openai.com".to_string(),
+                    base_path: "/v1".to_string(),
+                    endpoint_path: None,
+                    api_key: None,
+                    azure_deployment: None,
+                    azure_api_version: "2024-10-21".to_string(),
+                    endpoint_type: Type::OpenAIChatCompletion,
+                    model_name: "gpt-4".to_string(),
+                    timeout: Duration::from_secs(10),
+                    expected_keyword: None,
+                    audio_path: None,
+                    voice: "alloy".to_string(),
+                    min_audio_bytes: 1024,
+                    image_size: "256x256".to_string(),
+                    check_usage: false,
+                    tls: TlsOptions::default(),
+                    network: NetworkOptions::default(),
+                }
+            }
+        }
+
+        impl OpenAIConfig {
+            pub fn new(server_url: impl Into<String>, endpoint_type: Type) -> Self {
+                OpenAIConfig {
+                    server_url: server_url.into(),
+                    endpoint_type,
+                    ..Default::default()
+                }
+            }
+
+            pub fn base_path(mut self, base_path: impl Into<String>) -> Self {
+                self.base_path = base_path.into();
+                self
+            }
+
+            pub fn endpoint_path(mut self, endpoint_path: impl Into<String>) -> Self {
+                self.endpoint_path = Some(endpoint_path.into());
+                self
+            }
+
+            pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+                self.api_key = Some(api_key.into());
+                self
+            }
+
+            pub fn azure_deployment(mut self, deployment: impl Into<String>) -> Self {
+                self.azure_deployment = Some(deployment.into());
+                self
+            }
+
+            pub fn azure_api_version(mut self, api_version: impl Into<String>) -> Self {
+                self.azure_api_version = api_version.into();
+                self
+            }
+
+            pub fn model_name(mut self, model_name: impl Into<String>) -> Self {
+                self.model_name = model_name.into();
+                self
+            }
+
+            pub fn timeout(mut self, timeout: Duration) -> Self {
+                self.timeout = timeout;
+                self
+            }
+
+            pub fn expected_keyword(mut self, keyword: impl Into<String>) -> Self {
+                self.expected_keyword = Some(keyword.into());
+                self
+            }
+
+            pub fn audio_path(mut self, audio_path: impl Into<String>) -> Self {
+                self.audio_path = Some(audio_path.into());
+                self
+            }
+
+            pub fn voice(mut self, voice: impl Into<String>) -> Self {
+                self.voice = voice.into();
+                self
+            }
+
+            pub fn min_audio_bytes(mut self, min_audio_bytes: usize) -> Self {
+                self.min_audio_bytes = min_audio_bytes;
+                self
+            }
+
+            pub fn image_size(mut self, image_size: impl Into<String>) -> Self {
+                self.image_size = image_size.into();
+                self
+            }
+
+            pub fn check_usage(mut self, check_usage: bool) -> Self {
+                self.check_usage = check_usage;
+                self
+            }
+
+            pub fn tls(mut self, tls: TlsOptions) -> Self {
+                self.tls = tls;
+                self
+            }
+
+            pub fn network(mut self, network: NetworkOptions) -> Self {
+                self.network = network;
+                self
+            }
+
+            pub fn build(self) -> Result<OpenAI> {
+                OpenAI::new(self)
+            }
+        }
+
+        /// LLM endpoint probe functionality
+        pub struct OpenAI {
+            client: Client,
+            config: OpenAIConfig,
+            endpoint: Endpoint,
+            audio: Cow<'static, [u8]>,
+        }
+
+        impl OpenAI {
+            pub fn new(config: OpenAIConfig) -> Result<Self> {
+                let endpoint = Endpoint::try_from(config.endpoint_type)?;
+
+                let client = client_builder(&config.tls, &config.network)?
+                    .timeout(config.timeout)
+                    .build()
+                    .map_err(Error::HttpClient)?;
+
+                let audio = match &config.audio_path {
+                    Some(path) => {
+                        Cow::Owned(std::fs::read(path).map_err(|source| Error::ReadFile {
+                            path: path.clone(),
+                            source,
+                        })?)
+                    }
+                    None => Cow::Borrowed(TRANSCRIPTION_TEST_AUDIO),
+                };
+
+                if endpoint == Endpoint::Image {
+                    parse_image_size(&config.image_size)?;
+                }
+
+                Ok(OpenAI {
+                    client,
+                    config,
+                    endpoint,
+                    audio,
+                })
+            }
+
+            /// Start building an OpenAI probe, see [`OpenAIConfig`]
+            pub fn builder(server_url: impl Into<String>, endpoint_type: Type) -> OpenAIConfig {
+                OpenAIConfig::new(server_url, endpoint_type)
+            }
+        }
+
+        /// LLM probe implementation
+        #[async_trait::async_trait]
+        impl crate::Probe for OpenAI {
+            async fn probe(&self) -> ProbeResult {
+                match self.probe_request() {
+                    Ok((request, max_tokens)) => self.exchange(request, max_tokens).await.0,
+                    Err(e) => ProbeResult::Misconfigured(e.to_string()),
+                }
+            }
+        }
+
+        /// Response to a probe request
+        struct Reply {
+            status: u16,
+            content_type: String,
+            body: Vec<u8>,
+            /// Time until the whole body was read
+            latency: Duration,
+        }
+
+        /// Internal methods for OpenAI probe
+        impl OpenAI {
+            /// Start a request to the endpoint, authenticated with the API key
+            fn request(&self) -> RequestBuilder {
+                self.post(&self.build_endpoint_url())
+            }
+
+            /// Start a request to any URL of the server, authenticated with the API key
+            fn post(&self, url: &str) -> RequestBuilder {
+                info!("Querying {url}");
+
+                let request = self.client.post(url);
+                match (&self.config.api_key, &self.config.azure_deployment) {
+                    (Some(api_key), Some(_)) => request.header("api-key", api_key),
+                    (Some(api_key), None) => request.bearer_auth(api_key),
+                    (None, _) => request,
+                }
+            }
+
+            /// Start the probe's request, with the payload for the endpoint, also returning the
+            /// payload's `max_tokens`
+            fn probe_request(&self) -> Result<(RequestBuilder, Option<u64>)> {
+                Ok(match self.endpoint {
+                    Endpoint::Transcription => (self.request().multipart(self.build_form()?), None),
+                    _ => {
+                        let payload = self.build_payload();
+                        let max_tokens = payload["max_tokens"].as_u64();
+                        (self.request().json(&payload), max_tokens)
+                    }
+                })
+            }
+
+            /// Send a request and check the response like the probe does, also returning the
+            /// response if one was read. `max_tokens` is the one of the request sent.
+            async fn exchange(
+                &self,
+                request: RequestBuilder,
+                max_tokens: Option<u64>,
+            ) -> (ProbeResult, Option<Reply>) {
+                let reply = match self.send(request).await {
+                    Ok(reply) => reply,
+                    Err(result) => return (result, None),
+                };
+
+                let result = if !(200..300).contains(&reply.status) {
+                    ProbeResult::Error(reply.status)
+                } else {
+                    match self
+                        .validate_response(&reply.content_type, &reply.body, max_tokens)
+                        .await
+                    {
+                        Ok(()) => ProbeResult::Success,
+                        Err(reason) => ProbeResult::InvalidResponse(reason),
+                    }
+                };
+                (result, Some(reply))
+            }
+
+            /// Send a request and read the response, returning the probe result if it failed
+            /// before a response was read
+            async fn send(&self, request: RequestBuilder) -> Result<Reply, ProbeResult> {
+                let start = Instant::now();
+                let response = match request.send().await {
+                    Ok(response) => response,
+                    Err(e) if e.is_timeout() => return Err(ProbeResult::Timeout),
+                    Err(e) => return Err(ProbeResult::NetworkError(e.to_string())),
+                };
+
+                let status = response.status().as_u16();
+                let content_type = response
+                    .headers()
+                    .get(CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string();
+                let body = response.bytes().await.unwrap_or_default().to_vec();
+                let latency = start.elapsed();
+                if content_type.starts_with("audio/") || self.endpoint == Endpoint::Image {
+                    info!("Response body: {} bytes of {content_type}", body.len());
+                } else {
+                    info!("Response body: {}", String::from_utf8_lossy(&body));
+                }
+
+                Ok(Reply {
+                    status,
+                    content_type,
+                    body,
+                    latency,
+                })
+            }
+
+            pub fn build_endpoint_url(&self) -> String {
+                let path = self
+                    .config
+                    .endpoint_path
+                    .as_deref()
+                    .unwrap_or_else(|| self.default_endpoint_path());
+
+                match &self.config.azure_deployment {
+                    Some(deployment) => format!(
+                        "{}?api-version={}",
+                        join_url(
+                            &self.config.server_url,
+                            &format!("/openai/deployments/{deployment}"),
+                            path
+                        ),
+                        self.config.azure_api_version
+                    ),
+                    None => join_url(&self.config.server_url, &self.config.base_path, path),
+                }
+            }
+
+            /// Path of the endpoint for the probe type, relative to the API base path
+            fn default_endpoint_path(&self) -> &'static str {
+                match self.endpoint {
+                    Endpoint::ChatCompletion | Endpoint::ToolCall | Endpoint::Vision => {
+                        "/chat/completions"
+                    }
+                    Endpoint::Embedding => "/embeddings",
+                    Endpoint::Transcription => "/audio/transcriptions",
+                    Endpoint::Speech => "/audio/speech",
+                    Endpoint::Image => "/images/generations",
+                    Endpoint::Moderation => "/moderations",
+                }
+            }
+
+            pub fn build_payload(&self) -> serde_json::Value {
+                match self.endpoint {
+                    Endpoint::ChatCompletion => json!({
+                        "model": self.config.model_name,
+                        "messages": [{ "role": "user", "content": "test" }],
+                        "max_tokens": 1,
+                        "priority": -100
+                    }),
+                    Endpoint::Embedding => json!({
+                        "model": self.config.model_name,
+                        "input": "test",
+                        "priority": -100
+                    }),
+                    Endpoint::ToolCall => json!({
+                        "model": self.config.model_name,
+                        "messages": [{
+                            "role": "user",
+                            "content": "What is the weather like in Paris right now?"
+                        }],
+                        "tools": [tool_definition()],
+                        "tool_choice": {
+                            "type": "function",
+                            "function": { "name": TOOL_CALL_FUNCTION }
+                        },
+                        "max_tokens": 64,
+                        "priority": -100
+                    }),
+                    Endpoint::Vision => {
+                        let image_url = format!(
+                            "data:image/png;base64,{}",
+                            STANDARD.encode(VISION_TEST_IMAGE)
+                        );
+                        json!({
+                            "model": self.config.model_name,
+                            "messages": [{
+                                "role": "user",
+                                "content": [
+                                    {
+                                        "type": "text",
+                                        "text": "Describe the colour of this image in one word."
+                                    },
+                                    { "type": "image_url", "image_url": { "url": image_url } }
+                                ]
+                            }],
+                            "max_tokens": 16,
+                            "priority": -100
+                        })
+                    }
+                    // Sent as multipart form fields alongside the audio file, see `build_form`
+                    Endpoint::Transcription => json!({
+                        "model": self.config.model_name
+                    }),
+                    Endpoint::Speech => json!({
+                        "model": self.config.model_name,
+                        "input": SPEECH_TEST_INPUT,
+                        "voice": self.config.voice,
+                        "response_format": "wav"
+                    }),
+                    Endpoint::Image => json!({
+                        "model": self.config.model_name,
+                        "prompt": "A red circle on a white background",
+                        "n": 1,
+                        "size": self.config.image_size
+                    }),
+                    Endpoint::Moderation => json!({
+                        "model": self.config.model_name,
+                        "input": [MODERATION_SAFE_INPUT, MODERATION_FLAGGED_INPUT]
+                    }),
+                }
+            }
+
+            /// Build the multipart form for endpoints that take a file upload
+            pub fn build_form(&self) -> Result<Form> {
+                let file = Part::bytes(self.audio.to_vec())
+                    .file_name("audio.wav")
+                    .mime_str("audio/wav")
+                    .map_err(Error::HttpClient)?;
+
+                let fields = self.build_payload();
+                let form = fields
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|(name, value)| Some((name.clone(), value.as_str()?.to_string())))
+                    .fold(Form::new(), |form, (name, value)| form.text(name, value));
+                Ok(form.part("file", file))
+            }
+
+            /// Check the body of a successful response for the endpoint type.
+            ///
+            /// Returns a human readable reason if the response is not what we expect.
+            /// `max_tokens` is the one of the request sent, which the reported usage is checked
+            /// against.
+            pub async fn validate_response(
+                &self,
+                content_type: &str,
+                body: &[u8],
+                max_tokens: Option<u64>,
+            ) -> Result<(), String> {
+                let text = String::from_utf8_lossy(body);
+                if self.config.check_usage
+                    && matches!(
+                        self.endpoint,
+                        Endpoint::ChatCompletion
+                            | Endpoint::Embedding
+                            | Endpoint::ToolCall
+                            | Endpoint::Vision
+                    )
+                {
+                    validate_usage(&text, max_tokens)?;
+                }
+                match self.endpoint {
+                    Endpoint::ToolCall => validate_tool_call(&text),
+                    Endpoint::Vision => match &self.config.expected_keyword {
+                        Some(keyword) => validate_keyword(&text, keyword),
+                        None => Ok(()),
+                    },
+                    Endpoint::Transcription => {
+                        validate_transcription(&text, self.config.expected_keyword.as_deref())
+                    }
+                    Endpoint::Speech => {
+                        validate_speech(content_type, body, self.config.min_audio_bytes)
+                    }
+                    Endpoint::Image => self.validate_image(&text).await,
+                    Endpoint::Moderation => validate_moderation(&text),
+                    _ => Ok(()),
+                }
+            }
+
+            /// Validate an image generation response: the first image, either inline or behind a
+            /// URL, must decode to an image of the requested size.
+            async fn validate_image(&self, body: &str) -> Result<(), String> {
+                let response: serde_json::Value =
+                    serde_json::from_str(body).map_err(|e| format!("response is not JSON: {e}"))?;
+                let image = &response["data"][0];
+
+                let bytes = if let Some(b64_json) = image["b64_json"].as_str() {
+                    STANDARD
+                        .decode(b64_json)
+                        .map_err(|e| format!("b64_json is not valid base64: {e}"))?
+                } else if let Some(url) = image["url"].as_str() {
+                    let resp = self
+                        .client
+                        .get(url)
+                        .send()
+                        .await
+                        .map_err(|e| format!("failed to fetch image URL: {e}"))?;
+                    if !resp.status().is_success() {
+                        return Err(format!(
+                            "fetching image URL returned HTTP {}",
+                            resp.status()
+                        ));
+                    }
+                    resp.bytes()
+                        .await
+                        .map_err(|e| format!("failed to read image URL: {e}"))?
+                        .to_vec()
+                } else {
+                    return Err("response has no b64_json or url image".to_string());
+                };
+
+                let expected =
+                    parse_image_size(&self.config.image_size).map_err(|e| e.to_string())?;
+                match image_dimensions(&bytes) {
+                    Some(dimensions) if dimensions == expected => Ok(()),
+                    Some((width, height)) => Err(format!(
+                        "expected a {}x{} image, got {width}x{height}",
+                        expected.0, expected.1
+                    )),
+                    None => Err("image is not a decodable PNG or JPEG".to_string()),
+                }
+            }
+        }
+
+        /// One second, 16kHz mono tone uploaded by the transcription probe when no audio file is
+        /// configured. It exercises audio decoding without relying on any particular transcript.
+        const TRANSCRIPTION_TEST_AUDIO: &[u8] = include_bytes!("../assets/transcription-test.wav");
+
+        /// Text the text-to-speech probe asks to be spoken
+        const SPEECH_TEST_INPUT: &str = "The quick brown fox jumps over the lazy dog.";
+
+        /// Input the moderation probe expects to pass the safety classifier
+        const MODERATION_SAFE_INPUT: &str =
+            "I baked a loaf of bread this morning and it came out well.";
+
+        /// Input the moderation probe expects to be flagged by the safety classifier
+        const MODERATION_FLAGGED_INPUT: &str =
+            "I am going to kill you and your whole family tonight, and make them suffer first.";
+
+        /// Validate that the moderation endpoint passed the safe input and flagged the violating one
+        fn validate_moderation(body: &str) -> Result<(), String> {
+            let response: serde_json::Value =
+                serde_json::from_str(body).map_err(|e| format!("response is not JSON: {e}"))?;
+
+            let flagged = |index: usize| {
+                response
+                    .pointer(&format!("/results/{index}/flagged"))
+                    .and_then(|flagged| flagged.as_bool())
+                    .ok_or_else(|| format!("response has no flagged result for input {index}"))
+            };
+
+            match (flagged(0)?, flagged(1)?) {
+                (false, true) => Ok(()),
+                (true, true) => Err("safe input was flagged".to_string()),
+                (false, false) => Err("violating input was not flagged".to_string()),
+                (true, false) => {
+                    Err("safe input was flagged and violating input was not".to_string())
+                }
+            }
+        }
+
+        /// Parse an image size of the form WIDTHxHEIGHT
+        fn parse_image_size(size: &str) -> Result<(u32, u32)> {
+            let invalid = || Error::InvalidConfig {
+                field: "image size",
+                reason: format!("{size} is not of the form WIDTHxHEIGHT"),
+            };
+
+            let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+            Ok((
+                width.parse().map_err(|_| invalid())?,
+                height.parse().map_err(|_| invalid())?,
+            ))
+        }
+
+        /// Read the dimensions of a PNG or JPEG image from its header
+        fn image_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+            const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+            if bytes.starts_with(PNG_SIGNATURE) {
+                // The IHDR chunk always comes first, holding the width and height
+                let width = u32::from_be_bytes(bytes.get(16..20)?.try_into().ok()?);
+                let height = u32::from_be_bytes(bytes.get(20..24)?.try_into().ok()?);
+                return Some((width, height));
+            }
+
+            if bytes.starts_with(&[0xFF, 0xD8]) {
+                // Walk the JPEG segments until a start of frame marker, which holds the dimensions
+                let mut offset = 2;
+                while offset + 9 < bytes.len() {
+                    if bytes[offset] != 0xFF {
+                        return None;
+                    }
+                    let marker = bytes[offset + 1];
+                    let length =
+                        u16::from_be_bytes([bytes[offset + 2], bytes[offset + 3]]) as usize;
+                    if matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+                        let height = u16::from_be_bytes([bytes[offset + 5], bytes[offset + 6]]);
+                        let width = u16::from_be_bytes([bytes[offset + 7], bytes[offset + 8]]);
+                        return Some((width.into(), height.into()));
+                    }
+                    offset += 2 + length;
+                }
+            }
+
+            None
+        }
+
+        /// Validate a transcription response, optionally requiring the transcript to contain a keyword
+        fn validate_transcription(body: &str, keyword: Option<&str>) -> Result<(), String> {
+            let response: serde_json::Value =
+                serde_json::from_str(body).map_err(|e| format!("response is not JSON: {e}"))?;
+
+            let text = response["text"]
+                .as_str()
+                .ok_or("response has no transcript text")?;
+
+            match keyword {
+                Some(keyword) if !text.to_lowercase().contains(&keyword.to_lowercase()) => Err(
+                    format!("expected transcript to contain '{keyword}', got '{text}'"),
+                ),
+                _ => Ok(()),
+            }
+        }
+
+        /// Validate a text-to-speech response is WAV audio of at least the given size, with a
+        /// non-zero duration
+        fn validate_speech(
+            content_type: &str,
+            body: &[u8],
+            min_bytes: usize,
+        ) -> Result<(), String> {
+            if !content_type.starts_with("audio/") {
+                return Err(format!(
+                    "expected an audio content type, got '{content_type}'"
+                ));
+            }
+            if body.len() < min_bytes {
+                return Err(format!(
+                    "expected at least {min_bytes} bytes of audio, got {}",
+                    body.len()
+                ));
+            }
+            match wav_duration(body) {
+                Some(duration) if !duration.is_zero() => Ok(()),
+                Some(_) => Err("expected audio with a duration, got an empty WAV".to_string()),
+                None => Err("expected WAV audio, got an unreadable header".to_string()),
+            }
+        }
+
+        /// Read the duration of WAV audio from its header
+        fn wav_duration(bytes: &[u8]) -> Option<Duration> {
+            if bytes.get(0..4)? != b"RIFF" || bytes.get(8..12)? != b"WAVE" {
+                return None;
+            }
+
+            // Walk the chunks for the format, holding the byte rate, and the samples
+            let mut byte_rate = None;
+            let mut offset = 12;
+            while offset + 8 <= bytes.len() {
+                let id = &bytes[offset..offset + 4];
+                let length =
+                    u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().ok()?) as usize;
+                let data = offset + 8;
+                match id {
+                    b"fmt " => {
+                        byte_rate = Some(u32::from_le_bytes(
+                            bytes.get(data + 8..data + 12)?.try_into().ok()?,
+                        ));
+                    }
+                    b"data" => {
+                        // Streamed WAV leaves the length unset, so count the bytes received
+                        let samples = length.min(bytes.len() - data);
+                        return match byte_rate? {
+                            0 => None,
+                            rate => Some(Duration::from_secs_f64(samples as f64 / rate as f64)),
+                        };
+                    }
+                    _ => {}
+                }
+                // Chunks are padded to an even length
+                offset = data.checked_add(length)?.checked_add(length % 2)?;
+            }
+
+            None
+        }
+
+        /// Solid red PNG sent with the vision probe
+        const VISION_TEST_IMAGE: &[u8] = include_bytes!("../assets/vision-test.png");
+
+        /// Extract the assistant message content from a chat completion response
+        fn chat_content(body: &str) -> Result<String, String> {
+            let response: serde_json::Value =
+                serde_json::from_str(body).map_err(|e| format!("response is not JSON: {e}"))?;
+
+            response
+                .pointer("/choices/0/message/content")
+                .and_then(|content| content.as_str())
+                .map(str::to_string)
+                .ok_or_else(|| "response has no message content".to_string())
+        }
+
+        /// Validate the token usage reported in a chat completion or embedding response: prompt
+        /// tokens must be counted, completion tokens must not exceed `max_tokens`, or be absent
+        /// or zero for requests without a completion, and the total must add up
+        fn validate_usage(body: &str, max_tokens: Option<u64>) -> Result<(), String> {
+            let response: serde_json::Value =
+                serde_json::from_str(body).map_err(|e| format!("response is not JSON: {e}"))?;
+            let usage = response
+                .get("usage")
+                .filter(|usage| usage.is_object())
+                .ok_or_else(|| "response has no usage".to_string())?;
+            let count = |field: &str| match usage.get(field) {
+                None | Some(serde_json::Value::Null) => Ok(None),
+                Some(value) => value
+                    .as_u64()
+                    .map(Some)
+                    .ok_or_else(|| format!("usage.{field} is not a token count: {value}")),
+            };
+
+            let prompt =
+                count("prompt_tokens")?.ok_or_else(|| "usage has no prompt_tokens".to_string())?;
+            if prompt == 0 {
+                return Err("usage.prompt_tokens is 0".to_string());
+            }
+            let completion = match (count("completion_tokens")?, max_tokens) {
+                (Some(completion), Some(max_tokens)) if completion > max_tokens => {
+                    return Err(format!(
+                        "usage.completion_tokens is {completion}, more than the {max_tokens} \
+                         max_tokens requested"
+                    ));
+                }
+                (Some(completion), Some(_)) => completion,
+                (None, Some(_)) => return Err("usage has no completion_tokens".to_string()),
+                (Some(completion), None) if completion > 0 => {
+                    return Err(format!(
+                        "usage.completion_tokens is {completion} for a request without a \
+                         completion"
+                    ));
+                }
+                (_, None) => 0,
+            };
+            let total =
+                count("total_tokens")?.ok_or_else(|| "usage has no total_tokens".to_string())?;
+            if total != prompt + completion {
+                return Err(format!(
+                    "usage.total_tokens is {total}, but prompt_tokens and completion_tokens add \
+                     up to {}",
+                    prompt + completion
+                ));
+            }
+            Ok(())
+        }
+
+        /// Validate that the answer in a chat completion response contains the expected keyword
+        fn validate_keyword(body: &str, keyword: &str) -> Result<(), String> {
+            let content = chat_content(body)?;
+            if content.to_lowercase().contains(&keyword.to_lowercase()) {
+                Ok(())
+            } else {
+                Err(format!(
+                    "expected answer to mention '{keyword}', got '{content}'"
+                ))
+            }
+        }
+
+        /// Prompt of the deterministic chat requests, asking for an answer long enough that
+        /// diverging generations show
+        const DETERMINISTIC_PROMPT: &str =
+            "List the first ten prime numbers, then explain in two sentences why 1 is not prime.";
+
+        /// Chat completion request that should always get the same answer, sampling greedily
+        /// with a fixed seed
+        fn deterministic_chat_payload(model_name: &str, seed: u64) -> serde_json::Value {
+            json!({
+                "model": model_name,
+                "messages": [{ "role": "user", "content": DETERMINISTIC_PROMPT }],
+                "max_tokens": 64,
+                "temperature": 0,
+                "seed": seed,
+                "priority": -100
+            })
+        }
+
+        /// Name of the function the tool-calling probe forces the model to call
+        const TOOL_CALL_FUNCTION: &str = "get_current_weather";
+
+        /// Tool definition sent with the tool-calling probe
+        fn tool_definition() -> serde_json::Value {
+            json!({
+                "type": "function",
+                "function": {
+                    "name": TOOL_CALL_FUNCTION,
+                    "description": "Get the current weather in a given city",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "city": {
+                                "type": "string",
+                                "description": "The city to get the weather for"
+                            }
+                        },
+                        "required": ["city"]
+                    }
+                }
+            })
+        }
+
+        /// Validate that a chat completion response contains a call to the forced tool, with
+        /// arguments matching the declared parameters.
+        fn validate_tool_call(body: &str) -> Result<(), String> {
+            let response: serde_json::Value =
+                serde_json::from_str(body).map_err(|e| format!("response is not JSON: {e}"))?;
+
+            let tool_call = response
+                .pointer("/choices/0/message/tool_calls/0")
+                .ok_or("response has no tool_calls")?;
+
+            let name = tool_call
+                .pointer("/function/name")
+                .and_then(|name| name.as_str())
+                .ok_or("tool call has no function name")?;
+            if name != TOOL_CALL_FUNCTION {
+                return Err(format!(
+                    "expected a call to {TOOL_CALL_FUNCTION}, got a call to {name}"
+                ));
+            }
+
+            let arguments = tool_call
+                .pointer("/function/arguments")
+                .and_then(|arguments| arguments.as_str())
+                .ok_or("tool call has no arguments")?;
+            let arguments: serde_json::Value = serde_json::from_str(arguments)
+                .map_err(|e| format!("tool call arguments are not valid JSON: {e}"))?;
+
+            let parameters = &tool_definition()["function"]["parameters"];
+            check_json_schema(&arguments, parameters)
+                .map_err(|e| format!("tool call arguments {e}"))
+        }
+
+        /// Minimal JSON schema check, covering the `type`, `properties` and `required` keywords.
+        fn check_json_schema(
+            value: &serde_json::Value,
+            schema: &serde_json::Value,
+        ) -> Result<(), String> {
+            if let Some(expected) = schema["type"].as_str() {
+                let matches = match expected {
+                    "object" => value.is_object(),
+                    "array" => value.is_array(),
+                    "string" => value.is_string(),
+                    "number" => value.is_number(),
+                    "integer" => value.is_i64() || value.is_u64(),
+                    "boolean" => value.is_boolean(),
+                    "null" => value.is_null(),
+                    _ => true,
+                };
+                if !matches {
+                    return Err(format!("should be of type {expected}, got {value}"));
+                }
+            }
+
+            if let Some(required) = schema["required"].as_array() {
+                for field in required.iter().filter_map(|field| field.as_str()) {
+                    if value.get(field).is_none() {
+                        return Err(format!("are missing required field '{field}'"));
+                    }
+                }
+            }
+
+            if let Some(properties) = schema["properties"].as_object() {
+                for (field, property_schema) in properties {
+                    if let Some(property) = value.get(field) {
+                        check_json_schema(property, property_schema)
+                            .map_err(|e| format!("field '{field}' {e}"))?;
+                    }
+                }
+            }
+
+            Ok(())
+        }
+
+        mod bench {
+            use std::{
+                collections::BTreeMap,
+                fmt,
+                sync::Arc,
+                time::{Duration, Instant},
+            };
+            use tracing::info;
+
+            use super::{Endpoint, OpenAI, OpenAIConfig};
+            use crate::{Error, ProbeResult, Result};
+
+            /// Configuration for a [`Bench`] run
+            #[derive(Debug, Clone, PartialEq)]
+            pub struct BenchConfig {
+                /// Endpoint to load, queried with the same request as the probe
+                pub probe: OpenAIConfig,
+                /// Number of clients sending requests back to back
+                pub concurrency: usize,
+                /// How long the clients keep sending requests
+                pub duration: Duration,
+                /// Optional: replace the probe payload's `max_tokens`, e.g. to measure
+                /// generation throughput rather than request overhead
+                pub max_tokens: Option<u32>,
+            }
+
+            impl Default for BenchConfig {
+                fn default() -> Self {
+                    BenchConfig {
+                        probe: OpenAIConfig::default(),
+                        concurrency: 8,
+                        duration: Duration::from_secs(30),
+                        max_tokens: None,
+                    }
+                }
+            }
+
+            impl BenchConfig {
+                pub fn new(probe: OpenAIConfig) -> Self {
+                    BenchConfig {
+                        probe,
+                        ..Default::default()
+                    }
+                }
+
+                pub fn concurrency(mut self, concurrency: usize) -> Self {
+                    self.concurrency = concurrency;
+                    self
+                }
+
+                pub fn duration(mut self, duration: Duration) -> Self {
+                    self.duration = duration;
+                    self
+                }
+
+                pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+                    self.max_tokens = Some(max_tokens);
+                    self
+                }
+
+                pub fn build(self) -> Result<Bench> {
+                    Bench::new(self)
+                }
+            }
+
+            /// Load generator driving concurrent clients through the [`OpenAI`] probe's request
+            /// path, with its payload, authentication and response checks, so that capacity
+            /// tests and monitoring agree on what a successful request is
+            pub struct Bench {
+                config: BenchConfig,
+                probe: Arc<OpenAI>,
+                /// Payload of every request, built once. `None` for the transcription probe,
+                /// whose multipart form is built for each request.
+                payload: Option<serde_json::Value>,
+            }
+
+            impl Bench {
+                pub fn new(config: BenchConfig) -> Result<Self> {
+                    if config.concurrency == 0 {
+                        return Err(Error::InvalidConfig {
+                            field: "bench concurrency",
+                            reason: "at least one client is needed".to_string(),
+                        });
+                    }
+
+                    let probe = OpenAI::new(config.probe.clone())?;
+                    let mut payload =
+                        (probe.endpoint != Endpoint::Transcription).then(|| probe.build_payload());
+                    if let Some(max_tokens) = config.max_tokens {
+                        match payload
+                            .as_mut()
+                            .and_then(|payload| payload.get_mut("max_tokens"))
+                        {
+                            Some(value) => *value = max_tokens.into(),
+                            None => {
+                                return Err(Error::InvalidConfig {
+                                    field: "bench max tokens",
+                                    reason: format!(
+                                        "the {} request has no max_tokens",
+                                        config.probe.endpoint_type.as_str()
+                                    ),
+                                });
+                            }
+                        }
+                    }
+
+                    Ok(Bench {
+                        config,
+                        probe: Arc::new(probe),
+                        payload,
+                    })
+                }
+
+                /// Start building a benchmark, see [`BenchConfig`]
+                pub fn builder(probe: OpenAIConfig) -> BenchConfig {
+                    BenchConfig::new(probe)
+                }
+
+                /// Send requests from every client until the duration is up, and wait for the
+                /// requests still in flight
+                pub async fn run(&self) -> BenchReport {
+                    info!(
+                        "Running {} clients for {} s",
+                        self.config.concurrency,
+                        self.config.duration.as_secs_f64()
+                    );
+                    let start = Instant::now();
+                    let deadline = start + self.config.duration;
+                    let clients: Vec<_> = (0..self.config.concurrency)
+                        .map(|_| {
+                            let (probe, payload) = (self.probe.clone(), self.payload.clone());
+                            tokio::spawn(async move {
+                                let mut samples = Vec::new();
+                                while Instant::now() < deadline {
+                                    let request = match &payload {
+                                        Some(payload) => Ok((
+                                            probe.request().json(payload),
+                                            payload["max_tokens"].as_u64(),
+                                        )),
+                                        None => probe.probe_request(),
+                                    };
+                                    let (request, max_tokens) = match request {
+                                        Ok(request) => request,
+                                        Err(e) => {
+                                            let result = ProbeResult::Misconfigured(e.to_string());
+                                            samples.push((result, Duration::ZERO, 0));
+                                            break;
+                                        }
+                                    };
+                                    let sent = Instant::now();
+                                    let (result, reply) = probe.exchange(request, max_tokens).await;
+                                    let tokens = reply
+                                        .map(|reply| usage_tokens(&reply.body))
+                                        .unwrap_or_default();
+                                    samples.push((result, sent.elapsed(), tokens));
+                                }
+                                samples
+                            })
+                        })
+                        .collect();
+
+                    let mut report = BenchReport::default();
+                    for client in clients {
+                        // Clients are never aborted, so only a panic fails one. Its requests are
+                        // lost, so count it as one failure.
+                        let samples = client.await.unwrap_or_else(|_| {
+                            let result = ProbeResult::Misconfigured("bench client panicked".into());
+                            vec![(result, Duration::ZERO, 0)]
+                        });
+                        for (result, latency, tokens) in samples {
+                            report.requests += 1;
+                            match result {
+                                ProbeResult::Success => {
+                                    report.successes += 1;
+                                    report.tokens += tokens;
+                                    report.latencies.push(latency);
+                                }
+                                result => {
+                                    *report.errors.entry(result.to_string()).or_default() += 1
+                                }
+                            }
+                        }
+                    }
+                    report.elapsed = start.elapsed();
+                    report.latencies.sort();
+                    report
+                }
+            }
+
+            /// Tokens a response reports using: the completion tokens, or the total for
+            /// endpoints without a completion such as embeddings
+            fn usage_tokens(body: &[u8]) -> u64 {
+                let Ok(response) = serde_json::from_slice::<serde_json::Value>(body) else {
+                    return 0;
+                };
+                ["/usage/completion_tokens", "/usage/total_tokens"]
+                    .iter()
+                    .find_map(|pointer| response.pointer(pointer)?.as_u64())
+                    .unwrap_or_default()
+            }
+
+            /// Outcome of a [`Bench`] run
+            #[derive(Debug, Clone, Default, PartialEq)]
+            pub struct BenchReport {
+                /// Time from the first request until the last response
+                pub elapsed: Duration,
+                /// Requests sent
+                pub requests: usize,
+                /// Requests the probe would have counted as a success
+                pub successes: usize,
+                /// Tokens used by the successful requests, see [`BenchReport::tokens_per_second`]
+                pub tokens: u64,
+                /// Latencies of the successful requests, sorted
+                pub latencies: Vec<Duration>,
+                /// Number of failed requests by probe result, e.g. `HTTP 503` or `timeout`
+                pub errors: BTreeMap<String, usize>,
+            }
+
+            impl BenchReport {
+                /// Successful requests per second
+                pub fn requests_per_second(&self) -> f64 {
+                    self.per_second(self.successes as f64)
+                }
+
+                /// Completion tokens per second, or total tokens per second for endpoints
+                /// without a completion such as embeddings, as reported in the responses' usage
+                pub fn tokens_per_second(&self) -> f64 {
+                    self.per_second(self.tokens as f64)
+                }
+
+                /// Latency the given percentage of successful requests didn't exceed, using the
+                /// nearest rank. `None` if no request succeeded.
+                pub fn percentile(&self, percent: f64) -> Option<Duration> {
+                    let rank = (percent / 100.0 * self.latencies.len() as f64).ceil() as usize;
+                    self.latencies
+                        .get(rank.clamp(1, self.latencies.len().max(1)) - 1)
+                        .copied()
+                }
+
+                fn per_second(&self, count: f64) -> f64 {
+                    if self.elapsed.is_zero() {
+                        0.0
+                    } else {
+                        count / self.elapsed.as_secs_f64()
+                    }
+                }
+            }
+
+            impl fmt::Display for BenchReport {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    writeln!(
+                        f,
+                        "Requests: {} in {:.1} s, {} succeeded ({:.1} requests/s)",
+                        self.requests,
+                        self.elapsed.as_secs_f64(),
+                        self.successes,
+                        self.requests_per_second()
+                    )?;
+                    writeln!(
+                        f,
+                        "Tokens:   {} ({:.1} tokens/s)",
+                        self.tokens,
+                        self.tokens_per_second()
+                    )?;
+                    match [50.0, 90.0, 99.0, 100.0].map(|percent| self.percentile(percent)) {
+                        [Some(p50), Some(p90), Some(p99), Some(max)] => writeln!(
+                            f,
+                            "Latency:  p50 {} ms, p90 {} ms, p99 {} ms, max {} ms",
+                            p50.as_millis(),
+                            p90.as_millis(),
+                            p99.as_millis(),
+                            max.as_millis()
+                        )?,
+                        _ => writeln!(f, "Latency:  no successful requests")?,
+                    }
+                    if self.errors.is_empty() {
+                        write!(f, "Errors:   none")
+                    } else {
+                        let errors: Vec<_> = self
+                            .errors
+                            .iter()
+                            .map(|(result, count)| format!("{count} x {result}"))
+                            .collect();
+                        write!(f, "Errors:   {}", errors.join(", "))
+                    }
+                }
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+                use crate::probes::Type;
+                use httpmock::prelude::*;
+                use serde_json::json;
+
+                fn bench(server: &MockServer, endpoint_type: Type) -> BenchConfig {
+                    Bench::builder(OpenAIConfig::new(server.base_url(), endpoint_type))
+                        .concurrency(2)
+                        .duration(Duration::from_millis(200))
+                }
+
+                #[test]
+                fn test_bench_report_percentiles() {
+                    let report = BenchReport {
+                        elapsed: Duration::from_secs(2),
+                        requests: 12,
+                        successes: 10,
+                        tokens: 50,
+                        latencies: (1..=10).map(Duration::from_millis).collect(),
+                        errors: BTreeMap::from([("HTTP 503".to_string(), 2)]),
+                    };
+
+                    assert_eq!(report.percentile(50.0), Some(Duration::from_millis(5)));
+                    assert_eq!(report.percentile(99.0), Some(Duration::from_millis(10)));
+                    assert_eq!(report.percentile(0.0), Some(Duration::from_millis(1)));
+                    assert_eq!(report.requests_per_second(), 5.0);
+                    assert_eq!(report.tokens_per_second(), 25.0);
+                    assert_eq!(
+                        report.to_string(),
+                        "Requests: 12 in 2.0 s, 10 succeeded (5.0 requests/s)\n\
+                         Tokens:   50 (25.0 tokens/s)\n\
+                         Latency:  p50 5 ms, p90 9 ms, p99 10 ms, max 10 ms\n\
+                         Errors:   2 x HTTP 503"
+                    );
+                    assert_eq!(BenchReport::default().percentile(50.0), None);
+                }
+
+                #[tokio::test]
+                async fn test_bench_counts_requests_and_tokens() {
+                    let server = MockServer::start();
+                    let mock = server.mock(|when, then| {
+                        when.method(POST)
+                            .path("/v1/chat/completions")
+                            .json_body_partial(r#"{"max_tokens": 128}"#);
+                        then.status(200).json_body(json!({
+                            "choices": [{"message": {"role": "assistant", "content": "hi"}}],
+                            "usage": {"prompt_tokens": 3, "completion_tokens": 5, "total_tokens": 8}
+                        }));
+                    });
+
+                    let report = bench(&server, Type::OpenAIChatCompletion)
+                        .max_tokens(128)
+                        .build()
+                        .unwrap()
+                        .run()
+                        .await;
+
+                    assert!(report.requests >= 2, "{report:?}");
+                    assert_eq!(mock.hits(), report.requests);
+                    assert_eq!(report.successes, report.requests);
+                    assert_eq!(report.tokens, 5 * report.requests as u64);
+                    assert_eq!(report.latencies.len(), report.requests);
+                    assert!(report.errors.is_empty());
+                }
+
+                #[tokio::test]
+                async fn test_bench_checks_usage_against_sent_max_tokens() {
+                    let server = MockServer::start();
+                    server.mock(|when, then| {
+                        when.method(POST)
+                            .path("/v1/chat/completions")
+                            .json_body_partial(r#"{"max_tokens": 128}"#);
+                        then.status(200).json_body(json!({
+                            "choices": [{"message": {"role": "assistant", "content": "hi"}}],
+                            "usage": {"prompt_tokens": 3, "completion_tokens": 100, "total_tokens": 103}
+                        }));
+                    });
+
+                    let probe = OpenAIConfig::new(server.base_url(), Type::OpenAIChatCompletion)
+                        .check_usage(true);
+                    let report = Bench::builder(probe)
+                        .concurrency(2)
+                        .duration(Duration::from_millis(200))
+                        .max_tokens(128)
+                        .build()
+                        .unwrap()
+                        .run()
+                        .await;
+
+                    assert!(report.requests >= 2, "{report:?}");
+                    assert_eq!(report.successes, report.requests, "{report:?}");
+                    assert_eq!(report.tokens, 100 * report.requests as u64);
+                }
+
+                #[tokio::test]
+                async fn test_bench_error_breakdown() {
+                    let server = MockServer::start();
+                    server.mock(|when, then| {
+                        when.path("/v1/embeddings");
+                        then.status(503);
+                    });
+
+                    let report = bench(&server, Type::OpenAIEmbedding)
+                        .build()
+                        .unwrap()
+                        .run()
+                        .await;
+
+                    assert_eq!(report.successes, 0);
+                    assert_eq!(
+                        report.errors,
+                        BTreeMap::from([("HTTP 503".to_string(), report.requests)])
+                    );
+                    assert!(report.to_string().contains("no successful requests"));
+                }
+
+                #[test]
+                fn test_bench_rejects_invalid_config() {
+                    let probe = OpenAIConfig::new("http://llm", Type::OpenAIEmbedding);
+                    assert!(matches!(
+                        Bench::builder(probe.clone()).concurrency(0).build(),
+                        Err(Error::InvalidConfig {
+                            field: "bench concurrency",
+                            ..
+                        })
+                    ));
+                    assert!(matches!(
+                        Bench::builder(probe).max_tokens(16).build(),
+                        Err(Error::InvalidConfig {
+                            field: "bench max tokens",
+                            ..
+                        })
+                    ));
+                }
+            }
+        }
+
+        /// Minimal HTTP server for tests whose responses depend on the request, which httpmock
+        /// can't serve
+        #[cfg(test)]
+        mod stub {
+            use tokio::{
+                io::{AsyncReadExt, AsyncWriteExt},
+                net::TcpListener,
+            };
+
+            /// Serve JSON requests one connection at a time, answering each with the status and
+            /// JSON body the handler returns for the request's path and body. Returns the
+            /// server's URL.
+            pub async fn serve(
+                mut handler: impl FnMut(&str, serde_json::Value) -> (u16, serde_json::Value)
+                + Send
+                + 'static,
+            ) -> String {
+                let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+                let url = format!("http://{}", listener.local_addr().unwrap());
+                tokio::spawn(async move {
+                    while let Ok((mut stream, _)) = listener.accept().await {
+                        let mut request = Vec::new();
+                        let (path, body) = loop {
+                            let mut chunk = [0; 65536];
+                            let read = stream.read(&mut chunk).await.unwrap();
+                            request.extend_from_slice(&chunk[..read]);
+                            let text = String::from_utf8_lossy(&request);
+                            if let Some((head, body)) = text.split_once("\r\n\r\n")
+                                && let Some(length) = head.lines().find_map(|line| {
+                                    line.to_lowercase()
+                                        .strip_prefix("content-length: ")
+                                        .map(|length| length.parse::<usize>().unwrap())
+                                })
+                                && body.len() >= length
+                            {
+                                let path = head.split(' ').nth(1).unwrap_or_default();
+                                break (path.to_string(), body.to_string());
+                            }
+                        };
+
+                        let (status, reply) = handler(&path, serde_json::from_str(&body).unwrap());
+                        let reply = reply.to_string();
+                        let response = format!(
+                            "HTTP/1.1 {status} Stub\r\ncontent-type: application/json\r\n\
+                             content-length: {}\r\nconnection: close\r\n\r\n{reply}",
+                            reply.len()
+                        );
+                        stream.write_all(response.as_bytes()).await.unwrap();
+                    }
+                });
+                url
+            }
+        }
+
+        mod long_context {
+            use std::time::{SystemTime, UNIX_EPOCH};
+            use tracing::info;
+
+            use super::{OpenAI, OpenAIConfig, Type, chat_content};
+            use crate::{Error, ProbeResult, Result};
+
+            /// Sentences the needle is hidden among, repeated to fill the prompt
+            const FILLER: [&str; 6] = [
+                "The grass is green and the sky is blue.",
+                "The sun is yellow and rises in the east every morning.",
+                "Rivers flow downhill until they reach the sea.",
+                "Most trees lose their leaves in autumn and grow new ones in spring.",
+                "A week has seven days and a year has twelve months.",
+                "Bread is baked in an oven from flour, water, salt and yeast.",
+            ];
+
+            /// Question asked after the filler
+            const QUESTION: &str = "What is the secret passphrase mentioned in the text above? \
+                                    Answer with the passphrase only.";
+
+            /// Characters per token of the prompt assumed without a tokenizer endpoint, typical
+            /// of English text
+            const CHARS_PER_TOKEN: f64 = 4.0;
+
+            /// Most tokens the model may answer with
+            const ANSWER_TOKENS: usize = 32;
+
+            /// Tokens of the context left free besides the answer, as the filler is cut at whole
+            /// sentences and its size is scaled from the tokenizer's count of a sample
+            const MARGIN_TOKENS: usize = 64;
+
+            /// Tokens of the context not filled by the prompt
+            const RESERVED_TOKENS: usize = ANSWER_TOKENS + MARGIN_TOKENS;
+
+            /// Configuration for the [`LongContext`] probe
+            #[derive(Debug, Clone, PartialEq)]
+            pub struct LongContextConfig {
+                /// Chat completion endpoint to probe. Its endpoint type is ignored.
+                pub chat: OpenAIConfig,
+                /// Context window the model should accept, in tokens. The prompt fills it, less
+                /// a few tokens for the answer and a margin.
+                pub context_tokens: usize,
+                /// Optional: path of a tokenizer endpoint on the server URL's host, ignoring the
+                /// URL's own path such as `/v1`, e.g. vLLM's `/tokenize`. Used to size the
+                /// prompt exactly and to check the server's `max_model_len`. The prompt's size is
+                /// estimated without it.
+                pub tokenize_path: Option<String>,
+                /// Position of the needle in the prompt, from 0 (start) to 1 (end)
+                pub needle_depth: f64,
+            }
+
+            impl Default for LongContextConfig {
+                fn default() -> Self {
+                    LongContextConfig {
+                        chat: OpenAIConfig::default(),
+                        context_tokens: 8192,
+                        tokenize_path: None,
+                        needle_depth: 0.5,
+                    }
+                }
+            }
+
+            impl LongContextConfig {
+                pub fn new(chat: OpenAIConfig, context_tokens: usize) -> Self {
+                    LongContextConfig {
+                        chat,
+                        context_tokens,
+                        ..Default::default()
+                    }
+                }
+
+                pub fn tokenize_path(mut self, path: impl Into<String>) -> Self {
+                    self.tokenize_path = Some(path.into());
+                    self
+                }
+
+                pub fn needle_depth(mut self, depth: f64) -> Self {
+                    self.needle_depth = depth;
+                    self
+                }
+
+                pub fn build(self) -> Result<LongContext> {
+                    LongContext::new(self)
+                }
+            }
+
+            /// Probe filling the advertised context window with a prompt hiding a passphrase,
+            /// the needle, and checking the request is accepted and the model retrieves it.
+            /// Catches deployments serving a smaller context window than advertised, which
+            /// short prompts never notice.
+            pub struct LongContext {
+                config: LongContextConfig,
+                chat: OpenAI,
+            }
+
+            /// Token count of a text, from the tokenizer endpoint
+            struct Tokenized {
+                count: usize,
+                max_model_len: Option<usize>,
+            }
+
+            impl LongContext {
+                pub fn new(config: LongContextConfig) -> Result<Self> {
+                    if config.context_tokens <= 2 * RESERVED_TOKENS {
+                        return Err(Error::InvalidConfig {
+                            field: "context tokens",
+                            reason: format!(
+                                "expected more than {} tokens, got {}",
+                                2 * RESERVED_TOKENS,
+                                config.context_tokens
+                            ),
+                        });
+                    }
+                    if !(0.0..=1.0).contains(&config.needle_depth) {
+                        return Err(Error::InvalidConfig {
+                            field: "needle depth",
+                            reason: format!(
+                                "expected a position between 0 and 1, got {}",
+                                config.needle_depth
+                            ),
+                        });
+                    }
+
+                    let chat = OpenAI::new(OpenAIConfig {
+                        endpoint_type: Type::OpenAIChatCompletion,
+                        ..config.chat.clone()
+                    })?;
+                    Ok(LongContext { config, chat })
+                }
+
+                /// Start building a long-context probe, see [`LongContextConfig`]
+                pub fn builder(chat: OpenAIConfig, context_tokens: usize) -> LongContextConfig {
+                    LongContextConfig::new(chat, context_tokens)
+                }
+
+                /// Count the tokens of a prompt with the tokenizer endpoint, as the server would
+                /// for a chat completion, including the question and the chat template
+                async fn tokenize(
+                    &self,
+                    path: &str,
+                    prompt: &str,
+                ) -> std::result::Result<Tokenized, ProbeResult> {
+                    // Resolved against the server's origin, as vLLM serves its tokenizer next to
+                    // rather than under the /v1 API
+                    let url = match reqwest::Url::parse(&self.config.chat.server_url) {
+                        Ok(mut url) => {
+                            url.set_path(path);
+                            url.set_query(None);
+                            url.to_string()
+                        }
+                        Err(e) => {
+                            return Err(ProbeResult::Misconfigured(format!(
+                                "invalid server URL {}: {e}",
+                                self.config.chat.server_url
+                            )));
+                        }
+                    };
+                    // Not sent through `OpenAI::send`, which would log every token
+                    let request = self.chat.post(&url).json(&serde_json::json!({
+                        "model": self.config.chat.model_name,
+                        "messages": [{ "role": "user", "content": prompt }]
+                    }));
+                    let response = match request.send().await {
+                        Ok(response) => response,
+                        Err(e) if e.is_timeout() => return Err(ProbeResult::Timeout),
+                        Err(e) => return Err(ProbeResult::NetworkError(e.to_string())),
+                    };
+                    if !response.status().is_success() {
+                        return Err(ProbeResult::Misconfigured(format!(
+                            "tokenizer endpoint {url} returned HTTP {}",
+                            response.status().as_u16()
+                        )));
+                    }
+
+                    let response: serde_json::Value = response.json().await.map_err(|e| {
+                        ProbeResult::InvalidResponse(format!("tokenizer response is not JSON: {e}"))
+                    })?;
+                    let count = response["count"]
+                        .as_u64()
+                        .or_else(|| Some(response["tokens"].as_array()?.len() as u64))
+                        .ok_or_else(|| {
+                            ProbeResult::InvalidResponse(
+                                "tokenizer response has no count or tokens".to_string(),
+                            )
+                        })?;
+                    Ok(Tokenized {
+                        count: count as usize,
+                        max_model_len: response["max_model_len"].as_u64().map(|len| len as usize),
+                    })
+                }
+            }
+
+            #[async_trait::async_trait]
+            impl crate::Probe for LongContext {
+                async fn probe(&self) -> ProbeResult {
+                    let context_tokens = self.config.context_tokens;
+                    let prompt_tokens = context_tokens - RESERVED_TOKENS;
+                    let passphrase = passphrase();
+                    let needle = format!("The secret passphrase is {passphrase}.");
+                    let prompt = |chars_per_token: f64| {
+                        let filler = haystack(
+                            ((prompt_tokens as f64 * chars_per_token) as usize)
+                                .saturating_sub(QUESTION.len() + 2),
+                            &needle,
+                            self.config.needle_depth,
+                        );
+                        format!("{filler}\n\n{QUESTION}")
+                    };
+
+                    let mut chars_per_token = CHARS_PER_TOKEN;
+                    if let Some(path) = &self.config.tokenize_path {
+                        let sample = prompt(chars_per_token);
+                        let tokenized = match self.tokenize(path, &sample).await {
+                            Ok(tokenized) => tokenized,
+                            Err(result) => return result,
+                        };
+                        if let Some(max_model_len) = tokenized.max_model_len
+                            && max_model_len < context_tokens
+                        {
+                            return ProbeResult::TestFailure(format!(
+                                "the server's max_model_len is {max_model_len}, less than the \
+                                 {context_tokens} tokens expected"
+                            ));
+                        }
+                        chars_per_token = sample.len() as f64 / tokenized.count.max(1) as f64;
+                    }
+
+                    let prompt = prompt(chars_per_token);
+                    info!(
+                        "Sending a prompt of {} characters, about {context_tokens} tokens",
+                        prompt.len()
+                    );
+                    let payload = serde_json::json!({
+                        "model": self.config.chat.model_name,
+                        "messages": [{ "role": "user", "content": prompt }],
+                        "max_tokens": ANSWER_TOKENS,
+                        "temperature": 0,
+                        "priority": -100
+                    });
+                    let reply = match self.chat.send(self.chat.request().json(&payload)).await {
+                        Ok(reply) => reply,
+                        Err(result) => return result,
+                    };
+                    let body = String::from_utf8_lossy(&reply.body);
+
+                    // The server rejecting the prompt as too long is what this probe looks for,
+                    // so report why
+                    if matches!(reply.status, 400 | 413) {
+                        let reason: serde_json::Value =
+                            serde_json::from_str(&body).unwrap_or_default();
+                        let reason = reason
+                            .pointer("/error/message")
+                            .or_else(|| reason.pointer("/message"))
+                            .and_then(|message| message.as_str())
+                            .map(str::to_string)
+                            .unwrap_or_else(|| body.to_string());
+                        return ProbeResult::TestFailure(format!(
+                            "a prompt of about {context_tokens} tokens was rejected with HTTP {}: \
+                             {reason}",
+                            reply.status
+                        ));
+                    }
+                    if !(200..300).contains(&reply.status) {
+                        return ProbeResult::Error(reply.status);
+                    }
+
+                    match chat_content(&body) {
+                        Ok(answer) if answer.to_lowercase().contains(&passphrase) => {
+                            ProbeResult::Success
+                        }
+                        Ok(answer) => ProbeResult::TestFailure(format!(
+                            "the model didn't find the passphrase in a prompt of about \
+                             {context_tokens} tokens: expected '{passphrase}', got '{answer}'"
+                        )),
+                        Err(reason) => ProbeResult::InvalidResponse(reason),
+                    }
+                }
+            }
+
+            /// Passphrase changing with every run, so it can't be answered from a cache
+            fn passphrase() -> String {
+                const WORDS: [&str; 8] = [
+                    "amber", "cobalt", "indigo", "juniper", "marigold", "obsidian", "saffron",
+                    "teal",
+                ];
+                let nanos = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_nanos();
+                format!(
+                    "{}-{:06}",
+                    WORDS[nanos as usize % WORDS.len()],
+                    (nanos / 1000) % 1_000_000
+                )
+            }
+
+            /// Filler of about the given number of characters, with the needle at the given
+            /// depth
+            fn haystack(chars: usize, needle: &str, depth: f64) -> String {
+                let needle_at = (chars as f64 * depth) as usize;
+                let mut sentences = FILLER.iter().cycle();
+                let mut text = String::with_capacity(chars + needle.len() + 128);
+                let mut planted = false;
+                while text.len() < chars {
+                    if !planted && text.len() >= needle_at {
+                        text.push_str(needle);
+                        planted = true;
+                    } else {
+                        text.push_str(sentences.next().expect("filler cycles"));
+                    }
+                    text.push(' ');
+                }
+                if !planted {
+                    text.push_str(needle);
+                }
+                text
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+                use crate::{Probe, probes::openai::stub};
+                use serde_json::json;
+                use std::sync::{Arc, Mutex};
+
+                /// Answer chat completions with the passphrase planted in the prompt, if any,
+                /// rejecting requests whose prompt and answer exceed the window. Prompts count
+                /// five characters per token, to tell the server's count from the probe's
+                /// estimate, plus 20 tokens of chat template.
+                async fn serve_needle(max_model_len: usize) -> (String, Arc<Mutex<Vec<usize>>>) {
+                    let tokens = |request: &serde_json::Value| {
+                        request["messages"][0]["content"].as_str().unwrap().len() / 5 + 20
+                    };
+                    let prompt_lengths = Arc::new(Mutex::new(Vec::new()));
+                    let lengths = prompt_lengths.clone();
+                    let url = stub::serve(move |path, request| {
+                        if path == "/tokenize" {
+                            let count = tokens(&request);
+                            return (200, json!({"count": count, "max_model_len": max_model_len}));
+                        }
+                        let prompt = request["messages"][0]["content"].as_str().unwrap();
+                        lengths.lock().unwrap().push(prompt.len());
+                        let max_tokens = request["max_tokens"].as_u64().unwrap() as usize;
+                        if tokens(&request) + max_tokens > max_model_len {
+                            let message = format!("maximum context length is {max_model_len} tokens");
+                            return (400, json!({"error": {"message": message}}));
+                        }
+                        let answer = prompt
+                            .split("The secret passphrase is ")
+                            .nth(1)
+                            .and_then(|rest| rest.split('.').next())
+                            .unwrap_or("I don't know");
+                        (
+                            200,
+                            json!({"choices": [{"message": {"role": "assistant", "content": answer}}]}),
+                        )
+                    })
+                    .await;
+                    (url, prompt_lengths)
+                }
+
+                fn long_context(url: &str, context_tokens: usize) -> LongContextConfig {
+                    LongContext::builder(
+                        OpenAIConfig::new(url, Type::OpenAILongContext).model_name("llm"),
+                        context_tokens,
+                    )
+                }
+
+                #[test]
+                fn test_haystack_plants_needle() {
+                    let needle = "The secret passphrase is teal-000042.";
+                    for depth in [0.0, 0.5, 1.0] {
+                        let text = haystack(4000, needle, depth);
+                        let position = text.find(needle).unwrap() as f64 / text.len() as f64;
+                        assert!((position - depth).abs() < 0.05, "{depth}: {position}");
+                        assert!((4000..4200).contains(&text.len()));
+                    }
+                }
+
+                #[test]
+                fn test_long_context_rejects_invalid_config() {
+                    let chat = OpenAIConfig::new("http://llm", Type::OpenAILongContext);
+                    assert!(matches!(
+                        LongContext::builder(chat.clone(), 100).build(),
+                        Err(Error::InvalidConfig {
+                            field: "context tokens",
+                            ..
+                        })
+                    ));
+                    assert!(matches!(
+                        LongContext::builder(chat, 8192).needle_depth(1.5).build(),
+                        Err(Error::InvalidConfig {
+                            field: "needle depth",
+                            ..
+                        })
+                    ));
+                }
+
+                #[tokio::test]
+                async fn test_long_context_finds_needle() {
+                    let (url, prompt_lengths) = serve_needle(8192).await;
+
+                    let probe = long_context(&url, 8192).build().unwrap();
+
+                    assert_eq!(probe.probe().await, ProbeResult::Success);
+                    let length = prompt_lengths.lock().unwrap()[0];
+                    assert!((30_000..32_768).contains(&length), "{length}");
+                }
+
+                #[tokio::test]
+                async fn test_long_context_window_too_small() {
+                    let (url, _) = serve_needle(4096).await;
+
+                    let probe = long_context(&url, 8192).build().unwrap();
+                    assert_eq!(
+                        probe.probe().await,
+                        ProbeResult::TestFailure(
+                            "a prompt of about 8192 tokens was rejected with HTTP 400: maximum \
+                             context length is 4096 tokens"
+                                .to_string()
+                        )
+                    );
+
+                    // The tokenizer endpoint reports the window before the prompt is sent
+                    let probe = long_context(&url, 8192)
+                        .tokenize_path("/tokenize")
+                        .build()
+                        .unwrap();
+                    assert_eq!(
+                        probe.probe().await,
+                        ProbeResult::TestFailure(
+                            "the server's max_model_len is 4096, less than the 8192 tokens \
+                             expected"
+                                .to_string()
+                        )
+                    );
+                }
+
+                #[tokio::test]
+                async fn test_long_context_sized_with_tokenizer() {
+                    let (url, prompt_lengths) = serve_needle(16384).await;
+
+                    let probe = long_context(&url, 2048)
+                        .tokenize_path("/tokenize")
+                        .needle_depth(0.9)
+                        .build()
+                        .unwrap();
+
+                    assert_eq!(probe.probe().await, ProbeResult::Success);
+                    let length = prompt_lengths.lock().unwrap()[0];
+                    assert!((9_500..10_240).contains(&length), "{length}");
+                }
+
+                #[tokio::test]
+                async fn test_long_context_margin_at_exact_window() {
+                    let (url, prompt_lengths) = serve_needle(2048).await;
+
+                    // The tokenizer is found at the origin, next to the /v1 API
+                    let probe = long_context(&format!("{url}/v1"), 2048)
+                        .tokenize_path("tokenize")
+                        .build()
+                        .unwrap();
+
+                    assert_eq!(probe.probe().await, ProbeResult::Success);
+                    // The prompt, with its template, is sized to within about a sentence of the
+                    // window less the reserved tokens, so the answer fits
+                    let tokens = prompt_lengths.lock().unwrap()[0] / 5 + 20;
+                    assert!(tokens.abs_diff(2048 - RESERVED_TOKENS) < 16, "{tokens}");
+                    assert!(tokens + ANSWER_TOKENS < 2048, "{tokens}");
+                }
+
+                #[tokio::test]
+                async fn test_long_context_missed_needle() {
+                    let url = stub::serve(|_, _| {
+                        (
+                            200,
+                            json!({"choices": [{"message": {"role": "assistant", "content": "amber-123456"}}]}),
+                        )
+                    })
+                    .await;
+
+                    let probe = long_context(&url, 1024).build().unwrap();
+
+                    match probe.probe().await {
+                        ProbeResult::TestFailure(message) => assert!(
+                            message.starts_with(
+                                "the model didn't find the passphrase in a prompt of about 1024 tokens"
+                            ),
+                            "{message}"
+                        ),
+                        result => panic!("expected a test failure, got {result:?}"),
+                    }
+                }
+            }
+        }
+
+        mod determinism {
+            use std::{collections::HashMap, sync::Arc};
+            use tracing::{info, warn};
+
+            use super::{OpenAI, OpenAIConfig, Type, chat_content, deterministic_chat_payload};
+            use crate::{Error, ProbeResult, Result};
+
+            /// Configuration for the [`Determinism`] probe
+            #[derive(Debug, Clone, PartialEq)]
+            pub struct DeterminismConfig {
+                /// Chat completion endpoint to probe. Its endpoint type is ignored.
+                pub chat: OpenAIConfig,
+                /// Number of identical requests sent, at least 2
+                pub runs: usize,
+                /// Seed sent with every request
+                pub seed: u64,
+                /// Smallest share of the runs that must return the most common output, from 0
+                /// exclusive to 1, where 1 requires every output to be identical
+                pub min_agreement: f64,
+            }
+
+            impl Default for DeterminismConfig {
+                fn default() -> Self {
+                    DeterminismConfig {
+                        chat: OpenAIConfig::default(),
+                        runs: 5,
+                        seed: 42,
+                        min_agreement: 1.0,
+                    }
+                }
+            }
+
+            impl DeterminismConfig {
+                pub fn new(chat: OpenAIConfig) -> Self {
+                    DeterminismConfig {
+                        chat,
+                        ..Default::default()
+                    }
+                }
+
+                pub fn runs(mut self, runs: usize) -> Self {
+                    self.runs = runs;
+                    self
+                }
+
+                pub fn seed(mut self, seed: u64) -> Self {
+                    self.seed = seed;
+                    self
+                }
+
+                pub fn min_agreement(mut self, agreement: f64) -> Self {
+                    self.min_agreement = agreement;
+                    self
+                }
+
+                pub fn build(self) -> Result<Determinism> {
+                    Determinism::new(self)
+                }
+            }
+
+            /// Probe sending the same greedy, seeded chat completion request several times and
+            /// checking the outputs agree, catching e.g. batch-variant kernels breaking
+            /// reproducible generation.
+            ///
+            /// The requests are sent concurrently, so that the server is likely to batch them
+            /// differently.
+            pub struct Determinism {
+                config: DeterminismConfig,
+                chat: Arc<OpenAI>,
+            }
+
+            impl Determinism {
+                pub fn new(config: DeterminismConfig) -> Result<Self> {
+                    if config.runs < 2 {
+                        return Err(Error::InvalidConfig {
+                            field: "determinism runs",
+                            reason: format!("at least 2 runs are needed, got {}", config.runs),
+                        });
+                    }
+                    if !(config.min_agreement > 0.0 && config.min_agreement <= 1.0) {
+                        return Err(Error::InvalidConfig {
+                            field: "minimum agreement",
+                            reason: format!(
+                                "expected a share between 0 and 1, got {}",
+                                config.min_agreement
+                            ),
+                        });
+                    }
+
+                    let chat = OpenAI::new(OpenAIConfig {
+                        endpoint_type: Type::OpenAIChatCompletion,
+                        ..config.chat.clone()
+                    })?;
+                    Ok(Determinism {
+                        chat: Arc::new(chat),
+                        config,
+                    })
+                }
+
+                /// Start building a determinism probe, see [`DeterminismConfig`]
+                pub fn builder(chat: OpenAIConfig) -> DeterminismConfig {
+                    DeterminismConfig::new(chat)
+                }
+            }
+
+            #[async_trait::async_trait]
+            impl crate::Probe for Determinism {
+                async fn probe(&self) -> ProbeResult {
+                    if self.config.runs < 2 {
+                        return ProbeResult::Misconfigured(format!(
+                            "at least 2 determinism runs are needed, got {}",
+                            self.config.runs
+                        ));
+                    }
+                    let payload =
+                        deterministic_chat_payload(&self.config.chat.model_name, self.config.seed);
+                    let tasks: Vec<_> = (0..self.config.runs)
+                        .map(|_| {
+                            let (chat, payload) = (self.chat.clone(), payload.clone());
+                            tokio::spawn(
+                                async move { chat.send(chat.request().json(&payload)).await },
+                            )
+                        })
+                        .collect();
+
+                    let mut outputs = Vec::with_capacity(tasks.len());
+                    for task in tasks {
+                        let reply = match task.await {
+                            Ok(Ok(reply)) if (200..300).contains(&reply.status) => reply,
+                            Ok(Ok(reply)) => return ProbeResult::Error(reply.status),
+                            Ok(Err(result)) => return result,
+                            Err(e) => {
+                                return ProbeResult::Misconfigured(format!(
+                                    "determinism run failed: {e}"
+                                ));
+                            }
+                        };
+                        let output = String::from_utf8(reply.body)
+                            .map_err(|_| "response is not UTF-8".to_string())
+                            .and_then(|body| chat_content(&body));
+                        match output {
+                            Ok(output) => outputs.push(output),
+                            Err(reason) => return ProbeResult::InvalidResponse(reason),
+                        }
+                    }
+
+                    let mut counts: HashMap<&str, usize> = HashMap::new();
+                    for output in &outputs {
+                        *counts.entry(output).or_default() += 1;
+                    }
+                    // Ties go to the earliest output, so the message is stable
+                    let Some((majority, agreeing)) = outputs
+                        .iter()
+                        .map(|output| (output.as_str(), counts[output.as_str()]))
+                        .rev()
+                        .max_by_key(|(_, count)| *count)
+                    else {
+                        return ProbeResult::Misconfigured(
+                            "no determinism run returned an output".to_string(),
+                        );
+                    };
+                    let agreement = agreeing as f64 / outputs.len() as f64;
+                    info!(
+                        "{agreeing} of {} runs returned the same output, {} distinct outputs",
+                        outputs.len(),
+                        counts.len()
+                    );
+                    if agreement >= self.config.min_agreement {
+                        return ProbeResult::Success;
+                    }
+
+                    warn!("Outputs diverged: {outputs:?}");
+                    // Identical outputs agree fully, so there is always an outlier here
+                    let Some(outlier) = outputs.iter().find(|output| output.as_str() != majority)
+                    else {
+                        return ProbeResult::Success;
+                    };
+                    ProbeResult::TestFailure(format!(
+                        "only {agreeing} of {} runs returned the same output, at least {:.0}% \
+                         required: {majority:?} vs {outlier:?}",
+                        outputs.len(),
+                        self.config.min_agreement * 100.0
+                    ))
+                }
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+                use crate::{Probe, probes::openai::stub};
+                use serde_json::json;
+                use std::sync::Mutex;
+
+                /// Serve chat completions answering with the outputs in turn, returning the
+                /// server's URL and the requests' bodies
+                async fn serve_outputs(
+                    outputs: &'static [&'static str],
+                ) -> (String, Arc<Mutex<Vec<serde_json::Value>>>) {
+                    let requests = Arc::new(Mutex::new(Vec::new()));
+                    let received = requests.clone();
+                    let mut outputs = outputs.iter();
+                    let url = stub::serve(move |_, request| {
+                        received.lock().unwrap().push(request);
+                        let content = outputs.next().expect("more requests than outputs");
+                        (
+                            200,
+                            json!({
+                                "choices": [{"message": {"role": "assistant", "content": content}}]
+                            }),
+                        )
+                    })
+                    .await;
+                    (url, requests)
+                }
+
+                fn determinism(url: &str) -> DeterminismConfig {
+                    Determinism::builder(
+                        OpenAIConfig::new(url, Type::OpenAIDeterminism).model_name("llm"),
+                    )
+                    .runs(4)
+                }
+
+                #[test]
+                fn test_determinism_rejects_invalid_config() {
+                    let chat = OpenAIConfig::new("http://llm", Type::OpenAIDeterminism);
+                    assert!(matches!(
+                        Determinism::builder(chat.clone()).runs(1).build(),
+                        Err(Error::InvalidConfig {
+                            field: "determinism runs",
+                            ..
+                        })
+                    ));
+                    assert!(matches!(
+                        Determinism::builder(chat).min_agreement(0.0).build(),
+                        Err(Error::InvalidConfig {
+                            field: "minimum agreement",
+                            ..
+                        })
+                    ));
+                }
+
+                #[tokio::test]
+                async fn test_determinism_single_run_is_misconfigured() {
+                    // Bypasses the check in `Determinism::new`
+                    let chat = OpenAIConfig::new("http://llm", Type::OpenAIChatCompletion);
+                    let probe = Determinism {
+                        chat: Arc::new(OpenAI::new(chat.clone()).unwrap()),
+                        config: Determinism::builder(chat).runs(1),
+                    };
+
+                    assert_eq!(
+                        probe.probe().await,
+                        ProbeResult::Misconfigured(
+                            "at least 2 determinism runs are needed, got 1".to_string()
+                        )
+                    );
+                }
+
+                #[tokio::test]
+                async fn test_determinism_identical_outputs() {
+                    let (url, requests) = serve_outputs(&["2, 3, 5, 7"; 4]).await;
+
+                    let probe = determinism(&url).seed(7).build().unwrap();
+
+                    assert_eq!(probe.probe().await, ProbeResult::Success);
+                    let requests = requests.lock().unwrap();
+                    assert_eq!(requests.len(), 4);
+                    for request in requests.iter() {
+                        assert_eq!(request["model"], "llm");
+                        assert_eq!(request["temperature"], 0);
+                        assert_eq!(request["seed"], 7);
+                    }
+                }
+
+                #[tokio::test]
+                async fn test_determinism_diverging_outputs() {
+                    const OUTPUTS: [&str; 4] =
+                        ["2, 3, 5, 7", "2, 3, 5, 7", "2, 3, 5, 9", "2, 3, 5, 7"];
+
+                    let (url, _server) = serve_outputs(&OUTPUTS).await;
+                    let probe = determinism(&url).build().unwrap();
+                    assert_eq!(
+                        probe.probe().await,
+                        ProbeResult::TestFailure(
+                            r#"only 3 of 4 runs returned the same output, at least 100% required: "2, 3, 5, 7" vs "2, 3, 5, 9""#
+                                .to_string()
+                        )
+                    );
+
+                    // Within the tolerance
+                    let (url, _server) = serve_outputs(&OUTPUTS).await;
+                    let probe = determinism(&url).min_agreement(0.75).build().unwrap();
+                    assert_eq!(probe.probe().await, ProbeResult::Success);
+                }
+            }
+        }
+
+        mod compare {
+            use std::time::Duration;
+            use tracing::{info, warn};
+
+            use super::{
+                Endpoint, OpenAI, OpenAIConfig, Reply, chat_content, deterministic_chat_payload,
+            };
+            use crate::{Error, ProbeResult, Result};
+
+            /// Latencies below this are rounded up to it when comparing them, so that a few
+            /// milliseconds of jitter on a fast endpoint don't count as a slowdown
+            const MIN_COMPARED_LATENCY: Duration = Duration::from_millis(100);
+
+            /// Configuration for the [`Compare`] probe
+            #[derive(Debug, Clone, PartialEq)]
+            pub struct CompareConfig {
+                /// Reference endpoint, e.g. production, probed with a chat completion or
+                /// embedding request
+                pub baseline: OpenAIConfig,
+                /// Base URL of the endpoint compared with the baseline, e.g. a canary. It is
+                /// otherwise queried exactly like the baseline.
+                pub candidate_url: String,
+                /// Seed sent with the chat completion request
+                pub seed: u64,
+                /// Largest ratio of the candidate's latency to the baseline's
+                pub max_latency_ratio: f64,
+                /// Smallest cosine similarity between the two embeddings
+                pub min_similarity: f64,
+            }
+
+            impl Default for CompareConfig {
+                fn default() -> Self {
+                    CompareConfig {
+                        baseline: OpenAIConfig::default(),
+                        candidate_url: String::new(),
+                        seed: 42,
+                        max_latency_ratio: 2.0,
+                        min_similarity: 0.99,
+                    }
+                }
+            }
+
+            impl CompareConfig {
+                pub fn new(baseline: OpenAIConfig, candidate_url: impl Into<String>) -> Self {
+                    CompareConfig {
+                        baseline,
+                        candidate_url: candidate_url.into(),
+                        ..Default::default()
+                    }
+                }
+
+                pub fn seed(mut self, seed: u64) -> Self {
+                    self.seed = seed;
+                    self
+                }
+
+                pub fn max_latency_ratio(mut self, ratio: f64) -> Self {
+                    self.max_latency_ratio = ratio;
+                    self
+                }
+
+                pub fn min_similarity(mut self, similarity: f64) -> Self {
+                    self.min_similarity = similarity;
+                    self
+                }
+
+                pub fn build(self) -> Result<Compare> {
+                    Compare::new(self)
+                }
+            }
+
+            /// Probe sending the same deterministic request to a baseline and a candidate
+            /// endpoint, e.g. production and a canary of a new serving engine, and checking
+            /// the candidate answers with the same status, about as fast, and with the same
+            /// output or a similar embedding
+            pub struct Compare {
+                config: CompareConfig,
+                endpoint: Endpoint,
+                baseline: OpenAI,
+                candidate: OpenAI,
+            }
+
+            impl Compare {
+                pub fn new(config: CompareConfig) -> Result<Self> {
+                    let endpoint_type = config.baseline.endpoint_type;
+                    let endpoint = Endpoint::try_from(endpoint_type)?;
+                    if !matches!(endpoint, Endpoint::ChatCompletion | Endpoint::Embedding) {
+                        return Err(Error::UnsupportedEndpointType {
+                            probe: "comparison",
+                            endpoint_type,
+                        });
+                    }
+                    if config.candidate_url.is_empty() {
+                        return Err(Error::InvalidConfig {
+                            field: "candidate url",
+                            reason: "the comparison probe needs an endpoint to compare with"
+                                .to_string(),
+                        });
+                    }
+
+                    let candidate = OpenAIConfig {
+                        server_url: config.candidate_url.clone(),
+                        ..config.baseline.clone()
+                    };
+                    Ok(Compare {
+                        baseline: OpenAI::new(config.baseline.clone())?,
+                        candidate: OpenAI::new(candidate)?,
+                        endpoint,
+                        config,
+                    })
+                }
+
+                /// Start building a comparison probe, see [`CompareConfig`]
+                pub fn builder(
+                    baseline: OpenAIConfig,
+                    candidate_url: impl Into<String>,
+                ) -> CompareConfig {
+                    CompareConfig::new(baseline, candidate_url)
+                }
+
+                /// Check the outputs of two successful responses match
+                fn compare_outputs(
+                    &self,
+                    baseline: &Reply,
+                    candidate: &Reply,
+                ) -> std::result::Result<Option<String>, String> {
+                    let text = |side: &str, reply: &Reply| {
+                        String::from_utf8(reply.body.clone())
+                            .map_err(|_| format!("{side} response is not UTF-8"))
+                    };
+                    let (baseline, candidate) =
+                        (text("baseline", baseline)?, text("candidate", candidate)?);
+
+                    if self.endpoint == Endpoint::Embedding {
+                        let baseline = embedding(&baseline).map_err(|e| format!("baseline {e}"))?;
+                        let candidate =
+                            embedding(&candidate).map_err(|e| format!("candidate {e}"))?;
+                        if baseline.len() != candidate.len() {
+                            return Ok(Some(format!(
+                                "embedding dimensions differ: baseline {}, candidate {}",
+                                baseline.len(),
+                                candidate.len()
+                            )));
+                        }
+                        let similarity = cosine_similarity(&baseline, &candidate);
+                        info!("Embedding cosine similarity: {similarity:.4}");
+                        return Ok((similarity < self.config.min_similarity).then(|| {
+                            format!(
+                                "embedding cosine similarity {similarity:.4} is below {}",
+                                self.config.min_similarity
+                            )
+                        }));
+                    }
+
+                    let baseline = chat_content(&baseline).map_err(|e| format!("baseline {e}"))?;
+                    let candidate =
+                        chat_content(&candidate).map_err(|e| format!("candidate {e}"))?;
+                    Ok((baseline != candidate).then(|| {
+                        format!("outputs differ: baseline {baseline:?}, candidate {candidate:?}")
+                    }))
+                }
+            }
+
+            #[async_trait::async_trait]
+            impl crate::Probe for Compare {
+                async fn probe(&self) -> ProbeResult {
+                    let payload = match self.endpoint {
+                        Endpoint::Embedding => self.baseline.build_payload(),
+                        _ => deterministic_chat_payload(
+                            &self.config.baseline.model_name,
+                            self.config.seed,
+                        ),
+                    };
+                    let (baseline, candidate) = tokio::join!(
+                        self.baseline.send(self.baseline.request().json(&payload)),
+                        self.candidate.send(self.candidate.request().json(&payload)),
+                    );
+
+                    // Without a healthy baseline there is nothing to compare with, so report it
+                    // like the plain probe would
+                    let baseline = match baseline {
+                        Ok(reply) if (200..300).contains(&reply.status) => reply,
+                        Ok(reply) => {
+                            warn!("The baseline failed, so the candidate can't be compared");
+                            return ProbeResult::Error(reply.status);
+                        }
+                        Err(result) => {
+                            warn!("The baseline failed, so the candidate can't be compared");
+                            return result;
+                        }
+                    };
+                    let candidate = match candidate {
+                        Ok(reply) if reply.status == baseline.status => reply,
+                        Ok(reply) => {
+                            return ProbeResult::TestFailure(format!(
+                                "status differs: baseline HTTP {}, candidate HTTP {}",
+                                baseline.status, reply.status
+                            ));
+                        }
+                        Err(result) => {
+                            return ProbeResult::TestFailure(format!("candidate failed: {result}"));
+                        }
+                    };
+
+                    let mut failures = Vec::new();
+                    let ratio = candidate.latency.max(MIN_COMPARED_LATENCY).as_secs_f64()
+                        / baseline.latency.max(MIN_COMPARED_LATENCY).as_secs_f64();
+                    info!(
+                        "Latency: baseline {} ms, candidate {} ms",
+                        baseline.latency.as_millis(),
+                        candidate.latency.as_millis()
+                    );
+                    if ratio > self.config.max_latency_ratio {
+                        failures.push(format!(
+                            "candidate took {ratio:.1}x as long as the baseline ({} ms vs {} ms), \
+                             at most {}x accepted",
+                            candidate.latency.as_millis(),
+                            baseline.latency.as_millis(),
+                            self.config.max_latency_ratio
+                        ));
+                    }
+                    match self.compare_outputs(&baseline, &candidate) {
+                        Ok(difference) => failures.extend(difference),
+                        Err(reason) => return ProbeResult::InvalidResponse(reason),
+                    }
+
+                    if failures.is_empty() {
+                        ProbeResult::Success
+                    } else {
+                        ProbeResult::TestFailure(failures.join("; "))
+                    }
+                }
+            }
+
+            /// Extract the first embedding from an embedding response
+            fn embedding(body: &str) -> std::result::Result<Vec<f64>, String> {
+                let response: serde_json::Value =
+                    serde_json::from_str(body).map_err(|e| format!("response is not JSON: {e}"))?;
+                response
+                    .pointer("/data/0/embedding")
+                    .and_then(|embedding| embedding.as_array())
+                    .and_then(|embedding| embedding.iter().map(|x| x.as_f64()).collect())
+                    .ok_or_else(|| "response has no embedding".to_string())
+            }
+
+            /// Cosine similarity of two vectors of the same length, 0 if either is all zeros
+            fn cosine_similarity(a: &[f64], b: &[f64]) -> f64 {
+                let dot: f64 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+                let norm = |v: &[f64]| v.iter().map(|x| x * x).sum::<f64>().sqrt();
+                let norms = norm(a) * norm(b);
+                if norms == 0.0 { 0.0 } else { dot / norms }
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+                use crate::{Probe, probes::Type};
+                use httpmock::prelude::*;
+                use serde_json::json;
+
+                fn chat_reply(content: &str) -> serde_json::Value {
+                    json!({"choices": [{"message": {"role": "assistant", "content": content}}]})
+                }
+
+                fn compare(
+                    baseline: &MockServer,
+                    candidate: &MockServer,
+                    endpoint_type: Type,
+                ) -> CompareConfig {
+                    Compare::builder(
+                        OpenAIConfig::new(baseline.base_url(), endpoint_type).model_name("llm"),
+                        candidate.base_url(),
+                    )
+                }
+
+                #[test]
+                fn test_compare_rejects_invalid_config() {
+                    let baseline = OpenAIConfig::new("http://production", Type::OpenAIVision);
+                    assert!(matches!(
+                        Compare::builder(baseline, "http://canary").build(),
+                        Err(Error::UnsupportedEndpointType { .. })
+                    ));
+
+                    let baseline =
+                        OpenAIConfig::new("http://production", Type::OpenAIChatCompletion);
+                    assert!(matches!(
+                        Compare::builder(baseline, "").build(),
+                        Err(Error::InvalidConfig {
+                            field: "candidate url",
+                            ..
+                        })
+                    ));
+                }
+
+                #[tokio::test]
+                async fn test_compare_matching_chat_outputs() {
+                    let (baseline, candidate) = (MockServer::start(), MockServer::start());
+                    let mocks: Vec<_> = [&baseline, &candidate]
+                        .into_iter()
+                        .map(|server| {
+                            server.mock(|when, then| {
+                                when.method(POST)
+                                    .path("/v1/chat/completions")
+                                    .json_body_partial(
+                                        r#"{"model": "llm", "temperature": 0, "seed": 7}"#,
+                                    );
+                                then.status(200).json_body(chat_reply("2, 3, 5, 7"));
+                            })
+                        })
+                        .collect();
+
+                    let probe = compare(&baseline, &candidate, Type::OpenAIChatCompletion)
+                        .seed(7)
+                        .build()
+                        .unwrap();
+
+                    assert_eq!(probe.probe().await, ProbeResult::Success);
+                    for mock in mocks {
+                        mock.assert();
+                    }
+                }
+
+                #[tokio::test]
+                async fn test_compare_diverging_chat_outputs() {
+                    let (baseline, candidate) = (MockServer::start(), MockServer::start());
+                    baseline.mock(|when, then| {
+                        when.path("/v1/chat/completions");
+                        then.status(200).json_body(chat_reply("2, 3, 5, 7"));
+                    });
+                    candidate.mock(|when, then| {
+                        when.path("/v1/chat/completions");
+                        then.status(200)
+                            .json_body(chat_reply("2, 3, 5, 9"))
+                            .delay(Duration::from_millis(500));
+                    });
+
+                    let probe = compare(&baseline, &candidate, Type::OpenAIChatCompletion)
+                        .build()
+                        .unwrap();
+
+                    match probe.probe().await {
+                        ProbeResult::TestFailure(message) => {
+                            assert!(message.starts_with("candidate took "), "{message}");
+                            assert!(
+                                message.ends_with(
+                                    r#"outputs differ: baseline "2, 3, 5, 7", candidate "2, 3, 5, 9""#
+                                ),
+                                "{message}"
+                            );
+                        }
+                        result => panic!("expected a test failure, got {result:?}"),
+                    }
+                }
+
+                #[tokio::test]
+                async fn test_compare_status_parity() {
+                    let (baseline, candidate) = (MockServer::start(), MockServer::start());
+                    baseline.mock(|when, then| {
+                        when.path("/v1/chat/completions");
+                        then.status(200).json_body(chat_reply("2, 3, 5, 7"));
+                    });
+                    candidate.mock(|when, then| {
+                        when.path("/v1/chat/completions");
+                        then.status(503);
+                    });
+
+                    let probe = compare(&baseline, &candidate, Type::OpenAIChatCompletion)
+                        .build()
+                        .unwrap();
+                    assert_eq!(
+                        probe.probe().await,
+                        ProbeResult::TestFailure(
+                            "status differs: baseline HTTP 200, candidate HTTP 503".to_string()
+                        )
+                    );
+
+                    // A failing baseline is reported as is, since there's nothing to compare
+                    let probe = compare(&candidate, &baseline, Type::OpenAIChatCompletion)
+                        .build()
+                        .unwrap();
+                    assert_eq!(probe.probe().await, ProbeResult::Error(503));
+                }
+
+                #[tokio::test]
+                async fn test_compare_embedding_similarity() {
+                    let (baseline, candidate, drifted) = (
+                        MockServer::start(),
+                        MockServer::start(),
+                        MockServer::start(),
+                    );
+                    for (server, embedding) in [
+                        (&baseline, [0.6, 0.8, 0.0]),
+                        (&candidate, [0.6001, 0.7999, 0.0]),
+                        (&drifted, [0.0, 0.6, 0.8]),
+                    ] {
+                        server.mock(|when, then| {
+                            when.method(POST).path("/v1/embeddings");
+                            then.status(200)
+                                .json_body(json!({"data": [{"embedding": embedding}]}));
+                        });
+                    }
+
+                    let probe = compare(&baseline, &candidate, Type::OpenAIEmbedding)
+                        .build()
+                        .unwrap();
+                    assert_eq!(probe.probe().await, ProbeResult::Success);
+
+                    let probe = compare(&baseline, &drifted, Type::OpenAIEmbedding)
+                        .build()
+                        .unwrap();
+                    assert_eq!(
+                        probe.probe().await,
+                        ProbeResult::TestFailure(
+                            "embedding cosine similarity 0.4800 is below 0.99".to_string()
+                        )
+                    );
+                }
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+            use crate::Probe;
+            use httpmock::prelude::*;
+            use serde_json::json;
+            use std::fs;
+            use tempfile::TempDir;
+
+            #[test]
+            fn test_openai_creation() {
+                let config = OpenAIConfig::default();
+                let probe = OpenAI::new(config);
+                assert!(probe.is_ok());
+            }
+
+            #[test]
+            fn test_openai_rejects_unsupported_type() {
+                let config = OpenAIConfig {
+                    endpoint_type: Type::Newman,
+                    ..Default::default()
+                };
+                let err = OpenAI::new(config).err().unwrap();
+
+                assert!(matches!(
+                    err,
+                    Error::UnsupportedEndpointType {
+                        probe: "OpenAI",
+                        endpoint_type: Type::Newman
+                    }
+                ));
+                assert_eq!(
+                    err.to_string(),
+                    "the OpenAI probe does not support endpoint type Newman"
+                );
+            }
+
+            #[test]
+            fn test_openai_builder() {
+                let probe = OpenAI::builder("http://localhost:8000", Type::OpenAIEmbedding)
+                    .model_name("embed")
+                    .api_key("secret")
+                    .timeout(Duration::from_secs(5))
+                    .build()
+                    .unwrap();
+
+                assert_eq!(
+                    probe.build_endpoint_url(),
+                    "http://localhost:8000/v1/embeddings"
+                );
+                assert_eq!(probe.build_payload()["model"], "embed");
+            }
+
+            #[test]
+            fn test_openai_chat_endpoint_url() {
+                let config = OpenAIConfig {
+                    endpoint_type: Type::OpenAIChatCompletion,
+                    server_url: "https://api.openai.com".to_string(),
+                    ..Default::default()
+                };
+                let probe = OpenAI::new(config).unwrap();
+
+                let url = probe.build_endpoint_url();
+                assert_eq!(url, "https://api.openai.com/v1/chat/completions");
+            }
+
+            #[test]
+            fn test_openai_embedding_endpoint_url() {
+                let config = OpenAIConfig {
+                    endpoint_type: Type::OpenAIEmbedding,
+                    server_url: "https://api.example.com".to_string(),
+                    ..Default::default()
+                };
+                let probe = OpenAI::new(config).unwrap();
+
+                let url = probe.build_endpoint_url();
+                assert_eq!(url, "https://api.example.com/v1/embeddings");
+            }
+
+            #[test]
+            fn test_join_url_normalisation() {
+                let cases = [
+                    ("http://localhost:8000/v1", "/v1", "/chat/completions"),
+                    ("http://localhost:8000/v1/", "/v1", "chat/completions"),
+                    ("http://localhost:8000", "/v1", "/chat/completions"),
+                    ("http://localhost:8000", "v1/", "/v1/chat/completions"),
+                    ("http://localhost:8000/v1", "/v1", "/v1/chat/completions"),
+                ];
+                for (server_url, base_path, path) in cases {
+                    assert_eq!(
+                        join_url(server_url, base_path, path),
+                        "http://localhost:8000/v1/chat/completions",
+                        "joining {server_url} + {base_path} + {path}"
+                    );
+                }
+
+                assert_eq!(
+                    join_url("https://gateway", "/openai/v1", "/embeddings"),
+                    "https://gateway/openai/v1/embeddings"
+                );
+                assert_eq!(
+                    join_url("https://gateway/", "", "/tenant-a/chat"),
+                    "https://gateway/tenant-a/chat"
+                );
+            }
+
+            #[test]
+            fn test_openai_endpoint_path_override() {
+                let config = OpenAIConfig {
+                    server_url: "https://gateway".to_string(),
+                    base_path: "/openai/v1".to_string(),
+                    endpoint_path: Some("/custom/chat".to_string()),
+                    ..Default::default()
+                };
+                let probe = OpenAI::new(config).unwrap();
+
+                assert_eq!(
+                    probe.build_endpoint_url(),
+                    "https://gateway/openai/v1/custom/chat"
+                );
+            }
+
+            #[tokio::test]
+            async fn test_openai_azure_deployment() {
+                let server = MockServer::start();
+
+                let mock = server.mock(|when, then| {
+                    when.method(POST)
+                        .path("/openai/deployments/my-gpt/chat/completions")
+                        .query_param("api-version", "2024-10-21")
+                        .header("api-key", "secret");
+                    then.status(200).json_body(json!({
+                        "choices": [{"message": {"role": "assistant", "content": "Hello"}}]
+                    }));
+                });
+
+                let config = OpenAIConfig {
+                    server_url: server.base_url(),
+                    azure_deployment: Some("my-gpt".to_string()),
+                    api_key: Some("secret".to_string()),
+                    ..Default::default()
+                };
+                let probe = OpenAI::new(config).unwrap();
+
+                assert_eq!(probe.probe().await, ProbeResult::Success);
+                mock.assert();
+            }
+
+            #[tokio::test]
+            async fn test_openai_bearer_auth() {
+                let server = MockServer::start();
+
+                let mock = server.mock(|when, then| {
+                    when.method(POST)
+                        .path("/v1/embeddings")
+                        .header("authorization", "Bearer secret");
+                    then.status(200).json_body(json!({ "data": [] }));
+                });
+
+                let config = OpenAIConfig {
+                    server_url: server.base_url(),
+                    endpoint_type: Type::OpenAIEmbedding,
+                    api_key: Some("secret".to_string()),
+                    ..Default::default()
+                };
+                let probe = OpenAI::new(config).unwrap();
+
+                assert_eq!(probe.probe().await, ProbeResult::Success);
+                mock.assert();
+            }
+
+            #[test]
+            fn test_openai_chat_payload() {
+                let config = OpenAIConfig {
+                    endpoint_type: Type::OpenAIChatCompletion,
+                    model_name: "a-piece-of-cheese".to_string(),
+                    ..Default::default()
+                };
+                let probe = OpenAI::new(config).unwrap();
+
+                let payload = probe.build_payload();
+                let expected = json!({
+                    "model": "a-piece-of-cheese",
+                    "messages": [{ "role": "user", "content": "test" }],
+                    "max_tokens": 1,
+                    "priority": -100
+                });
+
+                assert_eq!(payload, expected);
+            }
+
+            #[test]
+            fn test_openai_embedding_payload() {
+                let config = OpenAIConfig {
+                    endpoint_type: Type::OpenAIEmbedding,
+                    model_name: "text-embedding-ada-002".to_string(),
+                    ..Default::default()
+                };
+                let probe = OpenAI::new(config).unwrap();
+
+                let payload = probe.build_payload();
+                let expected = json!({
+                    "model": "text-embedding-ada-002",
+                    "input": "test",
+                    "priority": -100
+                });
+
+                assert_eq!(payload, expected);
+            }
+
+            #[tokio::test]
+            async fn test_openai_successful_response() {
+                let server = MockServer::start();
+
+                // Mock successful LLM response
+                let mock = server.mock(|when, then| {
+                    when.method(POST)
+                        .path("/v1/chat/completions")
+                        .json_body(json!({
+                            "model": "gpt-4",
+                            "messages": [{ "role": "user", "content": "test" }],
+                            "max_tokens": 1,
+                            "priority": -100
+                        }));
+                    then.status(200).json_body(json!({
+                        "choices": [{"message": {"role": "assistant", "content": "Hello"}}]
+                    }));
+                });
+
+                let config = OpenAIConfig {
+                    server_url: server.base_url(),
+                    endpoint_type: Type::OpenAIChatCompletion,
+                    model_name: "gpt-4".to_string(),
+                    ..Default::default()
+                };
+
+                let probe = OpenAI::new(config).unwrap();
+                let result = probe.probe().await;
+
+                assert_eq!(result, ProbeResult::Success);
+
+                mock.assert();
+            }
+
+            #[tokio::test]
+            async fn test_openai_http_error_response() {
+                let server = MockServer::start();
+
+                // Mock failed LLM response
+                let mock = server.mock(|when, then| {
+                    when.method(POST).path("/v1/embeddings");
+                    then.status(420).json_body(json!({
+                        "error": {"message": "Internal server error"}
+                    }));
+                });
+
+                let config = OpenAIConfig {
+                    server_url: server.base_url(),
+                    endpoint_type: Type::OpenAIEmbedding,
+                    model_name: "text-embedding-ada-002".to_string(),
+                    ..Default::default()
+                };
+
+                let probe = OpenAI::new(config).unwrap();
+                let result = probe.probe().await;
+
+                match result {
+                    ProbeResult::Error(status_code) => {
+                        assert_eq!(status_code, 420);
+                    }
+                    _ => panic!("Expected HTTP error probe result"),
+                }
+
+                mock.assert();
+            }
+
+            #[tokio::test]
+            async fn test_openai_timeout() {
+                let config = OpenAIConfig {
+                    server_url: "http://10.255.255.1:12345".to_string(), // Non-routable IP for timeout
+                    timeout: Duration::from_secs(1),                     // Very short timeout
+                    ..Default::default()
+                };
+
+                let probe = OpenAI::new(config).unwrap();
+                let result = probe.probe().await;
+
+                assert!(matches!(result, ProbeResult::Timeout));
+            }
+
+            #[tokio::test]
+            async fn test_openai_network_error() {
+                let config = OpenAIConfig {
+                    server_url: "http://localhost:99999".to_string(), // Invalid port
+                    ..Default::default()
+                };
+
+                let probe = OpenAI::new(config).unwrap();
+                let result = probe.probe().await;
+
+                match result {
+                    ProbeResult::NetworkError(error) => {
+                        assert!(!error.is_empty());
+                    }
+                    _ => panic!("Expected network error probe result"),
+                }
+            }
+
+            #[test]
+            fn test_openai_tool_call_payload() {
+                let config = OpenAIConfig {
+                    endpoint_type: Type::OpenAIToolCall,
+                    model_name: "gpt-4".to_string(),
+                    ..Default::default()
+                };
+                let probe = OpenAI::new(config).unwrap();
+
+                assert_eq!(
+                    probe.build_endpoint_url(),
+                    "https://api.openai.com/v1/chat/completions"
+                );
+
+                let payload = probe.build_payload();
+                assert_eq!(
+                    payload["tools"][0]["function"]["name"],
+                    "get_current_weather"
+                );
+                assert_eq!(
+                    payload["tool_choice"],
+                    json!({ "type": "function", "function": { "name": "get_current_weather" } })
+                );
+            }
+
+            #[tokio::test]
+            async fn test_openai_tool_call_successful_response() {
+                let server = MockServer::start();
+
+                let mock = server.mock(|when, then| {
+                    when.method(POST)
+                        .path("/v1/chat/completions")
+                        .body_contains("tool_choice");
+                    then.status(200).json_body(json!({
+                        "choices": [{
+                            "message": {
+                                "role": "assistant",
+                                "content": null,
+                                "tool_calls": [{
+                                    "id": "call_1",
+                                    "type": "function",
+                                    "function": {
+                                        "name": "get_current_weather",
+                                        "arguments": "{\"city\": \"Paris\"}"
+                                    }
+                                }]
+                            }
+                        }]
+                    }));
+                });
+
+                let config = OpenAIConfig {
+                    server_url: server.base_url(),
+                    endpoint_type: Type::OpenAIToolCall,
+                    ..Default::default()
+                };
+
+                let probe = OpenAI::new(config).unwrap();
+                let result = probe.probe().await;
+
+                assert_eq!(result, ProbeResult::Success);
+                mock.assert();
+            }
+
+            #[tokio::test]
+            async fn test_openai_tool_call_missing_tool_calls() {
+                let server = MockServer::start();
+
+                let mock = server.mock(|when, then| {
+                    when.method(POST).path("/v1/chat/completions");
+                    then.status(200).json_body(json!({
+                        "choices": [{"message": {"role": "assistant", "content": "It is sunny"}}]
+                    }));
+                });
+
+                let config = OpenAIConfig {
+                    server_url: server.base_url(),
+                    endpoint_type: Type::OpenAIToolCall,
+                    ..Default::default()
+                };
+
+                let probe = OpenAI::new(config).unwrap();
+                let result = probe.probe().await;
+
+                assert_eq!(
+                    result,
+                    ProbeResult::InvalidResponse("response has no tool_calls".to_string())
+                );
+                mock.assert();
+            }
+
+            #[test]
+            fn test_validate_usage() {
+                let response = |usage: serde_json::Value| json!({ "usage": usage }).to_string();
+
+                let chat = response(
+                    json!({"prompt_tokens": 7, "completion_tokens": 1, "total_tokens": 8}),
+                );
+                assert_eq!(validate_usage(&chat, Some(1)), Ok(()));
+                assert_eq!(
+                    validate_usage(&chat, None),
+                    Err(
+                        "usage.completion_tokens is 1 for a request without a completion"
+                            .to_string()
+                    )
+                );
+
+                let embedding = response(json!({"prompt_tokens": 1, "total_tokens": 1}));
+                assert_eq!(validate_usage(&embedding, None), Ok(()));
+                assert_eq!(
+                    validate_usage(&embedding, Some(1)),
+                    Err("usage has no completion_tokens".to_string())
+                );
+
+                for (usage, error) in [
+                    (json!(null), "response has no usage"),
+                    (
+                        json!({"prompt_tokens": 0, "completion_tokens": 1, "total_tokens": 1}),
+                        "usage.prompt_tokens is 0",
+                    ),
+                    (
+                        json!({"prompt_tokens": "7", "completion_tokens": 1, "total_tokens": 8}),
+                        r#"usage.prompt_tokens is not a token count: "7""#,
+                    ),
+                    (
+                        json!({"prompt_tokens": 7, "completion_tokens": 16, "total_tokens": 23}),
+                        "usage.completion_tokens is 16, more than the 1 max_tokens requested",
+                    ),
+                    (
+                        json!({"prompt_tokens": 7, "completion_tokens": 1, "total_tokens": 7}),
+                        "usage.total_tokens is 7, but prompt_tokens and completion_tokens add up \
+                         to 8",
+                    ),
+                ] {
+                    assert_eq!(
+                        validate_usage(&response(usage), Some(1)),
+                        Err(error.to_string())
+                    );
+                }
+            }
+
+            #[tokio::test]
+            async fn test_openai_check_usage() {
+                let server = MockServer::start();
+                server.mock(|when, then| {
+                    when.method(POST).path("/v1/chat/completions");
+                    then.status(200).json_body(json!({
+                        "choices": [{"message": {"role": "assistant", "content": "ok"}}],
+                        "usage": {"prompt_tokens": 7, "completion_tokens": 1, "total_tokens": 8}
+                    }));
+                });
+                server.mock(|when, then| {
+                    when.method(POST).path("/v1/embeddings");
+                    then.status(200)
+                        .json_body(json!({"data": [{"embedding": [0.6, 0.8]}]}));
+                });
+
+                let probe = OpenAI::builder(server.base_url(), Type::OpenAIChatCompletion)
+                    .check_usage(true)
+                    .build()
+                    .unwrap();
+                assert_eq!(probe.probe().await, ProbeResult::Success);
+
+                // Missing usage only fails the probe when it's checked
+                let probe = OpenAI::builder(server.base_url(), Type::OpenAIEmbedding)
+                    .build()
+                    .unwrap();
+                assert_eq!(probe.probe().await, ProbeResult::Success);
+                let probe = OpenAI::builder(server.base_url(), Type::OpenAIEmbedding)
+                    .check_usage(true)
+                    .build()
+                    .unwrap();
+                assert_eq!(
+                    probe.probe().await,
+                    ProbeResult::InvalidResponse("response has no usage".to_string())
+                );
+            }
+
+            #[test]
+            fn test_validate_tool_call_rejects_bad_calls() {
+                let response = |name: &str, arguments: &str| {
+                    json!({
+                        "choices": [{
+                            "message": {
+                                "tool_calls": [{
+                                    "type": "function",
+                                    "function": { "name": name, "arguments": arguments }
+                                }]
+                            }
+                        }]
+                    })
+                    .to_string()
+                };
+
+                assert!(
+                    validate_tool_call(&response("get_current_weather", "{\"city\": \"Paris\"}"))
+                        .is_ok()
+                );
+
+                let err = validate_tool_call(&response("get_stock_price", "{}")).unwrap_err();
+                assert!(err.contains("got a call to get_stock_price"));
+
+                let err = validate_tool_call(&response("get_current_weather", "{\"city\": "))
+                    .unwrap_err();
+                assert!(err.contains("not valid JSON"));
+
+                let err = validate_tool_call(&response("get_current_weather", "{}")).unwrap_err();
+                assert_eq!(err, "tool call arguments are missing required field 'city'");
+
+                let err = validate_tool_call(&response("get_current_weather", "{\"city\": 3}"))
+                    .unwrap_err();
+                assert_eq!(
+                    err,
+                    "tool call arguments field 'city' should be of type string, got 3"
+                );
+            }
+
+            #[test]
+            fn test_openai_vision_payload() {
+                let config = OpenAIConfig {
+                    endpoint_type: Type::OpenAIVision,
+                    ..Default::default()
+                };
+                let probe = OpenAI::new(config).unwrap();
+
+                assert_eq!(
+                    probe.build_endpoint_url(),
+                    "https://api.openai.com/v1/chat/completions"
+                );
+
+                let payload = probe.build_payload();
+                let image_url = payload["messages"][0]["content"][1]["image_url"]["url"]
+                    .as_str()
+                    .unwrap();
+                let image = STANDARD
+                    .decode(image_url.strip_prefix("data:image/png;base64,").unwrap())
+                    .unwrap();
+                assert_eq!(image, VISION_TEST_IMAGE);
+            }
+
+            #[tokio::test]
+            async fn test_openai_vision_expected_keyword() {
+                let server = MockServer::start();
+
+                let mock = server.mock(|when, then| {
+                    when.method(POST)
+                        .path("/v1/chat/completions")
+                        .body_contains("image_url");
+                    then.status(200).json_body(json!({
+                        "choices": [{"message": {"role": "assistant", "content": "Red."}}]
+                    }));
+                });
+
+                let probe = |keyword: &str| {
+                    OpenAI::new(OpenAIConfig {
+                        server_url: server.base_url(),
+                        endpoint_type: Type::OpenAIVision,
+                        expected_keyword: Some(keyword.to_string()),
+                        ..Default::default()
+                    })
+                    .unwrap()
+                };
+
+                assert_eq!(probe("red").probe().await, ProbeResult::Success);
+                assert_eq!(
+                    probe("blue").probe().await,
+                    ProbeResult::InvalidResponse(
+                        "expected answer to mention 'blue', got 'Red.'".into()
+                    )
+                );
+                mock.assert_hits(2);
+            }
+
+            #[tokio::test]
+            async fn test_openai_transcription_response() {
+                let server = MockServer::start();
+
+                let mock = server.mock(|when, then| {
+                    when.method(POST)
+                        .path("/v1/audio/transcriptions")
+                        .header_exists("content-type")
+                        .body_contains("name=\"model\"")
+                        .body_contains("filename=\"audio.wav\"");
+                    then.status(200)
+                        .json_body(json!({ "text": "Hello from the monitor." }));
+                });
+
+                let probe = |keyword: Option<&str>| {
+                    OpenAI::new(OpenAIConfig {
+                        server_url: server.base_url(),
+                        endpoint_type: Type::OpenAITranscription,
+                        expected_keyword: keyword.map(str::to_string),
+                        ..Default::default()
+                    })
+                    .unwrap()
+                };
+
+                assert_eq!(probe(None).probe().await, ProbeResult::Success);
+                assert_eq!(probe(Some("monitor")).probe().await, ProbeResult::Success);
+                assert_eq!(
+                    probe(Some("goodbye")).probe().await,
+                    ProbeResult::InvalidResponse(
+                        "expected transcript to contain 'goodbye', got 'Hello from the monitor.'"
+                            .into()
+                    )
+                );
+                mock.assert_hits(3);
+            }
+
+            #[test]
+            fn test_openai_transcription_audio_path() {
+                let temp_dir = TempDir::new().unwrap();
+                let audio_path = temp_dir.path().join("speech.wav");
+                fs::write(&audio_path, b"RIFF").unwrap();
+
+                let config = OpenAIConfig {
+                    endpoint_type: Type::OpenAITranscription,
+                    audio_path: Some(audio_path.to_str().unwrap().to_string()),
+                    ..Default::default()
+                };
+                let probe = OpenAI::new(config).unwrap();
+                assert_eq!(probe.audio.as_ref(), b"RIFF");
+
+                let config = OpenAIConfig {
+                    endpoint_type: Type::OpenAITranscription,
+                    audio_path: Some("does-not-exist.wav".to_string()),
+                    ..Default::default()
+                };
+                assert!(OpenAI::new(config).is_err());
+            }
+
+            #[tokio::test]
+            async fn test_openai_speech_response() {
+                let server = MockServer::start();
+
+                let mock = server.mock(|when, then| {
+                    when.method(POST).path("/v1/audio/speech").json_body(json!({
+                        "model": "tts-1",
+                        "input": "The quick brown fox jumps over the lazy dog.",
+                        "voice": "alloy",
+                        "response_format": "wav"
+                    }));
+                    then.status(200)
+                        .header("content-type", "audio/wav")
+                        .body(TRANSCRIPTION_TEST_AUDIO);
+                });
+
+                let probe = |min_audio_bytes: usize| {
+                    OpenAI::new(OpenAIConfig {
+                        server_url: server.base_url(),
+                        endpoint_type: Type::OpenAISpeech,
+                        model_name: "tts-1".to_string(),
+                        min_audio_bytes,
+                        ..Default::default()
+                    })
+                    .unwrap()
+                };
+
+                assert_eq!(probe(1024).probe().await, ProbeResult::Success);
+                assert_eq!(
+                    probe(65536).probe().await,
+                    ProbeResult::InvalidResponse(
+                        "expected at least 65536 bytes of audio, got 32044".into()
+                    )
+                );
+                mock.assert_hits(2);
+            }
+
+            #[test]
+            fn test_validate_speech_duration() {
+                assert_eq!(
+                    wav_duration(TRANSCRIPTION_TEST_AUDIO),
+                    Some(Duration::from_secs(1))
+                );
+
+                // Only the header, without any samples
+                let empty = &TRANSCRIPTION_TEST_AUDIO[..44];
+                assert_eq!(
+                    validate_speech("audio/wav", empty, 0).unwrap_err(),
+                    "expected audio with a duration, got an empty WAV"
+                );
+                assert_eq!(
+                    validate_speech("audio/wav", &[0; 2048], 1024).unwrap_err(),
+                    "expected WAV audio, got an unreadable header"
+                );
+            }
+
+            #[test]
+            fn test_validate_speech_content_type() {
+                let err = validate_speech("application/json", b"{}", 0).unwrap_err();
+                assert_eq!(
+                    err,
+                    "expected an audio content type, got 'application/json'"
+                );
+            }
+
+            #[tokio::test]
+            async fn test_openai_image_b64_response() {
+                let server = MockServer::start();
+
+                let mock = server.mock(|when, then| {
+                    when.method(POST)
+                        .path("/v1/images/generations")
+                        .json_body_partial(r#"{ "n": 1, "size": "64x64" }"#);
+                    then.status(200).json_body(json!({
+                        "data": [{ "b64_json": STANDARD.encode(VISION_TEST_IMAGE) }]
+                    }));
+                });
+
+                let config = OpenAIConfig {
+                    server_url: server.base_url(),
+                    endpoint_type: Type::OpenAIImage,
+                    image_size: "64x64".to_string(),
+                    ..Default::default()
+                };
+                let probe = OpenAI::new(config).unwrap();
+
+                assert_eq!(probe.probe().await, ProbeResult::Success);
+                mock.assert();
+            }
+
+            #[tokio::test]
+            async fn test_openai_image_url_response() {
+                let server = MockServer::start();
+
+                let generation_mock = server.mock(|when, then| {
+                    when.method(POST).path("/v1/images/generations");
+                    then.status(200).json_body(json!({
+                        "data": [{ "url": server.url("/images/1.png") }]
+                    }));
+                });
+                let image_mock = server.mock(|when, then| {
+                    when.method(GET).path("/images/1.png");
+                    then.status(200)
+                        .header("content-type", "image/png")
+                        .body(VISION_TEST_IMAGE);
+                });
+
+                let config = OpenAIConfig {
+                    server_url: server.base_url(),
+                    endpoint_type: Type::OpenAIImage,
+                    ..Default::default()
+                };
+                let probe = OpenAI::new(config).unwrap();
+
+                assert_eq!(
+                    probe.probe().await,
+                    ProbeResult::InvalidResponse("expected a 256x256 image, got 64x64".into())
+                );
+                generation_mock.assert();
+                image_mock.assert();
+            }
+
+            #[test]
+            fn test_openai_image_invalid_size() {
+                let config = OpenAIConfig {
+                    endpoint_type: Type::OpenAIImage,
+                    image_size: "large".to_string(),
+                    ..Default::default()
+                };
+                assert!(OpenAI::new(config).is_err());
+            }
+
+            #[test]
+            fn test_image_dimensions() {
+                assert_eq!(image_dimensions(VISION_TEST_IMAGE), Some((64, 64)));
+
+                // SOI, an APP0 segment, then a baseline start of frame for a 320x200 image
+                let jpeg = [
+                    0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0, 0x00, 0x11, 0x08,
+                    0x00, 0xC8, 0x01, 0x40, 0x03,
+                ];
+                assert_eq!(image_dimensions(&jpeg), Some((320, 200)));
+
+                assert_eq!(image_dimensions(b"GIF89a"), None);
+            }
+
+            #[tokio::test]
+            async fn test_openai_moderation_response() {
+                let server = MockServer::start();
+
+                let mock = server.mock(|when, then| {
+                    when.method(POST).path("/v1/moderations").json_body(json!({
+                        "model": "omni-moderation-latest",
+                        "input": [MODERATION_SAFE_INPUT, MODERATION_FLAGGED_INPUT]
+                    }));
+                    then.status(200).json_body(json!({
+                        "results": [{ "flagged": false }, { "flagged": true }]
+                    }));
+                });
+
+                let config = OpenAIConfig {
+                    server_url: server.base_url(),
+                    endpoint_type: Type::OpenAIModeration,
+                    model_name: "omni-moderation-latest".to_string(),
+                    ..Default::default()
+                };
+                let probe = OpenAI::new(config).unwrap();
+
+                assert_eq!(probe.probe().await, ProbeResult::Success);
+                mock.assert();
+            }
+
+            #[test]
+            fn test_validate_moderation_mismatch() {
+                let response = |safe: bool, violating: bool| {
+                    json!({ "results": [{ "flagged": safe }, { "flagged": violating }] })
+                        .to_string()
+                };
+
+                assert_eq!(
+                    validate_moderation(&response(true, true)).unwrap_err(),
+                    "safe input was flagged"
+                );
+                assert_eq!(
+                    validate_moderation(&response(false, false)).unwrap_err(),
+                    "violating input was not flagged"
+                );
+                assert_eq!(
+                    validate_moderation(r#"{"results": []}"#).unwrap_err(),
+                    "response has no flagged result for input 0"
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "http")]
+    mod http {
+        use regex::Regex;
+        use reqwest::{Client, Method, header::HeaderMap};
+        use serde_json::Value;
+        use std::time::{Duration, Instant};
+        use tracing::info;
+
+        use super::{NetworkOptions, TlsOptions, client_builder, json_equal};
+        use crate::{Error, ProbeResult, Result};
+
+        /// Configuration for the [`Http`] probe
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct HttpConfig {
+            /// URL to request, e.g. https://my-tokenizer/health
+            pub url: String,
+            /// HTTP method of the request
+            pub method: String,
+            /// Headers sent with the request
+            pub headers: Vec<(String, String)>,
+            /// Body sent with the request
+            pub body: Option<String>,
+            /// Request timeout
+            pub timeout: Duration,
+            /// Status codes accepted, any 2xx if empty
+            pub expected_status: Vec<u16>,
+            /// Response headers that must be present, with a regex their value must match
+            pub expected_headers: Vec<(String, String)>,
+            /// Slowest response accepted, including reading the body
+            pub max_latency: Option<Duration>,
+            /// Regex the response body must match
+            pub body_regex: Option<String>,
+            /// Values the JSON response body must have at JSONPath expressions, e.g. `$.data[0].id`
+            pub expected_json: Vec<(String, Value)>,
+            /// CA certificates, client certificate and verification of the connection
+            pub tls: TlsOptions,
+            /// Proxy and DNS overrides for the connection
+            pub network: NetworkOptions,
+        }
+
+        impl Default for HttpConfig {
+            fn default() -> Self {
+                HttpConfig {
+                    url: "http://localhost:8000".to_string(),
+                    method: "GET".to_string(),
+                    headers: Vec::new(),
+                    body: None,
+                    timeout: Duration::from_secs(10),
+                    expected_status: Vec::new(),
+                    expected_headers: Vec::new(),
+                    max_latency: None,
+                    body_regex: None,
+                    expected_json: Vec::new(),
+                    tls: TlsOptions::default(),
+                    network: NetworkOptions::default(),
+                }
+            }
+        }
+
+        impl HttpConfig {
+            pub fn new(url: impl Into<String>) -> Self {
+                HttpConfig {
+                    url: url.into(),
+                    ..Default::default()
+                }
+            }
+
+            pub fn method(mut self, method: impl Into<String>) -> Self {
+                self.method = method.into();
+                self
+            }
+
+            /// Send a header with the request, can be repeated
+            pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+                self.headers.push((name.into(), value.into()));
+                self
+            }
+
+            pub fn body(mut self, body: impl Into<String>) -> Self {
+                self.body = Some(body.into());
+                self
+            }
+
+            pub fn timeout(mut self, timeout: Duration) -> Self {
+                self.timeout = timeout;
+                self
+            }
+
+            /// Accept a status code, can be repeated
+            pub fn expect_status(mut self, status: u16) -> Self {
+                self.expected_status.push(status);
+                self
+            }
+
+            /// Require a response header whose value matches a regex, can be repeated
+            pub fn expect_header(
+                mut self,
+                name: impl Into<String>,
+                pattern: impl Into<String>,
+            ) -> Self {
+                self.expected_headers.push((name.into(), pattern.into()));
+                self
+            }
+
+            pub fn max_latency(mut self, max_latency: Duration) -> Self {
+                self.max_latency = Some(max_latency);
+                self
+            }
+
+            pub fn body_regex(mut self, pattern: impl Into<String>) -> Self {
+                self.body_regex = Some(pattern.into());
+                self
+            }
+
+            /// Require a value in the JSON response body, can be repeated
+            pub fn expect_json(mut self, path: impl Into<String>, value: impl Into<Value>) -> Self {
+                self.expected_json.push((path.into(), value.into()));
+                self
+            }
+
+            pub fn tls(mut self, tls: TlsOptions) -> Self {
+                self.tls = tls;
+                self
+            }
+
+            pub fn network(mut self, network: NetworkOptions) -> Self {
+                self.network = network;
+                self
+            }
+
+            pub fn build(self) -> Result<Http> {
+                Http::new(self)
+            }
+        }
+
+        /// Probe making a single HTTP request and checking the response against the
+        /// assertions in its [`HttpConfig`]
+        pub struct Http {
+            client: Client,
+            config: HttpConfig,
+            method: Method,
+            expected_headers: Vec<(String, Regex)>,
+            body_regex: Option<Regex>,
+            expected_json: Vec<(Vec<Segment>, String, Value)>,
+        }
+
+        impl Http {
+            pub fn new(config: HttpConfig) -> Result<Self> {
+                let client = client_builder(&config.tls, &config.network)?
+                    .timeout(config.timeout)
+                    .build()
+                    .map_err(Error::HttpClient)?;
+
+                let method =
+                    Method::from_bytes(config.method.to_uppercase().as_bytes()).map_err(|_| {
+                        Error::InvalidConfig {
+                            field: "method",
+                            reason: format!("{} is not an HTTP method", config.method),
+                        }
+                    })?;
+                let regex = |field, pattern: &str| {
+                    Regex::new(pattern).map_err(|e| Error::InvalidConfig {
+                        field,
+                        reason: e.to_string(),
+                    })
+                };
+                let expected_headers = config
+                    .expected_headers
+                    .iter()
+                    .map(|(name, pattern)| Ok((name.clone(), regex("expected header", pattern)?)))
+                    .collect::<Result<_>>()?;
+                let body_regex = config
+                    .body_regex
+                    .as_deref()
+                    .map(|pattern| regex("body regex", pattern))
+                    .transpose()?;
+                let expected_json = config
+                    .expected_json
+                    .iter()
+                    .map(|(path, value)| {
+                        let segments =
+                            parse_json_path(path).ok_or_else(|| Error::InvalidConfig {
+                                field: "expected JSON",
+                                reason: format!(
+                                    "{path} is not a JSONPath selecting a single value"
+                                ),
+                            })?;
+                        Ok((segments, path.clone(), value.clone()))
+                    })
+                    .collect::<Result<_>>()?;
+
+                Ok(Http {
+                    client,
+                    config,
+                    method,
+                    expected_headers,
+                    body_regex,
+                    expected_json,
+                })
+            }
+
+            /// Start building an HTTP probe, see [`HttpConfig`]
+            pub fn builder(url: impl Into<String>) -> HttpConfig {
+                HttpConfig::new(url)
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl crate::Probe for Http {
+            async fn probe(&self) -> ProbeResult {
+                info!("Requesting {} {}", self.method, self.config.url);
+
+                let mut request = self.client.request(self.method.clone(), &self.config.url);
+                for (name, value) in &self.config.headers {
+                    request = request.header(name, value);
+                }
+                if let Some(body) = &self.config.body {
+                    request = request.body(body.clone());
+                }
+
+                let started = Instant::now();
+                let (status, headers, body) = match request.send().await {
+                    Ok(resp) => {
+                        let status = resp.status();
+                        let headers = resp.headers().clone();
+                        match resp.text().await {
+                            Ok(body) => (status, headers, body),
+                            Err(e) if e.is_timeout() => return ProbeResult::Timeout,
+                            Err(e) => return ProbeResult::NetworkError(e.to_string()),
+                        }
+                    }
+                    Err(e) if e.is_timeout() => return ProbeResult::Timeout,
+                    Err(e) => return ProbeResult::NetworkError(e.to_string()),
+                };
+                let latency = started.elapsed();
+                info!("HTTP {status} in {}ms: {body}", latency.as_millis());
+
+                let status_ok = if self.config.expected_status.is_empty() {
+                    status.is_success()
+                } else {
+                    self.config.expected_status.contains(&status.as_u16())
+                };
+                if !status_ok {
+                    return ProbeResult::Error(status.as_u16());
+                }
+
+                match self.validate_response(latency, &headers, &body) {
+                    Ok(()) => ProbeResult::Success,
+                    Err(reason) => ProbeResult::InvalidResponse(reason),
+                }
+            }
+        }
+
+        impl Http {
+            fn validate_response(
+                &self,
+                latency: Duration,
+                headers: &HeaderMap,
+                body: &str,
+            ) -> Result<(), String> {
+                if let Some(max_latency) = self.config.max_latency
+                    && latency > max_latency
+                {
+                    return Err(format!(
+                        "response took {}ms, more than the {}ms allowed",
+                        latency.as_millis(),
+                        max_latency.as_millis()
+                    ));
+                }
+
+                for (name, pattern) in &self.expected_headers {
+                    let value = headers
+                        .get(name)
+                        .ok_or_else(|| format!("response has no {name} header"))?
+                        .to_str()
+                        .unwrap_or_default();
+                    if !pattern.is_match(value) {
+                        return Err(format!(
+                            "header {name} is {value:?}, expected to match {pattern}"
+                        ));
+                    }
+                }
+
+                if let Some(pattern) = &self.body_regex
+                    && !pattern.is_match(body)
+                {
+                    return Err(format!("response body does not match {pattern}"));
+                }
+
+                if !self.expected_json.is_empty() {
+                    let json: Value = serde_json::from_str(body)
+                        .map_err(|e| format!("response is not valid JSON: {e}"))?;
+                    for (segments, path, expected) in &self.expected_json {
+                        match select(&json, segments) {
+                            Some(actual) if json_equal(actual, expected) => {}
+                            Some(actual) => {
+                                return Err(format!("{path} is {actual}, expected {expected}"));
+                            }
+                            None => return Err(format!("response has no value at {path}")),
+                        }
+                    }
+                }
+
+                Ok(())
+            }
+        }
+
+        /// Step of a JSONPath expression
+        #[derive(Debug, Clone, PartialEq)]
+        enum Segment {
+            Key(String),
+            Index(usize),
+        }
+
+        /// Parse the subset of JSONPath selecting a single value, e.g. `$.data[0].id` or
+        /// `$['model-name']`
+        fn parse_json_path(path: &str) -> Option<Vec<Segment>> {
+            let mut rest = path.strip_prefix('$')?;
+            let mut segments = Vec::new();
+            while !rest.is_empty() {
+                if let Some(tail) = rest.strip_prefix('.') {
+                    let end = tail.find(['.', '[']).unwrap_or(tail.len());
+                    if end == 0 {
+                        return None;
+                    }
+                    segments.push(Segment::Key(tail[..end].to_string()));
+                    rest = &tail[end..];
+                } else if let Some(tail) = rest.strip_prefix('[') {
+                    let (inner, tail) = tail.split_once(']')?;
+                    let quoted = ['\'', '"']
+                        .into_iter()
+                        .find_map(|quote| inner.strip_prefix(quote)?.strip_suffix(quote));
+                    segments.push(match quoted {
+                        Some(key) => Segment::Key(key.to_string()),
+                        None => Segment::Index(inner.trim().parse().ok()?),
+                    });
+                    rest = tail;
+                } else {
+                    return None;
+                }
+            }
+            Some(segments)
+        }
+
+        fn select<'a>(value: &'a Value, segments: &[Segment]) -> Option<&'a Value> {
+            segments
+                .iter()
+                .try_fold(value, |value, segment| match segment {
+                    Segment::Key(key) => value.get(key),
+                    Segment::Index(index) => value.get(index),
+                })
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+            use crate::Probe;
+            use httpmock::prelude::*;
+            use serde_json::json;
+
+            #[test]
+            fn test_parse_json_path() {
+                assert_eq!(
+                    parse_json_path("$.data[0]['model-name']"),
+                    Some(vec![
+                        Segment::Key("data".to_string()),
+                        Segment::Index(0),
+                        Segment::Key("model-name".to_string())
+                    ])
+                );
+                assert_eq!(parse_json_path("$"), Some(vec![]));
+                assert_eq!(parse_json_path("data.id"), None);
+                assert_eq!(parse_json_path("$..id"), None);
+                assert_eq!(parse_json_path("$.items[*]"), None);
+            }
+
+            #[test]
+            fn test_http_rejects_invalid_config() {
+                let invalid = [
+                    HttpConfig::new("http://localhost").method("NOT A METHOD"),
+                    HttpConfig::new("http://localhost").body_regex("("),
+                    HttpConfig::new("http://localhost").expect_header("content-type", "["),
+                    HttpConfig::new("http://localhost").expect_json("$.items[*]", 1),
+                ];
+                for config in invalid {
+                    assert!(
+                        matches!(config.build(), Err(Error::InvalidConfig { .. })),
+                        "expected an invalid configuration"
+                    );
+                }
+            }
+
+            #[tokio::test]
+            async fn test_http_probe_successful_assertions() {
+                let server = MockServer::start();
+                let mock = server.mock(|when, then| {
+                    when.method(POST)
+                        .path("/tokenize")
+                        .header("Authorization", "Bearer secret")
+                        .body(r#"{"text":"hello"}"#);
+                    then.status(200)
+                        .header("content-type", "application/json; charset=utf-8")
+                        .json_body(json!({"tokens": [15339], "model": {"name": "llama", "vocab": 128256.0}}));
+                });
+
+                let probe = Http::builder(server.url("/tokenize"))
+                    .method("post")
+                    .header("Authorization", "Bearer secret")
+                    .body(r#"{"text":"hello"}"#)
+                    .expect_header("content-type", "^application/json")
+                    .max_latency(Duration::from_secs(5))
+                    .body_regex(r#""tokens":\s*\[\d+"#)
+                    .expect_json("$.tokens[0]", 15339)
+                    .expect_json("$.model.name", "llama")
+                    .expect_json("$.model['vocab']", 128256)
+                    .build()
+                    .unwrap();
+
+                assert_eq!(probe.probe().await, ProbeResult::Success);
+                mock.assert();
+            }
+
+            #[tokio::test]
+            async fn test_http_probe_status_codes() {
+                let server = MockServer::start();
+                server.mock(|when, then| {
+                    when.path("/down");
+                    then.status(503);
+                });
+                server.mock(|when, then| {
+                    when.path("/missing");
+                    then.status(404);
+                });
+
+                let default = Http::builder(server.url("/down")).build().unwrap();
+                assert_eq!(default.probe().await, ProbeResult::Error(503));
+
+                let not_found = Http::builder(server.url("/missing"))
+                    .expect_status(404)
+                    .build()
+                    .unwrap();
+                assert_eq!(not_found.probe().await, ProbeResult::Success);
+            }
+
+            #[tokio::test]
+            async fn test_http_probe_dns_override() {
+                let server = MockServer::start();
+                let host = format!("replica.invalid:{}", server.port());
+                let mock = server.mock(|when, then| {
+                    when.path("/health").header("host", &host);
+                    then.status(200);
+                });
+                let url = format!("http://{host}/health");
+
+                let probe = Http::builder(&url)
+                    .network(NetworkOptions::new().resolve("replica.invalid", *server.address()))
+                    .build()
+                    .unwrap();
+                assert_eq!(probe.probe().await, ProbeResult::Success);
+                mock.assert();
+
+                let probe = Http::builder(&url).build().unwrap();
+                assert!(matches!(probe.probe().await, ProbeResult::NetworkError(_)));
+            }
+
+            #[tokio::test]
+            async fn test_http_probe_proxy() {
+                let proxy = MockServer::start();
+                let mock = proxy.mock(|when, then| {
+                    when.path("/health");
+                    then.status(200);
+                });
+                let probe = |network| {
+                    Http::builder("http://backend.invalid/health")
+                        .network(network)
+                        .build()
+                        .unwrap()
+                };
+
+                let proxied = probe(NetworkOptions::new().proxy(proxy.base_url()));
+                assert_eq!(proxied.probe().await, ProbeResult::Success);
+                mock.assert();
+
+                let bypassed = probe(NetworkOptions::new().proxy(proxy.base_url()).no_proxy("*"));
+                assert!(matches!(
+                    bypassed.probe().await,
+                    ProbeResult::NetworkError(_)
+                ));
+            }
+
+            #[tokio::test]
+            async fn test_http_probe_failed_assertions() {
+                let server = MockServer::start();
+                server.mock(|when, then| {
+                    when.path("/health");
+                    then.status(200)
+                        .header("content-type", "text/plain")
+                        .body(r#"{"status": "degraded", "checks": []}"#)
+                        .delay(Duration::from_millis(100));
+                });
+                let probe = || Http::builder(server.url("/health"));
+
+                for (config, reason) in [
+                    (
+                        probe().expect_header("content-type", "json"),
+                        r#"header content-type is "text/plain", expected to match json"#,
+                    ),
+                    (
+                        probe().expect_header("x-request-id", "."),
+                        "response has no x-request-id header",
+                    ),
+                    (
+                        probe().body_regex("\"ok\""),
+                        "response body does not match \"ok\"",
+                    ),
+                    (
+                        probe().expect_json("$.status", "ok"),
+                        r#"$.status is "degraded", expected "ok""#,
+                    ),
+                    (
+                        probe().expect_json("$.checks[0]", "database"),
+                        "response has no value at $.checks[0]",
+                    ),
+                ] {
+                    assert_eq!(
+                        config.build().unwrap().probe().await,
+                        ProbeResult::InvalidResponse(reason.to_string())
+                    );
+                }
+
+                match probe()
+                    .max_latency(Duration::from_millis(10))
+                    .build()
+                    .unwrap()
+                    .probe()
+                    .await
+                {
+                    ProbeResult::InvalidResponse(reason) => {
+                        assert!(reason.ends_with("more than the 10ms allowed"), "{reason}");
+                    }
+                    result => panic!("expected an invalid response, got {result:?}"),
+                }
+            }
+
+            #[tokio::test]
+            async fn test_http_probe_timeout() {
+                let server = MockServer::start();
+                server.mock(|when, then| {
+                    when.path("/slow");
+                    then.status(200).delay(Duration::from_secs(2));
+                });
+
+                let probe = Http::builder(server.url("/slow"))
+                    .timeout(Duration::from_millis(100))
+                    .build()
+                    .unwrap();
+
+                assert_eq!(probe.probe().await, ProbeResult::Timeout);
+            }
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    mod tls {
+        use chrono::{DateTime, Utc};
+        use reqwest::Url;
+        use rustls::{
+            CertificateError, ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+            client::{
+                WebPkiServerVerifier,
+                danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+            },
+            crypto::{CryptoProvider, ring},
+            pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime, pem::PemObject},
+        };
+        use std::{
+            sync::{Arc, Mutex},
+            time::Duration,
+        };
+        use tokio::net::TcpStream;
+        use tokio_rustls::TlsConnector;
+        use tracing::{info, warn};
+
+        use super::{NetworkOptions, TlsOptions};
+        use crate::{Error, ProbeResult, Result};
+
+        /// Configuration for the [`Tls`] probe
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct TlsConfig {
+            /// URL of the server whose certificate is checked, on port 443 unless the URL has
+            /// one, e.g. https://api.example.com
+            pub server_url: String,
+            /// Fail when the certificate expires within this many days
+            pub expiry_warning_days: u32,
+            /// Timeout for connecting and completing the handshake
+            pub timeout: Duration,
+            /// CA certificates to trust as well as the public roots, the client certificate to
+            /// present, and whether only the expiry is checked
+            pub tls: TlsOptions,
+            /// DNS overrides for the connection. The handshake is made directly, without a proxy.
+            pub network: NetworkOptions,
+        }
+
+        impl Default for TlsConfig {
+            fn default() -> Self {
+                TlsConfig {
+                    server_url: "https://localhost".to_string(),
+                    expiry_warning_days: 14,
+                    timeout: Duration::from_secs(10),
+                    tls: TlsOptions::default(),
+                    network: NetworkOptions::default(),
+                }
+            }
+        }
+
+        impl TlsConfig {
+            pub fn new(server_url: impl Into<String>) -> Self {
+                TlsConfig {
+                    server_url: server_url.into(),
+                    ..Default::default()
+                }
+            }
+
+            pub fn expiry_warning_days(mut self, days: u32) -> Self {
+                self.expiry_warning_days = days;
+                self
+            }
+
+            pub fn timeout(mut self, timeout: Duration) -> Self {
+                self.timeout = timeout;
+                self
+            }
+
+            pub fn tls(mut self, tls: TlsOptions) -> Self {
+                self.tls = tls;
+                self
+            }
+
+            pub fn network(mut self, network: NetworkOptions) -> Self {
+                self.network = network;
+                self
+            }
+
+            pub fn build(self) -> Result<Tls> {
+                Tls::new(self)
+            }
+        }
+
+        /// Probe completing a TLS handshake with the server and checking its certificate is
+        /// trusted, valid for the host name, and not about to expire
+        pub struct Tls {
+            config: TlsConfig,
+            host: String,
+            port: u16,
+            server_name: ServerName<'static>,
+            provider: Arc<CryptoProvider>,
+            verifier: Arc<WebPkiServerVerifier>,
+            client_certificate: Option<ClientCertificate>,
+        }
+
+        /// Certificate chain and private key presented for mutual TLS
+        type ClientCertificate = (Vec<CertificateDer<'static>>, PrivateKeyDer<'static>);
+
+        impl Tls {
+            pub fn new(config: TlsConfig) -> Result<Self> {
+                let invalid_url = |reason: String| Error::InvalidConfig {
+                    field: "server url",
+                    reason,
+                };
+                let url = Url::parse(&config.server_url)
+                    .map_err(|e| invalid_url(format!("{}: {e}", config.server_url)))?;
+                let host = url
+                    .host_str()
+                    .ok_or_else(|| invalid_url(format!("{} has no host", config.server_url)))?
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .to_string();
+                let server_name = ServerName::try_from(host.clone())
+                    .map_err(|e| invalid_url(format!("{host}: {e}")))?;
+
+                let mut roots = RootCertStore {
+                    roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+                };
+                if let Some(pem) = config.tls.ca_pem()? {
+                    let invalid_ca = |reason: String| Error::InvalidConfig {
+                        field: "CA certificates",
+                        reason,
+                    };
+                    for certificate in CertificateDer::pem_slice_iter(&pem) {
+                        roots
+                            .add(certificate.map_err(|e| invalid_ca(e.to_string()))?)
+                            .map_err(|e| invalid_ca(e.to_string()))?;
+                    }
+                }
+
+                let provider = Arc::new(ring::default_provider());
+                let verifier =
+                    WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                        .build()
+                        .map_err(|e| Error::InvalidConfig {
+                            field: "CA certificates",
+                            reason: e.to_string(),
+                        })?;
+
+                let client_certificate = match config.tls.identity_pem()? {
+                    Some(pem) => {
+                        let invalid_identity = |reason: String| Error::InvalidConfig {
+                            field: "client certificate",
+                            reason,
+                        };
+                        let chain = CertificateDer::pem_slice_iter(&pem)
+                            .collect::<std::result::Result<Vec<_>, _>>()
+                            .map_err(|e| invalid_identity(e.to_string()))?;
+                        if chain.is_empty() {
+                            return Err(invalid_identity("no certificate found".to_string()));
+                        }
+                        let key = PrivateKeyDer::from_pem_slice(&pem)
+                            .map_err(|e| invalid_identity(format!("private key: {e}")))?;
+                        provider
+                            .key_provider
+                            .load_private_key(key.clone_key())
+                            .map_err(|e| invalid_identity(format!("private key: {e}")))?;
+                        Some((chain, key))
+                    }
+                    None => None,
+                };
+
+                Ok(Tls {
+                    port: url.port().unwrap_or(443),
+                    config,
+                    host,
+                    server_name,
+                    provider,
+                    verifier,
+                    client_certificate,
+                })
+            }
+
+            /// Start building a TLS probe, see [`TlsConfig`]
+            pub fn builder(server_url: impl Into<String>) -> TlsConfig {
+                TlsConfig::new(server_url)
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl crate::Probe for Tls {
+            async fn probe(&self) -> ProbeResult {
+                info!(
+                    "Checking the TLS certificate of {}:{}",
+                    self.host, self.port
+                );
+
+                let recorder = Arc::new(RecordingVerifier {
+                    inner: self.verifier.clone(),
+                    verification: Mutex::new(None),
+                });
+                let builder = match ClientConfig::builder_with_provider(self.provider.clone())
+                    .with_safe_default_protocol_versions()
+                {
+                    Ok(builder) => builder
+                        .dangerous()
+                        .with_custom_certificate_verifier(recorder.clone()),
+                    Err(e) => return ProbeResult::NetworkError(e.to_string()),
+                };
+                let config = match &self.client_certificate {
+                    Some((chain, key)) => {
+                        match builder.with_client_auth_cert(chain.clone(), key.clone_key()) {
+                            Ok(config) => config,
+                            Err(e) => {
+                                return ProbeResult::Misconfigured(format!(
+                                    "cannot use the client certificate: {e}"
+                                ));
+                            }
+                        }
+                    }
+                    None => builder.with_no_client_auth(),
+                };
+
+                let handshake = async {
+                    let stream = match self.config.network.resolved(&self.host) {
+                        Some(address) => TcpStream::connect((address.ip(), self.port)).await,
+                        None => TcpStream::connect((self.host.as_str(), self.port)).await,
+                    }
+                    .map_err(|e| format!("failed to connect: {e}"))?;
+                    TlsConnector::from(Arc::new(config))
+                        .connect(self.server_name.clone(), stream)
+                        .await
+                        .map_err(|e| format!("TLS handshake failed: {e}"))
+                };
+                match tokio::time::timeout(self.config.timeout, handshake).await {
+                    Ok(Ok(_stream)) => {}
+                    Ok(Err(reason)) => return ProbeResult::NetworkError(reason),
+                    Err(_) => return ProbeResult::Timeout,
+                }
+
+                let Some((certificate, verification)) =
+                    recorder.verification.lock().unwrap().take()
+                else {
+                    return ProbeResult::InvalidResponse("server sent no certificate".to_string());
+                };
+                let expiry = match not_after(&certificate) {
+                    Ok(expiry) => expiry,
+                    Err(reason) => return ProbeResult::InvalidResponse(reason),
+                };
+                let expiry_date = expiry.format("%Y-%m-%d %H:%M:%S UTC");
+
+                if let Err(e) = verification {
+                    if self.config.tls.insecure {
+                        warn!("Ignoring the rejected certificate in insecure mode: {e}");
+                    } else {
+                        return ProbeResult::InvalidResponse(match e {
+                            rustls::Error::InvalidCertificate(
+                                CertificateError::Expired | CertificateError::ExpiredContext { .. },
+                            ) => format!("certificate expired on {expiry_date}"),
+                            rustls::Error::InvalidCertificate(
+                                CertificateError::NotValidForName
+                                | CertificateError::NotValidForNameContext { .. },
+                            ) => format!(
+                                "certificate is not valid for {} (expires on {expiry_date})",
+                                self.host
+                            ),
+                            rustls::Error::InvalidCertificate(CertificateError::UnknownIssuer) => {
+                                format!(
+                                    "certificate chain is not trusted: unknown issuer (expires on {expiry_date})"
+                                )
+                            }
+                            e => format!("certificate rejected: {e} (expires on {expiry_date})"),
+                        });
+                    }
+                }
+
+                let remaining = expiry - Utc::now();
+                if remaining < chrono::Duration::days(self.config.expiry_warning_days.into()) {
+                    return ProbeResult::InvalidResponse(format!(
+                        "certificate expires on {expiry_date}, within {} days",
+                        self.config.expiry_warning_days
+                    ));
+                }
+
+                info!(
+                    "Certificate is valid until {expiry_date}, in {} days",
+                    remaining.num_days()
+                );
+                ProbeResult::Success
+            }
+        }
+
+        /// Read the end of a certificate's validity period
+        fn not_after(certificate: &CertificateDer) -> Result<DateTime<Utc>, String> {
+            let (_, certificate) = x509_parser::parse_x509_certificate(certificate)
+                .map_err(|e| format!("cannot parse the server certificate: {e}"))?;
+            let timestamp = certificate.validity().not_after.timestamp();
+            DateTime::from_timestamp(timestamp, 0)
+                .ok_or_else(|| format!("certificate expiry {timestamp} is out of range"))
+        }
+
+        /// Server certificate and the outcome of verifying it
+        type Verification = (
+            CertificateDer<'static>,
+            std::result::Result<(), rustls::Error>,
+        );
+
+        /// Verifies the server certificate as usual, but records the outcome rather than
+        /// failing the handshake, so that rejected certificates can still be described
+        #[derive(Debug)]
+        struct RecordingVerifier {
+            inner: Arc<WebPkiServerVerifier>,
+            verification: Mutex<Option<Verification>>,
+        }
+
+        impl ServerCertVerifier for RecordingVerifier {
+            fn verify_server_cert(
+                &self,
+                end_entity: &CertificateDer<'_>,
+                intermediates: &[CertificateDer<'_>],
+                server_name: &ServerName<'_>,
+                ocsp_response: &[u8],
+                now: UnixTime,
+            ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+                let result = self
+                    .inner
+                    .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+                    .map(|_| ());
+                *self.verification.lock().unwrap() =
+                    Some((end_entity.clone().into_owned(), result));
+                Ok(ServerCertVerified::assertion())
+            }
+
+            fn verify_tls12_signature(
+                &self,
+                message: &[u8],
+                cert: &CertificateDer<'_>,
+                dss: &DigitallySignedStruct,
+            ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+                self.inner.verify_tls12_signature(message, cert, dss)
+            }
+
+            fn verify_tls13_signature(
+                &self,
+                message: &[u8],
+                cert: &CertificateDer<'_>,
+                dss: &DigitallySignedStruct,
+            ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+                self.inner.verify_tls13_signature(message, cert, dss)
+            }
+
+            fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+                self.inner.supported_verify_schemes()
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+            use crate::Probe;
+            use rcgen::{
+                BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair,
+            };
+            use rustls::{
+                ServerConfig, pki_types::PrivatePkcs8KeyDer, server::WebPkiClientVerifier,
+            };
+            use std::fs;
+            use tempfile::TempDir;
+            use time::OffsetDateTime;
+            use tokio::{
+                io::{AsyncReadExt, AsyncWriteExt},
+                net::TcpListener,
+            };
+            use tokio_rustls::TlsAcceptor;
+
+            struct TestCa {
+                certificate: rcgen::Certificate,
+                key: KeyPair,
+            }
+
+            impl TestCa {
+                fn new() -> Self {
+                    let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+                    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+                    params
+                        .distinguished_name
+                        .push(DnType::CommonName, "ai-vitals test CA");
+                    let key = KeyPair::generate().unwrap();
+                    TestCa {
+                        certificate: params.self_signed(&key).unwrap(),
+                        key,
+                    }
+                }
+
+                fn write(&self, dir: &TempDir) -> String {
+                    let path = dir.path().join("ca.pem");
+                    fs::write(&path, self.certificate.pem()).unwrap();
+                    path.to_str().unwrap().to_string()
+                }
+
+                /// Issue a client certificate from this CA, returning the certificate and key files
+                fn write_client(&self, dir: &TempDir) -> (String, String) {
+                    let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+                    params
+                        .distinguished_name
+                        .push(DnType::CommonName, "ai-vitals test client");
+                    params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+                    let key = KeyPair::generate().unwrap();
+                    let certificate = params
+                        .signed_by(&key, &self.certificate, &self.key)
+                        .unwrap();
+
+                    let cert_path = dir.path().join("client.pem");
+                    let key_path = dir.path().join("client.key");
+                    fs::write(&cert_path, certificate.pem()).unwrap();
+                    fs::write(&key_path, key.serialize_pem()).unwrap();
+                    (
+                        cert_path.to_str().unwrap().to_string(),
+                        key_path.to_str().unwrap().to_string(),
+                    )
+                }
+
+                /// Serve TLS on a local port with a certificate from this CA, returning the port
+                async fn serve(&self, name: &str, valid_days: (i64, i64)) -> u16 {
+                    self.listen(name, valid_days, false).await
+                }
+
+                /// Serve TLS on a local port, requiring a client certificate from this CA
+                async fn serve_mutual(&self, name: &str) -> u16 {
+                    self.listen(name, (-1, 90), true).await
+                }
+
+                /// Accept TLS connections, answering each request with an empty 200 response
+                async fn listen(&self, name: &str, valid_days: (i64, i64), mutual: bool) -> u16 {
+                    let mut params = CertificateParams::new(vec![name.to_string()]).unwrap();
+                    let now = OffsetDateTime::now_utc();
+                    params.not_before = now + time::Duration::days(valid_days.0);
+                    params.not_after = now + time::Duration::days(valid_days.1);
+                    let key = KeyPair::generate().unwrap();
+                    let certificate = params
+                        .signed_by(&key, &self.certificate, &self.key)
+                        .unwrap();
+
+                    let provider = Arc::new(ring::default_provider());
+                    let builder = ServerConfig::builder_with_provider(provider.clone())
+                        .with_safe_default_protocol_versions()
+                        .unwrap();
+                    let builder = if mutual {
+                        let mut roots = RootCertStore::empty();
+                        roots.add(self.certificate.der().clone()).unwrap();
+                        let verifier =
+                            WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                                .build()
+                                .unwrap();
+                        builder.with_client_cert_verifier(verifier)
+                    } else {
+                        builder.with_no_client_auth()
+                    };
+                    let config = builder
+                        .with_single_cert(
+                            vec![certificate.der().clone()],
+                            PrivatePkcs8KeyDer::from(key.serialize_der()).into(),
+                        )
+                        .unwrap();
+
+                    let acceptor = TlsAcceptor::from(Arc::new(config));
+                    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+                    let port = listener.local_addr().unwrap().port();
+                    tokio::spawn(async move {
+                        while let Ok((stream, _)) = listener.accept().await {
+                            let acceptor = acceptor.clone();
+                            tokio::spawn(async move {
+                                let Ok(mut stream) = acceptor.accept(stream).await else {
+                                    return;
+                                };
+                                let mut request = [0; 4096];
+                                if let Ok(read) = stream.read(&mut request).await
+                                    && read > 0
+                                {
+                                    let _ = stream
+                                        .write_all(
+                                            b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                                        )
+                                        .await;
+                                    let _ = stream.shutdown().await;
+                                }
+                            });
+                        }
+                    });
+                    port
+                }
+            }
+
+            #[test]
+            fn test_tls_rejects_invalid_url() {
+                assert!(matches!(
+                    Tls::builder("not a url").build(),
+                    Err(Error::InvalidConfig {
+                        field: "server url",
+                        ..
+                    })
+                ));
+            }
+
+            #[tokio::test]
+            async fn test_tls_probe_valid_certificate() {
+                let temp_dir = TempDir::new().unwrap();
+                let ca = TestCa::new();
+                let port = ca.serve("127.0.0.1", (-1, 90)).await;
+
+                let probe = Tls::builder(format!("https://127.0.0.1:{port}"))
+                    .tls(TlsOptions::new().ca_cert_path(ca.write(&temp_dir)))
+                    .build()
+                    .unwrap();
+
+                assert_eq!(probe.probe().await, ProbeResult::Success);
+            }
+
+            #[tokio::test]
+            async fn test_tls_probe_certificate_near_expiry() {
+                let temp_dir = TempDir::new().unwrap();
+                let ca = TestCa::new();
+                let port = ca.serve("127.0.0.1", (-60, 5)).await;
+
+                let probe = Tls::builder(format!("https://127.0.0.1:{port}"))
+                    .tls(TlsOptions::new().ca_cert_path(ca.write(&temp_dir)))
+                    .expiry_warning_days(14)
+                    .build()
+                    .unwrap();
+
+                match probe.probe().await {
+                    ProbeResult::InvalidResponse(reason) => {
+                        assert!(reason.starts_with("certificate expires on "), "{reason}");
+                        assert!(reason.ends_with(" UTC, within 14 days"), "{reason}");
+                    }
+                    result => panic!("expected an invalid response, got {result:?}"),
+                }
+            }
+
+            #[tokio::test]
+            async fn test_tls_probe_rejected_certificates() {
+                let temp_dir = TempDir::new().unwrap();
+                let ca = TestCa::new();
+                let ca_path = ca.write(&temp_dir);
+
+                let expired = ca.serve("127.0.0.1", (-60, -1)).await;
+                let wrong_name = ca.serve("other.internal", (-1, 90)).await;
+                for (port, ca_path, expected) in [
+                    (expired, Some(&ca_path), "certificate expired on "),
+                    (
+                        wrong_name,
+                        Some(&ca_path),
+                        "certificate is not valid for 127.0.0.1 (expires on ",
+                    ),
+                    (
+                        wrong_name,
+                        None,
+                        "certificate chain is not trusted: unknown issuer (expires on ",
+                    ),
+                ] {
+                    let mut config = TlsConfig::new(format!("https://127.0.0.1:{port}"));
+                    config.tls.ca_cert_path = ca_path.cloned();
+
+                    match config.build().unwrap().probe().await {
+                        ProbeResult::InvalidResponse(reason) => {
+                            assert!(reason.starts_with(expected), "{reason}");
+                        }
+                        result => panic!("expected an invalid response, got {result:?}"),
+                    }
+                }
+            }
+
+            #[tokio::test]
+            async fn test_tls_probe_insecure_only_checks_expiry() {
+                let ca = TestCa::new();
+                let wrong_name = ca.serve("other.internal", (-1, 90)).await;
+                let expiring = ca.serve("other.internal", (-60, 5)).await;
+                let insecure = TlsOptions::new().insecure(true);
+
+                let probe = Tls::builder(format!("https://127.0.0.1:{wrong_name}"))
+                    .tls(insecure.clone())
+                    .build()
+                    .unwrap();
+                assert_eq!(probe.probe().await, ProbeResult::Success);
+
+                let probe = Tls::builder(format!("https://127.0.0.1:{expiring}"))
+                    .tls(insecure)
+                    .build()
+                    .unwrap();
+                match probe.probe().await {
+                    ProbeResult::InvalidResponse(reason) => {
+                        assert!(reason.starts_with("certificate expires on "), "{reason}");
+                    }
+                    result => panic!("expected an invalid response, got {result:?}"),
+                }
+            }
+
+            #[tokio::test]
+            async fn test_tls_probe_presents_client_certificate() {
+                let temp_dir = TempDir::new().unwrap();
+                let ca = TestCa::new();
+                let (cert_path, key_path) = ca.write_client(&temp_dir);
+                let port = ca.serve_mutual("127.0.0.1").await;
+
+                let probe = Tls::builder(format!("https://127.0.0.1:{port}"))
+                    .tls(
+                        TlsOptions::new()
+                            .ca_cert_path(ca.write(&temp_dir))
+                            .client_cert_path(cert_path)
+                            .client_key_path(key_path),
+                    )
+                    .build()
+                    .unwrap();
+
+                assert_eq!(probe.probe().await, ProbeResult::Success);
+            }
+
+            #[test]
+            fn test_tls_client_key_requires_certificate() {
+                let temp_dir = TempDir::new().unwrap();
+                let (_, key_path) = TestCa::new().write_client(&temp_dir);
+
+                let result = Tls::builder("https://127.0.0.1")
+                    .tls(TlsOptions::new().client_key_path(key_path))
+                    .build();
+
+                assert!(matches!(
+                    result,
+                    Err(Error::InvalidConfig {
+                        field: "client certificate",
+                        ..
+                    })
+                ));
+            }
+
+            #[cfg(feature = "http")]
+            #[tokio::test]
+            async fn test_http_probe_mutual_tls() {
+                use crate::probes::Http;
+
+                let temp_dir = TempDir::new().unwrap();
+                let ca = TestCa::new();
+                let ca_path = ca.write(&temp_dir);
+                let (cert_path, key_path) = ca.write_client(&temp_dir);
+                let port = ca.serve_mutual("127.0.0.1").await;
+                let url = format!("https://127.0.0.1:{port}/health");
+
+                let probe = Http::builder(&url)
+                    .tls(
+                        TlsOptions::new()
+                            .ca_cert_path(&ca_path)
+                            .client_cert_path(cert_path)
+                            .client_key_path(key_path),
+                    )
+                    .build()
+                    .unwrap();
+                assert_eq!(probe.probe().await, ProbeResult::Success);
+
+                let probe = Http::builder(&url)
+                    .tls(TlsOptions::new().ca_cert_path(&ca_path))
+                    .build()
+                    .unwrap();
+                assert!(matches!(probe.probe().await, ProbeResult::NetworkError(_)));
+            }
+
+            #[cfg(feature = "http")]
+            #[tokio::test]
+            async fn test_http_probe_insecure() {
+                use crate::probes::Http;
+
+                let port = TestCa::new().serve("127.0.0.1", (-1, 90)).await;
+                let url = format!("https://127.0.0.1:{port}/health");
+
+                let probe = Http::builder(&url).build().unwrap();
+                assert!(matches!(probe.probe().await, ProbeResult::NetworkError(_)));
+
+                let probe = Http::builder(&url)
+                    .tls(TlsOptions::new().insecure(true))
+                    .build()
+                    .unwrap();
+                assert_eq!(probe.probe().await, ProbeResult::Success);
+            }
+
+            #[tokio::test]
+            async fn test_tls_probe_dns_override() {
+                let temp_dir = TempDir::new().unwrap();
+                let ca = TestCa::new();
+                let port = ca.serve("replica.invalid", (-1, 90)).await;
+
+                let probe = Tls::builder(format!("https://replica.invalid:{port}"))
+                    .tls(TlsOptions::new().ca_cert_path(ca.write(&temp_dir)))
+                    .network(
+                        NetworkOptions::new()
+                            .resolve("replica.invalid", ([127, 0, 0, 1], 0).into()),
+                    )
+                    .build()
+                    .unwrap();
+
+                assert_eq!(probe.probe().await, ProbeResult::Success);
+            }
+
+            #[tokio::test]
+            async fn test_tls_probe_connection_refused() {
+                let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+                let port = listener.local_addr().unwrap().port();
+                drop(listener);
+
+                let probe = Tls::builder(format!("https://127.0.0.1:{port}"))
+                    .build()
+                    .unwrap();
+
+                match probe.probe().await {
+                    ProbeResult::NetworkError(reason) => {
+                        assert!(reason.starts_with("failed to connect"), "{reason}");
+                    }
+                    result => panic!("expected a network error, got {result:?}"),
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "newman")]
+    mod newman {
+        use serde::Deserialize;
+        use std::{process::Stdio, time::Duration};
+        use tokio::process::Command;
+        use tracing::{error, info, warn};
+
+        use super::{NetworkOptions, TlsOptions};
+        use crate::{ProbeResult, Result};
+
+        pub use native::Postman;
+
+        /// Configuration for the [`Newman`] probe
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct NewmanConfig {
+            /// Path to the Postman collection JSON file
+            pub collection_path: String,
+            /// Path to the Postman environment JSON file
+            pub environment_path: Option<String>,
+            /// Delay between requests in milliseconds, to avoid hitting rate limits
+            pub request_delay_milliseconds: Option<u64>,
+            /// Timeout for each request in the collection
+            pub timeout: Duration,
+            /// Deadline for the whole newman run, after which newman is killed and the probe
+            /// times out
+            pub run_timeout: Duration,
+            /// newman executable to run
+            pub newman_path: String,
+            /// Path to a CSV or JSON data file, running the collection once per row
+            pub iteration_data_path: Option<String>,
+            /// Folders or requests to run, instead of the whole collection
+            pub folders: Vec<String>,
+            /// Variables overriding those in the environment file, e.g. secrets from the
+            /// process environment
+            pub env_vars: Vec<(String, String)>,
+            /// Global variables, overridden by collection and environment variables
+            pub global_vars: Vec<(String, String)>,
+            /// Stop the run at the first failed request or test
+            pub bail: bool,
+            /// CA certificates, client certificate and verification of the collection's requests
+            pub tls: TlsOptions,
+            /// Proxy for the collection's requests. DNS overrides are only supported by the
+            /// native runner.
+            pub network: NetworkOptions,
+        }
+
+        impl Default for NewmanConfig {
+            fn default() -> Self {
+                NewmanConfig {
+                    collection_path: "collection.json".to_string(),
+                    environment_path: None,
+                    request_delay_milliseconds: None,
+                    timeout: Duration::from_secs(10),
+                    run_timeout: Duration::from_secs(120),
+                    newman_path: "newman".to_string(),
+                    iteration_data_path: None,
+                    folders: Vec::new(),
+                    env_vars: Vec::new(),
+                    global_vars: Vec::new(),
+                    bail: false,
+                    tls: TlsOptions::default(),
+                    network: NetworkOptions::default(),
+                }
+            }
+        }
+
+        impl NewmanConfig {
+            pub fn new(collection_path: impl Into<String>) -> Self {
+                NewmanConfig {
+                    collection_path: collection_path.into(),
+                    ..Default::default()
+                }
+            }
+
+            pub fn environment_path(mut self, environment_path: impl Into<String>) -> Self {
+                self.environment_path = Some(environment_path.into());
+                self
+            }
+
+            pub fn request_delay_milliseconds(mut self, delay: u64) -> Self {
+                self.request_delay_milliseconds = Some(delay);
+                self
+            }
+
+            pub fn timeout(mut self, timeout: Duration) -> Self {
+                self.timeout = timeout;
+                self
+            }
+
+            pub fn run_timeout(mut self, run_timeout: Duration) -> Self {
+                self.run_timeout = run_timeout;
+                self
+            }
+
+            pub fn newman_path(mut self, newman_path: impl Into<String>) -> Self {
+                self.newman_path = newman_path.into();
+                self
+            }
+
+            pub fn iteration_data_path(mut self, iteration_data_path: impl Into<String>) -> Self {
+                self.iteration_data_path = Some(iteration_data_path.into());
+                self
+            }
+
+            /// Run a folder or request, can be repeated
+            pub fn folder(mut self, folder: impl Into<String>) -> Self {
+                self.folders.push(folder.into());
+                self
+            }
+
+            pub fn env_var(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+                self.env_vars.push((key.into(), value.into()));
+                self
+            }
+
+            pub fn global_var(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+                self.global_vars.push((key.into(), value.into()));
+                self
+            }
+
+            pub fn bail(mut self, bail: bool) -> Self {
+                self.bail = bail;
+                self
+            }
+
+            pub fn tls(mut self, tls: TlsOptions) -> Self {
+                self.tls = tls;
+                self
+            }
+
+            pub fn network(mut self, network: NetworkOptions) -> Self {
+                self.network = network;
+                self
+            }
+
+            pub fn build(self) -> Result<Newman> {
+                Newman::new(self)
+            }
+        }
+
+        /// Newman probe functionality
+        pub struct Newman {
+            config: NewmanConfig,
+        }
+
+        impl Newman {
+            pub fn new(config: NewmanConfig) -> Result<Self> {
+                Ok(Newman { config })
+            }
+
+            /// Start building a Newman probe, see [`NewmanConfig`]
+            pub fn builder(collection_path: impl Into<String>) -> NewmanConfig {
+                NewmanConfig::new(collection_path)
+            }
+        }
+
+        /// Newman probe implementation
+        #[async_trait::async_trait]
+        impl crate::Probe for Newman {
+            async fn probe(&self) -> ProbeResult {
+                // Check the inputs first, since newman reports them as an ordinary failed run
+                if let Err(reason) = check_inputs(&self.config) {
+                    return ProbeResult::Misconfigured(reason);
+                }
+                if !self.config.network.resolve.is_empty() {
+                    return ProbeResult::Misconfigured(
+                        "newman does not support DNS overrides, run the collection with the \
+                         postman probe instead"
+                            .to_string(),
+                    );
+                }
+
+                let mut newman = Command::new(&self.config.newman_path);
+
+                newman.arg("run").arg(&self.config.collection_path);
+
+                // Set timeout - timeout is in seconds, but newman expects milliseconds
+                newman
+                    .arg("--timeout-request")
+                    .arg(self.config.timeout.as_millis().to_string());
+
+                // Optional args if set in config
+                if let Some(env_path) = &self.config.environment_path {
+                    newman.arg("-e").arg(env_path);
+                }
+                if let Some(delay) = self.config.request_delay_milliseconds {
+                    newman.arg("--delay-request").arg(delay.to_string());
+                }
+                if let Some(data_path) = &self.config.iteration_data_path {
+                    newman.arg("-d").arg(data_path);
+                }
+                for folder in &self.config.folders {
+                    newman.arg("--folder").arg(folder);
+                }
+                for (key, value) in &self.config.env_vars {
+                    newman.arg("--env-var").arg(format!("{key}={value}"));
+                }
+                for (key, value) in &self.config.global_vars {
+                    newman.arg("--global-var").arg(format!("{key}={value}"));
+                }
+                if self.config.bail {
+                    newman.arg("--bail");
+                }
+                if self.config.tls.insecure {
+                    newman.arg("--insecure");
+                }
+                if let Some(ca_path) = &self.config.tls.ca_cert_path {
+                    newman.arg("--ssl-extra-ca-certs").arg(ca_path);
+                }
+                // newman only takes proxies from the environment
+                if let Some(proxy) = &self.config.network.proxy {
+                    newman.env("HTTP_PROXY", proxy).env("HTTPS_PROXY", proxy);
+                }
+                if let Some(no_proxy) = &self.config.network.no_proxy {
+                    newman.env("NO_PROXY", no_proxy);
+                }
+                if let Some(cert_path) = &self.config.tls.client_cert_path {
+                    newman.arg("--ssl-client-cert").arg(cert_path);
+                }
+                if let Some(key_path) = &self.config.tls.client_key_path {
+                    newman.arg("--ssl-client-key").arg(key_path);
+                }
+
+                // Keep the human readable output for the logs, and read the results from the
+                // JSON reporter
+                let report_file = match tempfile::Builder::new()
+                    .prefix("newman-report-")
+                    .suffix(".json")
+                    .tempfile()
+                {
+                    Ok(file) => file,
+                    Err(e) => {
+                        return ProbeResult::Misconfigured(format!(
+                            "cannot create newman report file: {e}"
+                        ));
+                    }
+                };
+                newman
+                    .arg("--reporters")
+                    .arg("cli,json")
+                    .arg("--reporter-json-export")
+                    .arg(report_file.path());
+
+                newman
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .kill_on_drop(true);
+                // Run newman in its own process group, so that anything it spawns is killed
+                // along with it on timeout
+                #[cfg(unix)]
+                newman.process_group(0);
+
+                let child = match newman.spawn() {
+                    Ok(child) => child,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                        return ProbeResult::Misconfigured(format!(
+                            "newman executable '{}' not found",
+                            self.config.newman_path
+                        ));
+                    }
+                    Err(e) => {
+                        return ProbeResult::Misconfigured(format!("failed to start newman: {e}"));
+                    }
+                };
+                let pid = child.id();
+
+                match tokio::time::timeout(self.config.run_timeout, child.wait_with_output()).await
+                {
+                    Ok(Ok(output)) => {
+                        let status = output.status;
+                        let body = String::from_utf8_lossy(&output.stdout);
+                        info!("--- Newman stdout ---\n {body}");
+
+                        let executions = match std::fs::read_to_string(report_file.path())
+                            .map_err(|e| e.to_string())
+                            .and_then(|report| parse_report(&report))
+                        {
+                            Ok(executions) => executions,
+                            // Newman writes the report whenever the run starts, so without one
+                            // it rejected its inputs
+                            Err(e) if !status.success() => {
+                                let stderr = String::from_utf8_lossy(&output.stderr);
+                                warn!("No newman JSON report: {e}");
+                                return ProbeResult::Misconfigured(format!(
+                                    "newman did not run the collection: {}",
+                                    stderr.trim()
+                                ));
+                            }
+                            Err(e) => {
+                                warn!("Failed to read newman JSON report: {e}");
+                                Vec::new()
+                            }
+                        };
+                        let failures = report_failures(&executions);
+
+                        match (status.success(), failures.is_empty()) {
+                            (true, true) => ProbeResult::Success,
+                            (_, false) => ProbeResult::TestFailure(failures.join("; ")),
+                            // Newman failed without reporting a failing request or assertion
+                            (false, true) => ProbeResult::Error(1),
+                        }
+                    }
+                    Ok(Err(e)) => {
+                        error!("Failed to wait for newman process: {e}");
+                        ProbeResult::Error(1)
+                    }
+                    Err(_) => {
+                        error!(
+                            "Newman run exceeded {:?}, killing it",
+                            self.config.run_timeout
+                        );
+                        // Dropping the child has already killed newman itself
+                        if let Some(pid) = pid {
+                            kill_process_group(pid);
+                        }
+                        ProbeResult::Timeout
+                    }
+                }
+            }
+        }
+
+        /// Read a collection or environment file, which must be JSON
+        fn read_json_file(kind: &str, path: &str) -> Result<serde_json::Value, String> {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| format!("cannot read {kind} {path}: {e}"))?;
+            serde_json::from_str(&contents)
+                .map_err(|e| format!("{kind} {path} is not valid JSON: {e}"))
+        }
+
+        /// Check that every file newman is given can be read
+        fn check_inputs(config: &NewmanConfig) -> Result<(), String> {
+            read_json_file("collection", &config.collection_path)?;
+            if let Some(path) = &config.environment_path {
+                read_json_file("environment", path)?;
+            }
+            for (kind, path) in [
+                ("iteration data", &config.iteration_data_path),
+                ("CA certificates", &config.tls.ca_cert_path),
+                ("client certificate", &config.tls.client_cert_path),
+                ("client key", &config.tls.client_key_path),
+            ] {
+                if let Some(path) = path {
+                    std::fs::File::open(path)
+                        .map_err(|e| format!("cannot read {kind} {path}: {e}"))?;
+                }
+            }
+            Ok(())
+        }
+
+        /// Log each execution and describe every failure in the run
+        fn report_failures(executions: &[Execution]) -> Vec<String> {
+            for execution in executions {
+                execution.log();
+            }
+            executions.iter().flat_map(Execution::failures).collect()
+        }
+
+        /// JSON reporter output of a newman run, limited to what the probe reports on
+        #[derive(Debug, Deserialize)]
+        struct Report {
+            run: Run,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct Run {
+            #[serde(default)]
+            executions: Vec<Execution>,
+        }
+
+        /// A request in the collection, with its response and test assertions
+        #[derive(Debug, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Execution {
+            item: Item,
+            response: Option<Response>,
+            request_error: Option<ReportError>,
+            #[serde(default)]
+            assertions: Vec<Assertion>,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct Item {
+            name: String,
+        }
+
+        #[derive(Debug, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Response {
+            code: u16,
+            #[serde(default)]
+            response_time: u64,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct ReportError {
+            message: String,
+        }
+
+        /// A `pm.test` in the request's test script
+        #[derive(Debug, Deserialize)]
+        struct Assertion {
+            assertion: String,
+            #[serde(default)]
+            skipped: bool,
+            error: Option<ReportError>,
+        }
+
+        fn parse_report(report: &str) -> Result<Vec<Execution>, String> {
+            serde_json::from_str::<Report>(report)
+                .map(|report| report.run.executions)
+                .map_err(|e| e.to_string())
+        }
+
+        impl Execution {
+            /// Log the response and the outcome of each assertion
+            fn log(&self) {
+                match (&self.response, &self.request_error) {
+                    (Some(response), _) => info!(
+                        "{}: HTTP {} in {}ms",
+                        self.item.name, response.code, response.response_time
+                    ),
+                    (None, Some(e)) => error!("{}: request failed: {}", self.item.name, e.message),
+                    (None, None) => info!("{}: no response", self.item.name),
+                }
+                for assertion in &self.assertions {
+                    match &assertion.error {
+                        _ if assertion.skipped => info!("  - {} (skipped)", assertion.assertion),
+                        Some(e) => error!("  ✗ {}: {}", assertion.assertion, e.message),
+                        None => info!("  ✓ {}", assertion.assertion),
+                    }
+                }
+            }
+
+            /// Describe the failed request or each failed assertion with its error, e.g.
+            /// "Fast enough (expected 812 to be below 200)". Assertions on the status code also
+            /// name the code received, unless their error already does.
+            fn failures(&self) -> Vec<String> {
+                if let Some(e) = &self.request_error {
+                    return vec![format!("{}: {}", self.item.name, e.message)];
+                }
+                self.assertions
+                    .iter()
+                    .filter(|assertion| !assertion.skipped)
+                    .filter_map(|assertion| {
+                        let error = assertion.error.as_ref()?;
+                        let code = self
+                            .response
+                            .as_ref()
+                            .map(|response| response.code.to_string())
+                            .filter(|code| {
+                                assertion.assertion.to_lowercase().contains("status")
+                                    && !error.message.contains(code.as_str())
+                            });
+                        Some(match code {
+                            Some(code) => format!(
+                                "{}: {} ({}, got {code})",
+                                self.item.name, assertion.assertion, error.message
+                            ),
+                            None => format!(
+                                "{}: {} ({})",
+                                self.item.name, assertion.assertion, error.message
+                            ),
+                        })
+                    })
+                    .collect()
+            }
+        }
+
+        /// Runs Postman collections without newman, for the [`Postman`] probe
+        mod native {
+            use reqwest::{Client, Method, header::CONTENT_TYPE};
+            use serde::Deserialize;
+            use serde_json::Value;
+            use std::{
+                collections::HashMap,
+                time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+            };
+            use tracing::info;
+
+            use super::{
+                Assertion, Execution, Item as ReportItem, Newman, NewmanConfig, ReportError,
+                Response as ReportResponse, read_json_file, report_failures,
+            };
+            use crate::{
+                Error, ProbeResult, Result,
+                probes::{client_builder, json_equal},
+            };
+
+            /// Probe running a Postman v2.1 collection natively, falling back to [`Newman`] when
+            /// the collection's scripts go beyond what the native runner understands.
+            ///
+            /// The native runner supports requests in nested folders, `{{variable}}` templating
+            /// from collection variables and the environment file plus `{{$timestamp}}`, bearer,
+            /// basic and API key auth, and test scripts made of `pm.test` blocks using common
+            /// assertions: status codes, response times, headers, and JSON property presence and
+            /// equality.
+            /// Collections with iteration data always run with newman.
+            pub struct Postman {
+                config: NewmanConfig,
+                client: Client,
+                newman: Newman,
+            }
+
+            impl Postman {
+                pub fn new(config: NewmanConfig) -> Result<Self> {
+                    let client = client_builder(&config.tls, &config.network)?
+                        .timeout(config.timeout)
+                        .build()
+                        .map_err(Error::HttpClient)?;
+                    Ok(Postman {
+                        newman: Newman::new(config.clone())?,
+                        config,
+                        client,
+                    })
+                }
+            }
+
+            #[async_trait::async_trait]
+            impl crate::Probe for Postman {
+                async fn probe(&self) -> ProbeResult {
+                    let collection =
+                        match read_json_file("collection", &self.config.collection_path) {
+                            Ok(collection) => collection,
+                            Err(reason) => return ProbeResult::Misconfigured(reason),
+                        };
+                    let environment = match &self.config.environment_path {
+                        Some(path) => match read_json_file("environment", path) {
+                            Ok(environment) => Some(environment),
+                            Err(reason) => return ProbeResult::Misconfigured(reason),
+                        },
+                        None => None,
+                    };
+
+                    let (steps, variables) = match compile(collection, environment, &self.config) {
+                        Ok(compiled) => compiled,
+                        Err(CompileError::Unsupported(reason)) => {
+                            info!("Running the collection with newman: {reason}");
+                            return self.newman.probe().await;
+                        }
+                        Err(CompileError::Misconfigured(reason)) => {
+                            return ProbeResult::Misconfigured(reason);
+                        }
+                    };
+
+                    match tokio::time::timeout(self.config.run_timeout, self.run(&steps, variables))
+                        .await
+                    {
+                        Ok(executions) => {
+                            let failures = report_failures(&executions);
+                            if failures.is_empty() {
+                                ProbeResult::Success
+                            } else {
+                                ProbeResult::TestFailure(failures.join("; "))
+                            }
+                        }
+                        Err(_) => ProbeResult::Timeout,
+                    }
+                }
+            }
+
+            impl Postman {
+                async fn run(
+                    &self,
+                    steps: &[Step],
+                    mut variables: HashMap<String, String>,
+                ) -> Vec<Execution> {
+                    let mut executions = Vec::with_capacity(steps.len());
+                    for (i, step) in steps.iter().enumerate() {
+                        if let (true, Some(delay)) = (i > 0, self.config.request_delay_milliseconds)
+                        {
+                            tokio::time::sleep(Duration::from_millis(delay)).await;
+                        }
+
+                        let mut assertions = Vec::new();
+                        run_script(
+                            "prerequest",
+                            &step.prerequest,
+                            None,
+                            &mut variables,
+                            &mut assertions,
+                        );
+
+                        let execution = match self.send(step, &variables).await {
+                            Ok(response) => {
+                                run_script(
+                                    "test",
+                                    &step.tests,
+                                    Some(&response),
+                                    &mut variables,
+                                    &mut assertions,
+                                );
+                                Execution {
+                                    item: ReportItem {
+                                        name: step.name.clone(),
+                                    },
+                                    response: Some(ReportResponse {
+                                        code: response.code,
+                                        response_time: response.time.as_millis() as u64,
+                                    }),
+                                    request_error: None,
+                                    assertions,
+                                }
+                            }
+                            Err(message) => Execution {
+                                item: ReportItem {
+                                    name: step.name.clone(),
+                                },
+                                response: None,
+                                request_error: Some(ReportError { message }),
+                                assertions,
+                            },
+                        };
+                        let failed = !execution.failures().is_empty();
+                        executions.push(execution);
+                        if failed && self.config.bail {
+                            break;
+                        }
+                    }
+                    executions
+                }
+
+                async fn send(
+                    &self,
+                    step: &Step,
+                    variables: &HashMap<String, String>,
+                ) -> Result<Observed, String> {
+                    let request = &step.request;
+                    let method = Method::from_bytes(request.method.to_uppercase().as_bytes())
+                        .map_err(|e| format!("invalid method {}: {e}", request.method))?;
+                    let mut url = substitute(request.url.raw(), variables);
+                    if !url.contains("://") {
+                        url = format!("http://{url}");
+                    }
+
+                    let mut builder = self.client.request(method, url);
+                    let (mut has_content_type, mut has_authorization) = (false, false);
+                    for header in request.header.iter().filter(|h| h.is_enabled()) {
+                        has_content_type |= header.key.eq_ignore_ascii_case("content-type");
+                        has_authorization |= header.key.eq_ignore_ascii_case("authorization");
+                        builder = builder.header(
+                            substitute(&header.key, variables),
+                            substitute(&header.value(), variables),
+                        );
+                    }
+
+                    // An explicit Authorization header takes precedence over inherited auth
+                    if let Some(auth) = step.auth.as_ref().filter(|_| !has_authorization) {
+                        let field = |name: &str| substitute(&auth.field(name), variables);
+                        builder = match auth.kind.as_str() {
+                            "bearer" => builder.bearer_auth(field("token")),
+                            "basic" => {
+                                builder.basic_auth(field("username"), Some(field("password")))
+                            }
+                            "apikey" if auth.field("in") == "query" => {
+                                builder.query(&[(field("key"), field("value"))])
+                            }
+                            "apikey" => builder.header(field("key"), field("value")),
+                            _ => builder,
+                        };
+                    }
+
+                    if let Some(body) = &request.body {
+                        match body.mode.as_str() {
+                            "raw" => {
+                                let is_json = body
+                                    .options
+                                    .as_ref()
+                                    .and_then(|options| options.pointer("/raw/language"))
+                                    .and_then(Value::as_str)
+                                    == Some("json");
+                                if is_json && !has_content_type {
+                                    builder = builder.header(CONTENT_TYPE, "application/json");
+                                }
+                                builder = builder
+                                    .body(substitute(body.raw.as_deref().unwrap_or(""), variables));
+                            }
+                            "urlencoded" => {
+                                let form: Vec<_> = body
+                                    .urlencoded
+                                    .iter()
+                                    .filter(|pair| pair.is_enabled())
+                                    .map(|pair| {
+                                        (
+                                            substitute(&pair.key, variables),
+                                            substitute(&pair.value(), variables),
+                                        )
+                                    })
+                                    .collect();
+                                builder = builder.form(&form);
+                            }
+                            _ => {}
+                        }
+                    }
+
+                    let started = Instant::now();
+                    let response = builder.send().await.map_err(|e| {
+                        if e.is_timeout() {
+                            "request timed out".to_string()
+                        } else {
+                            e.to_string()
+                        }
+                    })?;
+                    let code = response.status().as_u16();
+                    let headers = response.headers().clone();
+                    let body = response.text().await.map_err(|e| e.to_string())?;
+
+                    Ok(Observed {
+                        code,
+                        time: started.elapsed(),
+                        headers,
+                        body,
+                    })
+                }
+            }
+
+            /// The response a test script runs against
+            struct Observed {
+                code: u16,
+                time: Duration,
+                headers: reqwest::header::HeaderMap,
+                body: String,
+            }
+
+            /// A request to send, with the auth and scripts it inherits from its folders
+            #[derive(Debug)]
+            struct Step {
+                name: String,
+                request: Request,
+                auth: Option<Auth>,
+                prerequest: Vec<Statement>,
+                tests: Vec<Statement>,
+            }
+
+            #[derive(Debug, Deserialize)]
+            struct Collection {
+                #[serde(default)]
+                item: Vec<CollectionItem>,
+                #[serde(default)]
+                variable: Vec<KeyValue>,
+                #[serde(default)]
+                event: Vec<Event>,
+                auth: Option<Auth>,
+            }
+
+            /// A request, or a folder of further items
+            #[derive(Debug, Deserialize)]
+            struct CollectionItem {
+                name: String,
+                item: Option<Vec<CollectionItem>>,
+                request: Option<RequestDefinition>,
+                #[serde(default)]
+                event: Vec<Event>,
+                auth: Option<Auth>,
+            }
+
+            #[derive(Debug, Deserialize)]
+            struct Event {
+                listen: String,
+                script: Option<Script>,
+            }
+
+            #[derive(Debug, Deserialize)]
+            struct Script {
+                exec: Option<Exec>,
+            }
+
+            #[derive(Debug, Deserialize)]
+            #[serde(untagged)]
+            enum Exec {
+                Lines(Vec<String>),
+                Text(String),
+            }
+
+            #[derive(Debug, Deserialize)]
+            #[serde(untagged)]
+            enum RequestDefinition {
+                Url(String),
+                Request(Box<Request>),
+            }
+
+            #[derive(Debug, Clone, Deserialize)]
+            struct Request {
+                #[serde(default = "default_method")]
+                method: String,
+                url: Url,
+                #[serde(default)]
+                header: Vec<KeyValue>,
+                body: Option<Body>,
+                auth: Option<Auth>,
+            }
+
+            fn default_method() -> String {
+                "GET".to_string()
+            }
+
+            #[derive(Debug, Clone, Deserialize)]
+            #[serde(untagged)]
+            enum Url {
+                Raw(String),
+                Parts { raw: String },
+            }
+
+            impl Url {
+                fn raw(&self) -> &str {
+                    match self {
+                        Url::Raw(raw) | Url::Parts { raw } => raw,
+                    }
+                }
+            }
+
+            #[derive(Debug, Clone, Deserialize)]
+            struct Body {
+                mode: String,
+                raw: Option<String>,
+                #[serde(default)]
+                urlencoded: Vec<KeyValue>,
+                options: Option<Value>,
+            }
+
+            #[derive(Debug, Clone, Deserialize)]
+            struct Auth {
+                #[serde(rename = "type")]
+                kind: String,
+                #[serde(default)]
+                bearer: Vec<KeyValue>,
+                #[serde(default)]
+                basic: Vec<KeyValue>,
+                #[serde(default)]
+                apikey: Vec<KeyValue>,
+            }
+
+            impl Auth {
+                /// Value of a parameter of this auth type, e.g. the bearer token
+                fn field(&self, name: &str) -> String {
+                    let fields = match self.kind.as_str() {
+                        "bearer" => &self.bearer,
+                        "basic" => &self.basic,
+                        "apikey" => &self.apikey,
+                        _ => return String::new(),
+                    };
+                    fields
+                        .iter()
+                        .find(|field| field.key == name)
+                        .map(KeyValue::value)
+                        .unwrap_or_default()
+                }
+            }
+
+            /// A header, variable, form field or auth parameter
+            #[derive(Debug, Clone, Deserialize)]
+            struct KeyValue {
+                key: String,
+                #[serde(default)]
+                value: Value,
+                #[serde(default)]
+                disabled: bool,
+                enabled: Option<bool>,
+            }
+
+            impl KeyValue {
+                fn is_enabled(&self) -> bool {
+                    !self.disabled && self.enabled != Some(false)
+                }
+
+                fn value(&self) -> String {
+                    to_variable(&self.value)
+                }
+            }
+
+            #[derive(Debug, Deserialize)]
+            struct Environment {
+                #[serde(default)]
+                values: Vec<KeyValue>,
+            }
+
+            /// Turn a collection and environment into the requests to run and the initial
+            /// variables, or explain why the native runner can't run them
+            fn compile(
+                collection: Value,
+                environment: Option<Value>,
+                config: &NewmanConfig,
+            ) -> Result<(Vec<Step>, HashMap<String, String>), CompileError> {
+                if config.iteration_data_path.is_some() {
+                    return Err("iteration data needs newman".to_string().into());
+                }
+                let collection: Collection = serde_json::from_value(collection)
+                    .map_err(|e| format!("unsupported collection format: {e}"))?;
+                let environment: Option<Environment> = environment
+                    .map(serde_json::from_value)
+                    .transpose()
+                    .map_err(|e| format!("unsupported environment format: {e}"))?;
+                if let Some(folder) = config
+                    .folders
+                    .iter()
+                    .find(|folder| !contains_item(&collection.item, folder))
+                {
+                    return Err(CompileError::Misconfigured(format!(
+                        "no folder or request named {folder} in the collection"
+                    )));
+                }
+
+                // Later scopes take precedence, as in newman: globals, then collection
+                // variables, then the environment file and its overrides
+                let global_vars = config.global_vars.iter().cloned();
+                let env_vars = config.env_vars.iter().cloned();
+                let variables = global_vars
+                    .chain(
+                        collection
+                            .variable
+                            .iter()
+                            .chain(environment.iter().flat_map(|e| &e.values))
+                            .filter(|variable| variable.is_enabled())
+                            .map(|variable| (variable.key.clone(), variable.value())),
+                    )
+                    .chain(env_vars)
+                    .collect();
+
+                let mut steps = Vec::new();
+                let scripts = Scripts::default().with(&collection.event)?;
+                collect_steps(
+                    &collection.item,
+                    collection.auth.as_ref(),
+                    &scripts,
+                    &config.folders,
+                    &mut steps,
+                )?;
+                Ok((steps, variables))
+            }
+
+            /// Why the native runner can't run a collection
+            #[derive(Debug)]
+            enum CompileError {
+                /// The collection needs newman
+                Unsupported(String),
+                /// The collection can't be run with this configuration at all
+                Misconfigured(String),
+            }
+
+            impl From<String> for CompileError {
+                fn from(reason: String) -> Self {
+                    CompileError::Unsupported(reason)
+                }
+            }
+
+            /// Whether a folder or request has the given name, at any depth
+            fn contains_item(items: &[CollectionItem], name: &str) -> bool {
+                items.iter().any(|item| {
+                    item.name == name
+                        || item
+                            .item
+                            .as_ref()
+                            .is_some_and(|children| contains_item(children, name))
+                })
+            }
+
+            /// Scripts inherited from the collection and enclosing folders
+            #[derive(Debug, Clone, Default)]
+            struct Scripts {
+                prerequest: Vec<Statement>,
+                tests: Vec<Statement>,
+            }
+
+            impl Scripts {
+                fn with(&self, events: &[Event]) -> Result<Scripts, String> {
+                    let mut scripts = self.clone();
+                    for event in events {
+                        let source = match event.script.as_ref().and_then(|s| s.exec.as_ref()) {
+                            Some(Exec::Lines(lines)) => lines.join("\n"),
+                            Some(Exec::Text(text)) => text.clone(),
+                            None => continue,
+                        };
+                        let statements = parse_script(&source)?;
+                        match event.listen.as_str() {
+                            "test" => scripts.tests.extend(statements),
+                            "prerequest" => {
+                                if let Some(statement) =
+                                    statements.iter().find(|s| !s.is_prerequest_safe())
+                                {
+                                    return Err(format!(
+                                        "unsupported pre-request statement {statement:?}"
+                                    ));
+                                }
+                                scripts.prerequest.extend(statements)
+                            }
+                            listen => return Err(format!("unsupported script event {listen}")),
+                        }
+                    }
+                    Ok(scripts)
+                }
+            }
+
+            /// Flatten the requests to run, all of them when no folders are selected
+            fn collect_steps(
+                items: &[CollectionItem],
+                auth: Option<&Auth>,
+                scripts: &Scripts,
+                folders: &[String],
+                steps: &mut Vec<Step>,
+            ) -> Result<(), String> {
+                for item in items {
+                    let scripts = scripts.with(&item.event)?;
+                    let auth = item.auth.as_ref().or(auth);
+                    // Everything inside a selected folder runs
+                    let folders: &[String] = if folders.contains(&item.name) {
+                        &[]
+                    } else {
+                        folders
+                    };
+                    if let Some(children) = &item.item {
+                        collect_steps(children, auth, &scripts, folders, steps)?;
+                        continue;
+                    }
+                    if !folders.is_empty() {
+                        continue;
+                    }
+                    let request = match &item.request {
+                        Some(RequestDefinition::Url(url)) => Request {
+                            method: default_method(),
+                            url: Url::Raw(url.clone()),
+                            header: Vec::new(),
+                            body: None,
+                            auth: None,
+                        },
+                        Some(RequestDefinition::Request(request)) => (**request).clone(),
+                        None => continue,
+                    };
+                    let auth = request.auth.as_ref().or(auth).cloned();
+                    if let Some(auth) = &auth
+                        && !["noauth", "bearer", "basic", "apikey"].contains(&auth.kind.as_str())
+                    {
+                        return Err(format!("unsupported auth type {}", auth.kind));
+                    }
+                    if let Some(body) = &request.body
+                        && !["raw", "urlencoded", "none"].contains(&body.mode.as_str())
+                    {
+                        return Err(format!("unsupported body mode {}", body.mode));
+                    }
+                    let texts = [request.url.raw().to_string()]
+                        .into_iter()
+                        .chain(
+                            request
+                                .header
+                                .iter()
+                                .flat_map(|h| [h.key.clone(), h.value()]),
+                        )
+                        .chain(request.body.iter().flat_map(|body| {
+                            body.raw.iter().cloned().chain(
+                                body.urlencoded
+                                    .iter()
+                                    .flat_map(|field| [field.key.clone(), field.value()]),
+                            )
+                        }))
+                        .chain(auth.iter().flat_map(|auth| {
+                            [&auth.bearer, &auth.basic, &auth.apikey]
+                                .into_iter()
+                                .flatten()
+                                .map(KeyValue::value)
+                        }));
+                    for text in texts {
+                        if let Some(name) = unsupported_dynamic_variable(&text) {
+                            return Err(format!("unsupported dynamic variable {{{{{name}}}}}"));
+                        }
+                    }
+                    steps.push(Step {
+                        name: item.name.clone(),
+                        request,
+                        auth,
+                        prerequest: scripts.prerequest,
+                        tests: scripts.tests,
+                    });
+                }
+                Ok(())
+            }
+
+            /// First dynamic variable such as `{{$guid}}` in a text, other than `{{$timestamp}}`
+            /// which [`substitute`] generates
+            fn unsupported_dynamic_variable(text: &str) -> Option<&str> {
+                text.split("{{")
+                    .skip(1)
+                    .filter_map(|rest| Some(rest.split_once("}}")?.0))
+                    .find(|name| name.starts_with('$') && *name != "$timestamp")
+            }
+
+            /// Replace `{{variable}}` with its value, leaving unknown variables as they are
+            fn substitute(text: &str, variables: &HashMap<String, String>) -> String {
+                let mut result = String::with_capacity(text.len());
+                let mut rest = text;
+                while let Some(start) = rest.find("{{") {
+                    let Some(end) = rest[start..].find("}}") else {
+                        break;
+                    };
+                    let name = &rest[start + 2..start + end];
+                    result.push_str(&rest[..start]);
+                    match (variables.get(name), name) {
+                        (Some(value), _) => result.push_str(value),
+                        (None, "$timestamp") => result.push_str(&now().as_secs().to_string()),
+                        (None, _) => result.push_str(&rest[start..start + end + 2]),
+                    }
+                    rest = &rest[start + end + 2..];
+                }
+                result.push_str(rest);
+                result
+            }
+
+            fn now() -> Duration {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+            }
+
+            /// How Postman turns a value into a variable, i.e. strings as they are and anything
+            /// else as JSON
+            fn to_variable(value: &Value) -> String {
+                match value {
+                    Value::String(s) => s.clone(),
+                    Value::Null => String::new(),
+                    value => value.to_string(),
+                }
+            }
+
+            /// A statement from the subset of Postman scripts the native runner understands
+            #[derive(Debug, Clone, PartialEq)]
+            enum Statement {
+                /// `pm.test("name", function () { ... });`
+                Test { name: String, body: Vec<Statement> },
+                /// `var jsonData = pm.response.json();`, checking the body is JSON
+                ParseJson,
+                /// `pm.expect(...)...;` or `pm.response.to.have...;`
+                Expect(Expectation),
+                /// `pm.environment.set("name", value);` or the collectionVariables / globals /
+                /// variables equivalent
+                Set { name: String, value: Expr },
+                /// `if (condition) { ... }`
+                If {
+                    left: Expr,
+                    operator: String,
+                    right: Expr,
+                    body: Vec<Statement>,
+                },
+            }
+
+            impl Statement {
+                /// Whether the statement can run before there is a response
+                fn is_prerequest_safe(&self) -> bool {
+                    match self {
+                        Statement::Set { value, .. } => !value.needs_response(),
+                        _ => false,
+                    }
+                }
+            }
+
+            #[derive(Debug, Clone, PartialEq)]
+            enum Expectation {
+                Header(String),
+                Equals(Expr, Expr),
+                Below(Expr, f64),
+                Above(Expr, f64),
+                HasProperty(Expr, String),
+                Exists(Expr),
+            }
+
+            #[derive(Debug, Clone, PartialEq)]
+            enum Expr {
+                Literal(Value),
+                ResponseCode,
+                ResponseTime,
+                /// A value in the JSON response body, by object key or array index
+                Json(Vec<Value>),
+                Variable(String),
+                /// Milliseconds since the epoch, e.g. `Date.now()`
+                Now,
+            }
+
+            impl Expr {
+                fn needs_response(&self) -> bool {
+                    matches!(
+                        self,
+                        Expr::ResponseCode | Expr::ResponseTime | Expr::Json(_)
+                    )
+                }
+
+                fn eval(
+                    &self,
+                    response: Option<&Observed>,
+                    variables: &HashMap<String, String>,
+                ) -> Result<Value, String> {
+                    let observed = || response.ok_or("there is no response".to_string());
+                    Ok(match self {
+                        Expr::Literal(value) => value.clone(),
+                        Expr::ResponseCode => observed()?.code.into(),
+                        Expr::ResponseTime => (observed()?.time.as_millis() as u64).into(),
+                        Expr::Now => (now().as_millis() as u64).into(),
+                        Expr::Variable(name) => variables
+                            .get(name)
+                            .cloned()
+                            .map_or(Value::Null, Value::String),
+                        Expr::Json(path) => {
+                            let mut value = response_json(observed()?)?;
+                            for segment in path {
+                                value = match (segment, value) {
+                                    (Value::String(key), Value::Object(mut map)) => {
+                                        map.remove(key).unwrap_or(Value::Null)
+                                    }
+                                    (Value::Number(index), Value::Array(mut items)) => index
+                                        .as_u64()
+                                        .filter(|&i| (i as usize) < items.len())
+                                        .map(|i| items.swap_remove(i as usize))
+                                        .unwrap_or(Value::Null),
+                                    (segment, _) => {
+                                        return Err(format!(
+                                            "cannot read property {segment} of the response"
+                                        ));
+                                    }
+                                };
+                            }
+                            value
+                        }
+                    })
+                }
+            }
+
+            fn response_json(response: &Observed) -> Result<Value, String> {
+                serde_json::from_str(&response.body)
+                    .map_err(|e| format!("response is not valid JSON: {e}"))
+            }
+
+            /// Compare like JavaScript's loose equality `==`, so 200 equals 200.0 and "200"
+            fn loosely_equal(left: &Value, right: &Value) -> bool {
+                match (left, right) {
+                    (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+                    (Value::Number(n), Value::String(s)) | (Value::String(s), Value::Number(n)) => {
+                        s.parse::<f64>().ok() == n.as_f64()
+                    }
+                    (a, b) => a == b,
+                }
+            }
+
+            fn run_script(
+                listen: &str,
+                statements: &[Statement],
+                response: Option<&Observed>,
+                variables: &mut HashMap<String, String>,
+                assertions: &mut Vec<Assertion>,
+            ) {
+                // An error outside of a pm.test fails the script, as it would in newman
+                if let Err(message) = execute(statements, response, variables, assertions) {
+                    assertions.push(Assertion {
+                        assertion: format!("{listen} script"),
+                        skipped: false,
+                        error: Some(ReportError { message }),
+                    });
+                }
+            }
+
+            fn execute(
+                statements: &[Statement],
+                response: Option<&Observed>,
+                variables: &mut HashMap<String, String>,
+                assertions: &mut Vec<Assertion>,
+            ) -> Result<(), String> {
+                for statement in statements {
+                    match statement {
+                        Statement::Test { name, body } => {
+                            let result = execute(body, response, variables, assertions);
+                            assertions.push(Assertion {
+                                assertion: name.clone(),
+                                skipped: false,
+                                error: result.err().map(|message| ReportError { message }),
+                            });
+                        }
+                        Statement::ParseJson => {
+                            response_json(response.ok_or("there is no response")?)?;
+                        }
+                        Statement::Expect(expectation) => check(expectation, response, variables)?,
+                        Statement::Set { name, value } => {
+                            let value = to_variable(&value.eval(response, variables)?);
+                            variables.insert(name.clone(), value);
+                        }
+                        Statement::If {
+                            left,
+                            operator,
+                            right,
+                            body,
+                        } => {
+                            let left = left.eval(response, variables)?;
+                            let right = right.eval(response, variables)?;
+                            let numbers = left.as_f64().zip(right.as_f64());
+                            let holds = match (operator.as_str(), numbers) {
+                                ("==", _) => loosely_equal(&left, &right),
+                                ("!=", _) => !loosely_equal(&left, &right),
+                                ("===", _) => json_equal(&left, &right),
+                                ("!==", _) => !json_equal(&left, &right),
+                                (">", Some((l, r))) => l > r,
+                                (">=", Some((l, r))) => l >= r,
+                                ("<", Some((l, r))) => l < r,
+                                ("<=", Some((l, r))) => l <= r,
+                                _ => false,
+                            };
+                            if holds {
+                                execute(body, response, variables, assertions)?;
+                            }
+                        }
+                    }
+                }
+                Ok(())
+            }
+
+            /// Evaluate an expectation, describing the failure like chai would
+            fn check(
+                expectation: &Expectation,
+                response: Option<&Observed>,
+                variables: &HashMap<String, String>,
+            ) -> Result<(), String> {
+                match expectation {
+                    Expectation::Header(name) => {
+                        let response = response.ok_or("there is no response")?;
+                        if response.headers.contains_key(name.as_str()) {
+                            Ok(())
+                        } else {
+                            Err(format!("expected response to have header {name}"))
+                        }
+                    }
+                    Expectation::Equals(actual, expected) => {
+                        let actual = actual.eval(response, variables)?;
+                        let expected = expected.eval(response, variables)?;
+                        if json_equal(&actual, &expected) {
+                            Ok(())
+                        } else {
+                            Err(format!("expected {actual} to deeply equal {expected}"))
+                        }
+                    }
+                    Expectation::Below(actual, limit) => {
+                        let actual = actual.eval(response, variables)?;
+                        match actual.as_f64() {
+                            Some(value) if value < *limit => Ok(()),
+                            _ => Err(format!("expected {actual} to be below {limit}")),
+                        }
+                    }
+                    Expectation::Above(actual, limit) => {
+                        let actual = actual.eval(response, variables)?;
+                        match actual.as_f64() {
+                            Some(value) if value > *limit => Ok(()),
+                            _ => Err(format!("expected {actual} to be above {limit}")),
+                        }
+                    }
+                    Expectation::HasProperty(actual, property) => {
+                        let actual = actual.eval(response, variables)?;
+                        if actual.get(property).is_some() {
+                            Ok(())
+                        } else {
+                            Err(format!("expected {actual} to have property '{property}'"))
+                        }
+                    }
+                    Expectation::Exists(actual) => {
+                        let actual = actual.eval(response, variables)?;
+                        if actual.is_null() {
+                            Err("expected value to exist".to_string())
+                        } else {
+                            Ok(())
+                        }
+                    }
+                }
+            }
+
+            /// Parse a script into statements, or explain which part isn't supported
+            fn parse_script(source: &str) -> Result<Vec<Statement>, String> {
+                Parser {
+                    source,
+                    json_bindings: Vec::new(),
+                }
+                .statements()
+            }
+
+            struct Parser<'a> {
+                source: &'a str,
+                /// Names bound to `pm.response.json()` in scope
+                json_bindings: Vec<String>,
+            }
+
+            impl<'a> Parser<'a> {
+                fn statements(&mut self) -> Result<Vec<Statement>, String> {
+                    let mut statements = Vec::new();
+                    loop {
+                        self.skip_blank();
+                        if self.source.is_empty() {
+                            return Ok(statements);
+                        }
+                        if let Some(statement) = self.statement()? {
+                            statements.push(statement);
+                        }
+                    }
+                }
+
+                /// Skip whitespace and comments
+                fn skip_blank(&mut self) {
+                    loop {
+                        self.source = self.source.trim_start();
+                        if let Some(rest) = self.source.strip_prefix("//") {
+                            self.source = rest.find('\n').map_or("", |end| &rest[end..]);
+                        } else if let Some(rest) = self.source.strip_prefix("/*") {
+                            self.source = rest.find("*/").map_or("", |end| &rest[end + 2..]);
+                        } else {
+                            return;
+                        }
+                    }
+                }
+
+                fn eat(&mut self, token: &str) -> bool {
+                    self.skip_blank();
+                    match self.source.strip_prefix(token) {
+                        Some(rest) => {
+                            self.source = rest;
+                            true
+                        }
+                        None => false,
+                    }
+                }
+
+                fn expect(&mut self, token: &str) -> Result<(), String> {
+                    if self.eat(token) {
+                        Ok(())
+                    } else {
+                        Err(format!(
+                            "expected `{token}` in script near `{}`",
+                            self.snippet()
+                        ))
+                    }
+                }
+
+                fn snippet(&self) -> &str {
+                    let end = self
+                        .source
+                        .char_indices()
+                        .nth(40)
+                        .map_or(self.source.len(), |(i, _)| i);
+                    &self.source[..end]
+                }
+
+                /// Take the text up to the bracket closing the one the source starts with
+                fn bracketed(&mut self, open: char, close: char) -> Result<&'a str, String> {
+                    self.skip_blank();
+                    if !self.source.starts_with(open) {
+                        return Err(format!(
+                            "expected `{open}` in script near `{}`",
+                            self.snippet()
+                        ));
+                    }
+                    let end = scan(self.source, |depth, c| depth == 1 && c == close)
+                        .ok_or_else(|| format!("unbalanced `{open}` in script"))?;
+                    let inner = &self.source[1..end];
+                    self.source = &self.source[end + 1..];
+                    Ok(inner)
+                }
+
+                /// Parse a nested block with the bindings in scope
+                fn block(&self, source: &str) -> Result<Vec<Statement>, String> {
+                    Parser {
+                        source,
+                        json_bindings: self.json_bindings.clone(),
+                    }
+                    .statements()
+                }
+
+                fn statement(&mut self) -> Result<Option<Statement>, String> {
+                    if self.eat(";") {
+                        return Ok(None);
+                    }
+                    if self.eat("pm.test(") {
+                        self.skip_blank();
+                        let (name, rest) = string_literal(self.source)
+                            .ok_or_else(|| "pm.test needs a literal name".to_string())?;
+                        self.source = rest;
+                        self.expect(",")?;
+                        if self.eat("function") {
+                            self.expect("(")?;
+                            self.expect(")")?;
+                        } else {
+                            self.expect("(")?;
+                            self.expect(")")?;
+                            self.expect("=>")?;
+                        }
+                        let body = self.bracketed('{', '}')?;
+                        let body = self.block(body)?;
+                        self.expect(")")?;
+                        self.eat(";");
+                        return Ok(Some(Statement::Test { name, body }));
+                    }
+                    if self.source.starts_with("if")
+                        && self.source[2..].starts_with(|c: char| c == '(' || c.is_whitespace())
+                    {
+                        self.source = &self.source[2..];
+                        let condition = self.bracketed('(', ')')?;
+                        self.skip_blank();
+                        let body = if self.source.starts_with('{') {
+                            let body = self.bracketed('{', '}')?;
+                            self.block(body)?
+                        } else {
+                            self.statement()?.into_iter().collect()
+                        };
+                        if self.eat("else") {
+                            return Err("unsupported else branch in script".to_string());
+                        }
+                        let (left, operator, right) = split_comparison(condition)
+                            .ok_or_else(|| format!("unsupported condition `{condition}`"))?;
+                        return Ok(Some(Statement::If {
+                            left: self.expr(left)?,
+                            operator: operator.to_string(),
+                            right: self.expr(right)?,
+                            body,
+                        }));
+                    }
+
+                    // A simple statement ends at a semicolon or line break outside brackets
+                    let end = scan(self.source, |depth, c| {
+                        depth == 0 && (c == ';' || c == '\n')
+                    })
+                    .unwrap_or(self.source.len());
+                    let text = self.source[..end].trim();
+                    self.source = &self.source[end..];
+                    self.simple_statement(text).map(Some)
+                }
+
+                fn simple_statement(&mut self, text: &str) -> Result<Statement, String> {
+                    let unsupported = || format!("unsupported script statement `{text}`");
+
+                    for keyword in ["var ", "let ", "const "] {
+                        if let Some(binding) = text.strip_prefix(keyword) {
+                            let (name, value) = binding.split_once('=').ok_or_else(unsupported)?;
+                            if value.trim() != "pm.response.json()" {
+                                return Err(unsupported());
+                            }
+                            self.json_bindings.push(name.trim().to_string());
+                            return Ok(Statement::ParseJson);
+                        }
+                    }
+
+                    if let Some(status) = text
+                        .strip_prefix("pm.response.to.have.status(")
+                        .and_then(|rest| rest.strip_suffix(')'))
+                    {
+                        let status: u16 = status.trim().parse().map_err(|_| unsupported())?;
+                        return Ok(Statement::Expect(Expectation::Equals(
+                            Expr::ResponseCode,
+                            Expr::Literal(status.into()),
+                        )));
+                    }
+                    if text == "pm.response.to.be.ok" {
+                        return Ok(Statement::Expect(Expectation::Equals(
+                            Expr::ResponseCode,
+                            Expr::Literal(200.into()),
+                        )));
+                    }
+                    if let Some(name) = text
+                        .strip_prefix("pm.response.to.have.header(")
+                        .and_then(|rest| rest.strip_suffix(')'))
+                        .and_then(|name| string_literal(name.trim()))
+                        .filter(|(_, rest)| rest.trim().is_empty())
+                    {
+                        return Ok(Statement::Expect(Expectation::Header(name.0)));
+                    }
+
+                    for scope in ["environment", "collectionVariables", "globals", "variables"] {
+                        if let Some(arguments) = text
+                            .strip_prefix(&format!("pm.{scope}.set("))
+                            .and_then(|rest| rest.strip_suffix(')'))
+                        {
+                            let (name, rest) =
+                                string_literal(arguments.trim()).ok_or_else(unsupported)?;
+                            let value = rest.trim().strip_prefix(',').ok_or_else(unsupported)?;
+                            return Ok(Statement::Set {
+                                name,
+                                value: self.expr(value)?,
+                            });
+                        }
+                    }
+
+                    if let Some(rest) = text.strip_prefix("pm.expect") {
+                        let mut parser = Parser {
+                            source: rest,
+                            json_bindings: Vec::new(),
+                        };
+                        let actual = self.expr(parser.bracketed('(', ')')?)?;
+                        return self
+                            .expectation(actual, parser.source.trim())
+                            .map(Statement::Expect);
+                    }
+
+                    Err(unsupported())
+                }
+
+                /// Parse a chai assertion chain such as `.to.be.below(1000)`
+                fn expectation(&self, actual: Expr, chain: &str) -> Result<Expectation, String> {
+                    let unsupported = || format!("unsupported assertion `{chain}`");
+                    let (words, argument) = match chain.split_once('(') {
+                        Some((words, argument)) => (
+                            words,
+                            Some(argument.strip_suffix(')').ok_or_else(unsupported)?),
+                        ),
+                        None => (chain, None),
+                    };
+                    let words: Vec<_> = words
+                        .strip_prefix('.')
+                        .ok_or_else(unsupported)?
+                        .split('.')
+                        .collect();
+                    if words.contains(&"not") {
+                        return Err(unsupported());
+                    }
+                    let number = || {
+                        argument
+                            .and_then(|a| a.trim().parse::<f64>().ok())
+                            .ok_or_else(unsupported)
+                    };
+                    match (words.last().copied(), argument) {
+                        (Some("eql" | "equal" | "equals" | "eq"), Some(expected)) => {
+                            Ok(Expectation::Equals(actual, self.expr(expected)?))
+                        }
+                        (Some("below" | "lessThan" | "lt"), Some(_)) => {
+                            Ok(Expectation::Below(actual, number()?))
+                        }
+                        (Some("above" | "greaterThan" | "gt"), Some(_)) => {
+                            Ok(Expectation::Above(actual, number()?))
+                        }
+                        (Some("property"), Some(property)) => match string_literal(property.trim())
+                        {
+                            Some((property, rest)) if rest.trim().is_empty() => {
+                                Ok(Expectation::HasProperty(actual, property))
+                            }
+                            _ => Err(unsupported()),
+                        },
+                        (Some("exist"), None) => Ok(Expectation::Exists(actual)),
+                        _ => Err(unsupported()),
+                    }
+                }
+
+                fn expr(&self, text: &str) -> Result<Expr, String> {
+                    let text = text.trim();
+                    let unsupported = || format!("unsupported expression `{text}`");
+
+                    if let Some((value, rest)) = string_literal(text)
+                        && rest.trim().is_empty()
+                    {
+                        return Ok(Expr::Literal(Value::String(value)));
+                    }
+                    if let Ok(value @ (Value::Number(_) | Value::Bool(_) | Value::Null)) =
+                        serde_json::from_str::<Value>(text)
+                    {
+                        return Ok(Expr::Literal(value));
+                    }
+                    match text {
+                        "pm.response.code" => return Ok(Expr::ResponseCode),
+                        "pm.response.responseTime" => return Ok(Expr::ResponseTime),
+                        "Date.now()" | "new Date().getTime()" => return Ok(Expr::Now),
+                        _ => {}
+                    }
+                    for scope in ["environment", "collectionVariables", "globals", "variables"] {
+                        if let Some(name) = text
+                            .strip_prefix(&format!("pm.{scope}.get("))
+                            .and_then(|rest| rest.strip_suffix(')'))
+                        {
+                            return match string_literal(name.trim()) {
+                                Some((name, rest)) if rest.trim().is_empty() => {
+                                    Ok(Expr::Variable(name))
+                                }
+                                _ => Err(unsupported()),
+                            };
+                        }
+                    }
+
+                    let path = match text.strip_prefix("pm.response.json()") {
+                        Some(path) => path,
+                        None => {
+                            let end = text.find(['.', '[']).unwrap_or(text.len());
+                            if !self.json_bindings.iter().any(|name| name == &text[..end]) {
+                                return Err(unsupported());
+                            }
+                            &text[end..]
+                        }
+                    };
+                    json_path(path).map(Expr::Json).ok_or_else(unsupported)
+                }
+            }
+
+            /// Parse property accesses like `.data[0]["name"]` into keys and indices
+            fn json_path(mut path: &str) -> Option<Vec<Value>> {
+                let mut segments = Vec::new();
+                while !path.is_empty() {
+                    if let Some(rest) = path.strip_prefix('.') {
+                        let end = rest
+                            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                            .unwrap_or(rest.len());
+                        if end == 0 {
+                            return None;
+                        }
+                        segments.push(Value::String(rest[..end].to_string()));
+                        path = &rest[end..];
+                    } else if let Some(rest) = path.strip_prefix('[') {
+                        let (inner, rest) = rest.split_once(']')?;
+                        match string_literal(inner.trim()) {
+                            Some((key, "")) => segments.push(Value::String(key)),
+                            _ => segments
+                                .push(Value::Number(inner.trim().parse::<u64>().ok()?.into())),
+                        }
+                        path = rest;
+                    } else {
+                        return None;
+                    }
+                }
+                Some(segments)
+            }
+
+            /// Split `left op right` on its comparison operator
+            fn split_comparison(condition: &str) -> Option<(&str, &str, &str)> {
+                for operator in ["===", "!==", "==", "!=", ">=", "<=", ">", "<"] {
+                    if let Some((left, right)) = condition.split_once(operator) {
+                        return Some((left, operator, right));
+                    }
+                }
+                None
+            }
+
+            /// Parse a JavaScript string literal at the start of `text`, returning its value and
+            /// the text after it
+            fn string_literal(text: &str) -> Option<(String, &str)> {
+                let quote = text
+                    .chars()
+                    .next()
+                    .filter(|c| matches!(c, '"' | '\'' | '`'))?;
+                let mut value = String::new();
+                let mut chars = text.char_indices().skip(1);
+                while let Some((i, c)) = chars.next() {
+                    match c {
+                        '\\' => match chars.next()?.1 {
+                            'n' => value.push('\n'),
+                            't' => value.push('\t'),
+                            escaped => value.push(escaped),
+                        },
+                        c if c == quote => return Some((value, &text[i + 1..])),
+                        c => value.push(c),
+                    }
+                }
+                None
+            }
+
+            /// Find the first position where `stop(depth, char)` holds, tracking bracket depth
+            /// and skipping over string literals
+            fn scan(text: &str, stop: impl Fn(usize, char) -> bool) -> Option<usize> {
+                let mut depth = 0usize;
+                let mut quote = None;
+                let mut escaped = false;
+                for (i, c) in text.char_indices() {
+                    if let Some(q) = quote {
+                        match c {
+                            _ if escaped => escaped = false,
+                            '\\' => escaped = true,
+                            c if c == q => quote = None,
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    match c {
+                        '"' | '\'' | '`' => quote = Some(c),
+                        '(' | '{' | '[' => depth += 1,
+                        ')' | '}' | ']' => {
+                            if stop(depth, c) {
+                                return Some(i);
+                            }
+                            depth = depth.saturating_sub(1);
+                            continue;
+                        }
+                        _ => {}
+                    }
+                    if stop(depth, c) {
+                        return Some(i);
+                    }
+                }
+                None
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+                use crate::Probe;
+                use httpmock::prelude::*;
+                use serde_json::json;
+                use std::fs;
+                use tempfile::TempDir;
+
+                fn write_json(dir: &TempDir, name: &str, contents: &Value) -> String {
+                    let path = dir.path().join(name);
+                    fs::write(&path, contents.to_string()).unwrap();
+                    path.to_str().unwrap().to_string()
+                }
+
+                fn environment(base_url: &str) -> Value {
+                    json!({
+                        "values": [
+                            { "key": "base_url", "value": base_url, "enabled": true },
+                            { "key": "api_token", "value": "test-token-12345", "enabled": true }
+                        ]
+                    })
+                }
+
+                #[test]
+                fn test_parse_script() {
+                    let statements = parse_script(
+                        r#"
+                        // Checks on the created resource
+                        pm.test("Created", function () {
+                            pm.response.to.have.status(201);
+                            var jsonData = pm.response.json();
+                            pm.expect(jsonData.items[0]['name']).to.eql('Test; Resource');
+                        });
+                        pm.test("Fast", () => {
+                            pm.expect(pm.response.responseTime).to.be.below(1000)
+                        });
+                        pm.environment.set("id", pm.response.json().id);
+                        "#,
+                    )
+                    .unwrap();
+
+                    assert_eq!(
+                        statements,
+                        [
+                            Statement::Test {
+                                name: "Created".to_string(),
+                                body: vec![
+                                    Statement::Expect(Expectation::Equals(
+                                        Expr::ResponseCode,
+                                        Expr::Literal(201.into())
+                                    )),
+                                    Statement::ParseJson,
+                                    Statement::Expect(Expectation::Equals(
+                                        Expr::Json(vec![json!("items"), json!(0), json!("name")]),
+                                        Expr::Literal(json!("Test; Resource"))
+                                    )),
+                                ],
+                            },
+                            Statement::Test {
+                                name: "Fast".to_string(),
+                                body: vec![Statement::Expect(Expectation::Below(
+                                    Expr::ResponseTime,
+                                    1000.0
+                                ))],
+                            },
+                            Statement::Set {
+                                name: "id".to_string(),
+                                value: Expr::Json(vec![json!("id")]),
+                            },
+                        ]
+                    );
+                }
+
+                #[test]
+                fn test_parse_script_rejects_unsupported_statements() {
+                    for script in [
+                        "const schema = { type: 'object' };",
+                        "pm.test('Schema', function () { pm.response.to.have.jsonSchema(schema); });",
+                        "pm.test('Not', function () { pm.expect(pm.response.code).to.not.eql(500); });",
+                        "if (pm.response.code === 200) { console.log('ok'); } else { }",
+                    ] {
+                        assert!(parse_script(script).is_err(), "{script}");
+                    }
+                }
+
+                #[test]
+                fn test_script_equality() {
+                    let statements = parse_script(
+                        r#"
+                        pm.test("Id", function () { pm.expect(pm.response.json().id).to.eql(200); });
+                        pm.test("Count", function () { pm.expect(pm.response.json().count).to.eql(2); });
+                        if (pm.response.json().id == 200) { pm.environment.set("loose", "yes"); }
+                        if (pm.response.json().id === 200) { pm.environment.set("strict", "yes"); }
+                        "#,
+                    )
+                    .unwrap();
+                    let response = Observed {
+                        code: 200,
+                        time: Duration::from_millis(5),
+                        headers: Default::default(),
+                        body: r#"{"id": "200", "count": 2.0}"#.to_string(),
+                    };
+                    let (mut variables, mut assertions) = (HashMap::new(), Vec::new());
+
+                    run_script(
+                        "test",
+                        &statements,
+                        Some(&response),
+                        &mut variables,
+                        &mut assertions,
+                    );
+
+                    // Assertions compare strictly, except for the numbers' representation
+                    let errors: Vec<_> = assertions
+                        .iter()
+                        .map(|assertion| assertion.error.as_ref().map(|e| e.message.as_str()))
+                        .collect();
+                    assert_eq!(
+                        errors,
+                        [Some(r#"expected "200" to deeply equal 200"#), None]
+                    );
+                    // Conditions compare loosely with == only
+                    assert_eq!(
+                        variables,
+                        HashMap::from([("loose".to_string(), "yes".to_string())])
+                    );
+                }
+
+                #[test]
+                fn test_compile_rejects_dynamic_variables() {
+                    let compile_request = |request: Value| {
+                        let collection = json!({
+                            "info": { "name": "Dynamic" },
+                            "item": [{ "name": "Create", "request": request }]
+                        });
+                        compile(collection, None, &NewmanConfig::new("collection.json"))
+                    };
+
+                    assert!(compile_request(json!("http://api/items?at={{$timestamp}}")).is_ok());
+                    for request in [
+                        json!("http://api/items/{{$randomInt}}"),
+                        json!({
+                            "url": "http://api/items",
+                            "header": [{ "key": "X-Request-Id", "value": "{{$guid}}" }]
+                        }),
+                        json!({
+                            "method": "POST",
+                            "url": "http://api/items",
+                            "body": { "mode": "raw", "raw": "{\"at\": \"{{$isoTimestamp}}\"}" }
+                        }),
+                        json!({
+                            "url": "http://api/items",
+                            "auth": { "type": "bearer", "bearer": [{ "key": "token", "value": "{{$guid}}" }] }
+                        }),
+                    ] {
+                        match compile_request(request.clone()) {
+                            Err(CompileError::Unsupported(reason)) => {
+                                assert!(
+                                    reason.starts_with("unsupported dynamic variable {{$"),
+                                    "{reason}"
+                                )
+                            }
+                            result => panic!("expected {request} to need newman, got {result:?}"),
+                        }
+                    }
+                }
+
+                #[test]
+                fn test_substitute() {
+                    let variables =
+                        HashMap::from([("base_url".to_string(), "http://api".to_string())]);
+
+                    assert_eq!(
+                        substitute("{{base_url}}/users/{{missing}}", &variables),
+                        "http://api/users/{{missing}}"
+                    );
+                }
+
+                #[tokio::test]
+                async fn test_postman_runs_test_collection_natively() {
+                    let server = MockServer::start();
+                    let temp_dir = TempDir::new().unwrap();
+                    let health_mock = server.mock(|when, then| {
+                        when.method(GET).path("/health");
+                        then.status(200).json_body(json!({"status": "ok"}));
+                    });
+                    let user_mock = server.mock(|when, then| {
+                        when.method(GET)
+                            .path("/api/v1/users/123")
+                            .header("Authorization", "Bearer test-token-12345");
+                        then.status(200)
+                            .json_body(json!({"id": 123, "name": "Test User"}));
+                    });
+                    let create_mock = server.mock(|when, then| {
+                        when.method(POST)
+                            .path("/api/v1/resources")
+                            .header("Content-Type", "application/json")
+                            .body_contains("\"name\": \"Test Resource\"");
+                        then.status(201)
+                            .json_body(json!({"id": 789, "name": "Test Resource"}));
+                    });
+                    let delete_mock = server.mock(|when, then| {
+                        when.method(DELETE).path("/api/v1/resources/456");
+                        then.status(204);
+                    });
+
+                    let collection: Value =
+                        serde_json::from_str(include_str!("../test-collection.json")).unwrap();
+                    // newman isn't needed, so point the fallback at something that can't run
+                    let config =
+                        NewmanConfig::new(write_json(&temp_dir, "collection.json", &collection))
+                            .environment_path(write_json(
+                                &temp_dir,
+                                "environment.json",
+                                &environment(&server.base_url()),
+                            ))
+                            .newman_path("/nonexistent/newman");
+
+                    let result = Postman::new(config).unwrap().probe().await;
+
+                    assert_eq!(result, ProbeResult::Success);
+                    health_mock.assert();
+                    user_mock.assert();
+                    create_mock.assert();
+                    delete_mock.assert();
+                }
+
+                #[tokio::test]
+                async fn test_postman_folders_variables_and_failures() {
+                    let server = MockServer::start();
+                    let temp_dir = TempDir::new().unwrap();
+                    let create_mock = server.mock(|when, then| {
+                        when.method(POST)
+                            .path("/resources")
+                            .header("x-api-key", "secret");
+                        then.status(201).json_body(json!({"id": 789}));
+                    });
+                    let get_mock = server.mock(|when, then| {
+                        when.method(GET).path("/resources/789");
+                        then.status(500).body("oops");
+                    });
+
+                    let collection = json!({
+                        "info": { "name": "Chained" },
+                        "auth": {
+                            "type": "apikey",
+                            "apikey": [
+                                { "key": "key", "value": "x-api-key" },
+                                { "key": "value", "value": "{{key}}" }
+                            ]
+                        },
+                        "variable": [{ "key": "key", "value": "secret" }],
+                        "item": [{
+                            "name": "Resources",
+                            "item": [
+                                {
+                                    "name": "Create Resource",
+                                    "event": [{
+                                        "listen": "test",
+                                        "script": { "exec": [
+                                            "pm.collectionVariables.set('id', pm.response.json().id);"
+                                        ] }
+                                    }],
+                                    "request": { "method": "POST", "url": "{{base_url}}/resources" }
+                                },
+                                {
+                                    "name": "Get Resource",
+                                    "event": [{
+                                        "listen": "test",
+                                        "script": { "exec": [
+                                            "pm.test('Status code is 200', function () {",
+                                            "    pm.response.to.have.status(200);",
+                                            "});"
+                                        ] }
+                                    }],
+                                    "request": "{{base_url}}/resources/{{id}}"
+                                }
+                            ]
+                        }]
+                    });
+                    let config =
+                        NewmanConfig::new(write_json(&temp_dir, "collection.json", &collection))
+                            .environment_path(write_json(
+                                &temp_dir,
+                                "environment.json",
+                                &environment(&server.base_url()),
+                            ))
+                            .newman_path("/nonexistent/newman");
+
+                    let result = Postman::new(config).unwrap().probe().await;
+
+                    assert_eq!(
+                        result,
+                        ProbeResult::TestFailure(
+                            "Get Resource: Status code is 200 (expected 500 to deeply equal 200)"
+                                .to_string()
+                        )
+                    );
+                    create_mock.assert();
+                    get_mock.assert();
+                }
+
+                #[tokio::test]
+                async fn test_postman_selected_folders_overrides_and_bail() {
+                    let server = MockServer::start();
+                    let temp_dir = TempDir::new().unwrap();
+                    let setup_mock = server.mock(|when, then| {
+                        when.path("/setup");
+                        then.status(200);
+                    });
+                    let first_mock = server.mock(|when, then| {
+                        when.path("/first")
+                            .header("Authorization", "Bearer from-env")
+                            .header("x-region", "eu");
+                        then.status(500);
+                    });
+                    let second_mock = server.mock(|when, then| {
+                        when.path("/second");
+                        then.status(200);
+                    });
+
+                    let status_test = json!([{
+                        "listen": "test",
+                        "script": { "exec": "pm.test('Status code is 200', () => { pm.response.to.be.ok; });" }
+                    }]);
+                    let request = |path: &str| {
+                        json!({
+                            "url": format!("{{{{base_url}}}}{path}"),
+                            "header": [
+                                { "key": "Authorization", "value": "Bearer {{api_token}}" },
+                                { "key": "x-region", "value": "{{region}}" }
+                            ]
+                        })
+                    };
+                    let collection = json!({
+                        "info": { "name": "Selected" },
+                        "item": [
+                            { "name": "Setup", "request": request("/setup") },
+                            {
+                                "name": "Checks",
+                                "event": status_test,
+                                "item": [
+                                    { "name": "First", "request": request("/first") },
+                                    { "name": "Second", "request": request("/second") }
+                                ]
+                            }
+                        ]
+                    });
+                    let config =
+                        NewmanConfig::new(write_json(&temp_dir, "collection.json", &collection))
+                            .environment_path(write_json(
+                                &temp_dir,
+                                "environment.json",
+                                &environment(&server.base_url()),
+                            ))
+                            .folder("Checks")
+                            .env_var("api_token", "from-env")
+                            .global_var("region", "eu")
+                            .bail(true)
+                            .newman_path("/nonexistent/newman");
+
+                    let result = Postman::new(config).unwrap().probe().await;
+
+                    assert_eq!(
+                        result,
+                        ProbeResult::TestFailure(
+                            "First: Status code is 200 (expected 500 to deeply equal 200)"
+                                .to_string()
+                        )
+                    );
+                    setup_mock.assert_hits(0);
+                    first_mock.assert();
+                    second_mock.assert_hits(0);
+                }
+
+                #[tokio::test]
+                async fn test_postman_unknown_folder_is_misconfigured() {
+                    let temp_dir = TempDir::new().unwrap();
+                    let collection = json!({ "info": { "name": "Empty" }, "item": [] });
+                    let config =
+                        NewmanConfig::new(write_json(&temp_dir, "collection.json", &collection))
+                            .folder("Missing");
+
+                    assert_eq!(
+                        Postman::new(config).unwrap().probe().await,
+                        ProbeResult::Misconfigured(
+                            "no folder or request named Missing in the collection".to_string()
+                        )
+                    );
+                }
+
+                #[tokio::test]
+                async fn test_postman_falls_back_to_newman() {
+                    let temp_dir = TempDir::new().unwrap();
+                    let schema = json!({
+                        "info": { "name": "Schema" },
+                        "item": [{
+                            "name": "Validate",
+                            "event": [{
+                                "listen": "test",
+                                "script": { "exec": ["pm.response.to.have.jsonSchema({});"] }
+                            }],
+                            "request": "http://localhost/"
+                        }]
+                    });
+                    let dynamic = json!({
+                        "info": { "name": "Dynamic" },
+                        "item": [{ "name": "Fetch", "request": "http://localhost/{{$guid}}" }]
+                    });
+
+                    for collection in [schema, dynamic] {
+                        let config = NewmanConfig::new(write_json(
+                            &temp_dir,
+                            "collection.json",
+                            &collection,
+                        ))
+                        .newman_path("/nonexistent/newman");
+
+                        // The fallback runs newman, which isn't there
+                        assert_eq!(
+                            Postman::new(config).unwrap().probe().await,
+                            ProbeResult::Misconfigured(
+                                "newman executable '/nonexistent/newman' not found".to_string()
+                            )
+                        );
+                    }
+                }
+            }
         }
 
-        async fn probe(&self) -> ProbeResult {
-            let mut newman = Command::new("newman");
-
-            newman.arg("run").arg(&self.config.collection_path);
+        #[cfg(unix)]
+        fn kill_process_group(pid: u32) {
+            use nix::{
+                sys::signal::{Signal, killpg},
+                unistd::Pid,
+            };
 
-            // Set timeout - timeout is in seconds, but newman expects milliseconds
-            newman
-                .arg("--timeout-request")
-                .arg((self.config.timeout_seconds * 1000).to_string());
+            if let Err(e) = killpg(Pid::from_raw(pid as i32), Signal::SIGKILL) {
+                // ESRCH: everything in the group has already exited
+                if e != nix::errno::Errno::ESRCH {
+                    error!("Failed to kill newman process group {pid}: {e}");
+                }
+            }
+        }
 
-            // Optional args if set in config
-            if let Some(env_path) = &self.config.environment_path {
-                newman.arg("-e").arg(env_path);
+        #[cfg(not(unix))]
+        fn kill_process_group(_pid: u32) {}
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+            use crate::Probe;
+            use httpmock::prelude::*;
+            use serde_json::json;
+            use std::fs;
+            use tempfile::TempDir;
+
+            #[test]
+            fn test_newman_probe_creation() {
+                let config = NewmanConfig {
+                    collection_path: "test-collection.json".to_string(),
+                    environment_path: Some("test-environment.json".to_string()),
+                    ..Default::default()
+                };
+                let probe = Newman::new(config);
+                assert!(probe.is_ok());
             }
-            if let Some(delay) = self.config.request_delay_milliseconds {
-                newman.arg("--delay-request").arg(delay.to_string());
+
+            /// Write an executable shell script standing in for newman
+            #[cfg(unix)]
+            fn fake_newman(dir: &TempDir, script: &str) -> std::path::PathBuf {
+                use std::os::unix::fs::PermissionsExt;
+
+                let path = dir.path().join("newman");
+                fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+                fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+                path
             }
 
-            if let Ok(child) = newman
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn()
-                .context("spawning newman process")
-            {
-                match child.wait_with_output() {
-                    Ok(output) => {
-                        let status = output.status;
-                        let body = String::from_utf8_lossy(&output.stdout);
-                        info!("--- Newman stdout ---\n {body}");
+            /// Write a collection without any requests
+            fn empty_collection(dir: &TempDir) -> String {
+                let path = dir.path().join("collection.json");
+                fs::write(&path, r#"{"info": {"name": "Empty"}, "item": []}"#).unwrap();
+                path.to_str().unwrap().to_string()
+            }
 
-                        if status.success() {
-                            ProbeResult::Success
-                        } else {
-                            ProbeResult::Error(1)
+            const FAILED_REPORT: &str = r#"{
+                "run": {
+                    "executions": [
+                        {
+                            "item": { "name": "List Resources" },
+                            "response": { "code": 200, "status": "OK", "responseTime": 12 },
+                            "assertions": [
+                                { "assertion": "Status code is 200", "skipped": false },
+                                {
+                                    "assertion": "Fast enough",
+                                    "skipped": false,
+                                    "error": {
+                                        "name": "AssertionError",
+                                        "message": "expected 812 to be below 200"
+                                    }
+                                }
+                            ]
+                        },
+                        {
+                            "item": { "name": "Create Resource" },
+                            "response": { "code": 500, "responseTime": 48 },
+                            "assertions": [
+                                {
+                                    "assertion": "Status code is 201",
+                                    "skipped": false,
+                                    "error": {
+                                        "name": "AssertionError",
+                                        "message": "expected response to have status code 201 but got 500"
+                                    }
+                                },
+                                {
+                                    "assertion": "Status is a success",
+                                    "skipped": false,
+                                    "error": { "name": "AssertionError", "message": "expected false to be true" }
+                                },
+                                { "assertion": "Has an id", "skipped": true }
+                            ]
+                        },
+                        {
+                            "item": { "name": "Delete Resource" },
+                            "requestError": { "message": "connect ECONNREFUSED 127.0.0.1:1" },
+                            "assertions": []
                         }
-                    }
-                    Err(e) => {
-                        error!("Failed to wait for newman process: {e}");
-                        ProbeResult::Error(1)
-                    }
+                    ]
                 }
-            } else {
-                error!("Failed to start newman process");
-                ProbeResult::Error(1)
+            }"#;
+
+            #[test]
+            fn test_newman_report_failures() {
+                let executions = parse_report(FAILED_REPORT).unwrap();
+                let failures: Vec<_> = executions.iter().flat_map(Execution::failures).collect();
+
+                assert_eq!(
+                    failures,
+                    [
+                        "List Resources: Fast enough (expected 812 to be below 200)",
+                        "Create Resource: Status code is 201 \
+                         (expected response to have status code 201 but got 500)",
+                        "Create Resource: Status is a success (expected false to be true, got 500)",
+                        "Delete Resource: connect ECONNREFUSED 127.0.0.1:1",
+                    ]
+                );
+                assert_eq!(executions[0].response.as_ref().unwrap().response_time, 12);
             }
-        }
-    }
 
-    #[cfg(test)]
-    mod tests {
-        use super::{super::Probe, *};
-        use httpmock::prelude::*;
-        use serde_json::json;
+            #[cfg(unix)]
+            #[tokio::test]
+            async fn test_newman_probe_reports_failed_assertions() {
+                let temp_dir = TempDir::new().unwrap();
+                let report = temp_dir.path().join("report.json");
+                fs::write(&report, FAILED_REPORT).unwrap();
+                // Copy the canned report to wherever the probe asks the JSON reporter to write
+                let fake_newman = fake_newman(
+                    &temp_dir,
+                    &format!(
+                        "while [ $# -gt 0 ]; do\n\
+                         [ \"$1\" = --reporter-json-export ] && cp {} \"$2\"\n\
+                         shift\n\
+                         done\n\
+                         exit 1",
+                        report.display()
+                    ),
+                );
+
+                let probe = Newman::builder(empty_collection(&temp_dir))
+                    .newman_path(fake_newman.to_str().unwrap())
+                    .build()
+                    .unwrap();
+
+                assert_eq!(
+                    probe.probe().await,
+                    ProbeResult::TestFailure(
+                        "List Resources: Fast enough (expected 812 to be below 200); \
+                         Create Resource: Status code is 201 \
+                         (expected response to have status code 201 but got 500); \
+                         Create Resource: Status is a success (expected false to be true, got 500); \
+                         Delete Resource: connect ECONNREFUSED 127.0.0.1:1"
+                            .to_string()
+                    )
+                );
+            }
 
-        #[test]
-        fn test_openai_creation() {
-            let config = Config::default();
-            let probe = OpenAI::new(config);
-            assert!(probe.is_ok());
-        }
+            #[tokio::test]
+            async fn test_newman_probe_missing_collection_is_misconfigured() {
+                let probe = Newman::builder("does-not-exist.json").build().unwrap();
 
-        #[test]
-        fn test_openai_chat_endpoint_url() {
-            let config = Config {
-                endpoint_type: Type::OpenAIChatCompletion,
-                server_url: "https://api.openai.com".to_string(),
-                ..Default::default()
-            };
-            let probe = OpenAI::new(config).unwrap();
+                match probe.probe().await {
+                    ProbeResult::Misconfigured(reason) => {
+                        assert!(reason.starts_with("cannot read collection does-not-exist.json"));
+                    }
+                    result => panic!("expected a misconfiguration, got {result:?}"),
+                }
+            }
 
-            let url = probe.build_endpoint_url();
-            assert_eq!(url, "https://api.openai.com/v1/chat/completions");
-        }
+            #[tokio::test]
+            async fn test_newman_probe_malformed_environment_is_misconfigured() {
+                let temp_dir = TempDir::new().unwrap();
+                let environment_path = temp_dir.path().join("environment.json");
+                fs::write(&environment_path, "{ not json").unwrap();
 
-        #[test]
-        fn test_openai_embedding_endpoint_url() {
-            let config = Config {
-                endpoint_type: Type::OpenAIEmbedding,
-                server_url: "https://api.example.com".to_string(),
-                ..Default::default()
-            };
-            let probe = OpenAI::new(config).unwrap();
+                let probe = Newman::builder(empty_collection(&temp_dir))
+                    .environment_path(environment_path.to_str().unwrap())
+                    .build()
+                    .unwrap();
 
-            let url = probe.build_endpoint_url();
-            assert_eq!(url, "https://api.example.com/v1/embeddings");
-        }
+                match probe.probe().await {
+                    ProbeResult::Misconfigured(reason) => {
+                        assert!(reason.contains("is not valid JSON"), "{reason}");
+                    }
+                    result => panic!("expected a misconfiguration, got {result:?}"),
+                }
+            }
 
-        #[test]
-        fn test_openai_chat_payload() {
-            let config = Config {
-                endpoint_type: Type::OpenAIChatCompletion,
-                model_name: "a-piece-of-cheese".to_string(),
-                ..Default::default()
-            };
-            let probe = OpenAI::new(config).unwrap();
+            #[cfg(unix)]
+            #[tokio::test]
+            async fn test_newman_probe_passes_run_options() {
+                let temp_dir = TempDir::new().unwrap();
+                let data_path = temp_dir.path().join("data.csv");
+                fs::write(&data_path, "model\nsmall\nlarge\n").unwrap();
+                let args_path = temp_dir.path().join("args");
+                let fake_newman = fake_newman(
+                    &temp_dir,
+                    &format!("printf '%s\\n' \"$@\" > {}", args_path.display()),
+                );
+
+                let probe = Newman::builder(empty_collection(&temp_dir))
+                    .iteration_data_path(data_path.to_str().unwrap())
+                    .folder("Health")
+                    .folder("Chat")
+                    .env_var("api_token", "from-env")
+                    .global_var("region", "eu")
+                    .bail(true)
+                    .tls(TlsOptions::new().insecure(true))
+                    .newman_path(fake_newman.to_str().unwrap())
+                    .build()
+                    .unwrap();
+
+                assert_eq!(probe.probe().await, ProbeResult::Success);
+                let args = fs::read_to_string(&args_path).unwrap();
+                let args: Vec<_> = args.lines().collect();
+                for expected in [
+                    ["-d", data_path.to_str().unwrap()],
+                    ["--folder", "Health"],
+                    ["--folder", "Chat"],
+                    ["--env-var", "api_token=from-env"],
+                    ["--global-var", "region=eu"],
+                ] {
+                    assert!(args.windows(2).any(|pair| pair == expected), "{args:?}");
+                }
+                assert!(args.contains(&"--bail"));
+                assert!(args.contains(&"--insecure"));
+                assert!(!args.contains(&"--ssl-extra-ca-certs"));
+            }
 
-            let payload = probe.build_payload();
-            let expected = json!({
-                "model": "a-piece-of-cheese",
-                "messages": [{ "role": "user", "content": "test" }],
-                "max_tokens": 1,
-                "priority": -100
-            });
+            #[cfg(unix)]
+            #[tokio::test]
+            async fn test_newman_probe_network_options() {
+                let temp_dir = TempDir::new().unwrap();
+                let env_path = temp_dir.path().join("env");
+                let fake_newman = fake_newman(
+                    &temp_dir,
+                    &format!("echo \"$HTTPS_PROXY $NO_PROXY\" > {}", env_path.display()),
+                );
+                let probe = |network| {
+                    Newman::builder(empty_collection(&temp_dir))
+                        .network(network)
+                        .newman_path(fake_newman.to_str().unwrap())
+                        .build()
+                        .unwrap()
+                };
 
-            assert_eq!(payload, expected);
-        }
+                let proxied = probe(
+                    NetworkOptions::new()
+                        .proxy("http://proxy:3128")
+                        .no_proxy("localhost"),
+                );
+                assert_eq!(proxied.probe().await, ProbeResult::Success);
+                assert_eq!(
+                    fs::read_to_string(&env_path).unwrap(),
+                    "http://proxy:3128 localhost\n"
+                );
+
+                let resolved = probe(
+                    NetworkOptions::new().resolve("api.internal", ([10, 0, 3, 7], 443).into()),
+                );
+                match resolved.probe().await {
+                    ProbeResult::Misconfigured(reason) => {
+                        assert!(
+                            reason.contains("does not support DNS overrides"),
+                            "{reason}"
+                        );
+                    }
+                    result => panic!("expected a misconfiguration, got {result:?}"),
+                }
+            }
 
-        #[test]
-        fn test_openai_embedding_payload() {
-            let config = Config {
-                endpoint_type: Type::OpenAIEmbedding,
-                model_name: "text-embedding-ada-002".to_string(),
-                ..Default::default()
-            };
-            let probe = OpenAI::new(config).unwrap();
+            #[tokio::test]
+            async fn test_newman_probe_missing_iteration_data_is_misconfigured() {
+                let temp_dir = TempDir::new().unwrap();
+                let probe = Newman::builder(empty_collection(&temp_dir))
+                    .iteration_data_path("missing.csv")
+                    .build()
+                    .unwrap();
+
+                match probe.probe().await {
+                    ProbeResult::Misconfigured(reason) => {
+                        assert!(reason.starts_with("cannot read iteration data missing.csv"));
+                    }
+                    result => panic!("expected a misconfiguration, got {result:?}"),
+                }
+            }
 
-            let payload = probe.build_payload();
-            let expected = json!({
-                "model": "text-embedding-ada-002",
-                "input": "test",
-                "priority": -100
-            });
+            #[tokio::test]
+            async fn test_newman_probe_missing_binary_is_misconfigured() {
+                let temp_dir = TempDir::new().unwrap();
+                let probe = Newman::builder(empty_collection(&temp_dir))
+                    .newman_path("/nonexistent/newman")
+                    .build()
+                    .unwrap();
+
+                assert_eq!(
+                    probe.probe().await,
+                    ProbeResult::Misconfigured(
+                        "newman executable '/nonexistent/newman' not found".to_string()
+                    )
+                );
+            }
 
-            assert_eq!(payload, expected);
-        }
+            #[cfg(unix)]
+            #[tokio::test]
+            async fn test_newman_probe_rejected_collection_is_misconfigured() {
+                let temp_dir = TempDir::new().unwrap();
+                // newman exits without a report when it can't load the collection
+                let fake_newman = fake_newman(
+                    &temp_dir,
+                    "echo 'error: collection could not be loaded' >&2\nexit 1",
+                );
+                let probe = Newman::builder(empty_collection(&temp_dir))
+                    .newman_path(fake_newman.to_str().unwrap())
+                    .build()
+                    .unwrap();
+
+                assert_eq!(
+                    probe.probe().await,
+                    ProbeResult::Misconfigured(
+                        "newman did not run the collection: error: collection could not be loaded"
+                            .to_string()
+                    )
+                );
+            }
 
-        #[tokio::test]
-        async fn test_openai_successful_response() {
-            let server = MockServer::start();
+            #[cfg(unix)]
+            #[tokio::test]
+            async fn test_newman_probe_run_timeout_kills_process_group() {
+                let temp_dir = TempDir::new().unwrap();
+                let marker = temp_dir.path().join("survived");
+                // Stands in for a hung newman that also started a child of its own
+                let fake_newman = fake_newman(
+                    &temp_dir,
+                    &format!("(sleep 2 && touch {}) &\nsleep 30", marker.display()),
+                );
+
+                let probe = Newman::builder(empty_collection(&temp_dir))
+                    .newman_path(fake_newman.to_str().unwrap())
+                    .run_timeout(Duration::from_millis(500))
+                    .build()
+                    .unwrap();
+
+                let started = std::time::Instant::now();
+                assert_eq!(probe.probe().await, ProbeResult::Timeout);
+                assert!(started.elapsed() < Duration::from_secs(5));
+
+                tokio::time::sleep(Duration::from_secs(3)).await;
+                assert!(!marker.exists(), "child of newman outlived the timeout");
+            }
 
-            // Mock successful LLM response
-            let mock = server.mock(|when, then| {
-                when.method(POST)
-                    .path("/v1/chat/completions")
-                    .json_body(json!({
-                        "model": "gpt-4",
-                        "messages": [{ "role": "user", "content": "test" }],
-                        "max_tokens": 1,
-                        "priority": -100
+            #[tokio::test]
+            async fn test_newman_probe_with_mock_endpoints() {
+                let server = MockServer::start();
+                let temp_dir = TempDir::new().unwrap();
+
+                // Mock the endpoints from our test collection
+                let health_mock = server.mock(|when, then| {
+                    when.method(GET).path("/health");
+                    then.status(200).json_body(json!({"status": "ok"}));
+                });
+
+                let user_mock = server.mock(|when, then| {
+                    when.method(GET)
+                        .path("/api/v1/users/123")
+                        .header("Authorization", "Bearer test-token-12345");
+                    then.status(200)
+                        .json_body(json!({"id": 123, "name": "Test User"}));
+                });
+
+                let create_mock = server.mock(|when, then| {
+                    when.method(POST)
+                        .path("/api/v1/resources")
+                        .header("Authorization", "Bearer test-token-12345")
+                        .header("Content-Type", "application/json");
+                    then.status(201).json_body(json!({
+                        "id": 789,
+                        "name": "Test Resource",
+                        "description": "Created at 1234567890",
+                        "active": true
                     }));
-                then.status(200).json_body(json!({
-                    "choices": [{"message": {"role": "assistant", "content": "Hello"}}]
-                }));
-            });
+                });
+
+                let delete_mock = server.mock(|when, then| {
+                    when.method(DELETE)
+                        .path("/api/v1/resources/456")
+                        .header("Authorization", "Bearer test-token-12345");
+                    then.status(204);
+                });
+
+                // Create test collection file with server base URL
+                let collection_path = temp_dir.path().join("collection.json");
+                let collection_content = fs::read_to_string("test-collection.json")
+                    .unwrap_or_else(|_| include_str!("../test-collection.json").to_string());
+                fs::write(&collection_path, collection_content).unwrap();
+
+                // Create test environment file with mock server URL
+                let environment_path = temp_dir.path().join("environment.json");
+                let environment_content = json!({
+                    "id": "test-env",
+                    "name": "Test Environment",
+                    "values": [
+                        {
+                            "key": "base_url",
+                            "value": server.base_url(),
+                            "enabled": true,
+                            "type": "default"
+                        },
+                        {
+                            "key": "api_token",
+                            "value": "test-token-12345",
+                            "enabled": true,
+                            "type": "secret"
+                        }
+                    ],
+                    "_postman_variable_scope": "environment"
+                });
+                fs::write(&environment_path, environment_content.to_string()).unwrap();
+
+                let config = NewmanConfig {
+                    collection_path: collection_path.to_str().unwrap().to_string(),
+                    environment_path: Some(environment_path.to_str().unwrap().to_string()),
+                    ..Default::default()
+                };
 
-            let config = Config {
-                server_url: server.base_url(),
-                endpoint_type: Type::OpenAIChatCompletion,
-                model_name: "gpt-4".to_string(),
-                ..Default::default()
-            };
+                let probe = Newman::new(config).unwrap();
+                let result = probe.probe().await;
 
-            let probe = OpenAI::new(config).unwrap();
-            let result = probe.probe().await;
+                // Newman should succeed if all tests pass
+                assert_eq!(result, ProbeResult::Success);
 
-            assert_eq!(result, ProbeResult::Success);
+                // Verify all endpoints were called
+                health_mock.assert();
+                user_mock.assert();
+                create_mock.assert();
+                delete_mock.assert();
+            }
 
-            mock.assert();
-        }
+            #[tokio::test]
+            async fn test_newman_probe_with_failed_test() {
+                let server = MockServer::start();
+                let temp_dir = TempDir::new().unwrap();
+
+                // Mock health endpoint to return 500 (will fail the test)
+                let health_mock = server.mock(|when, then| {
+                    when.method(GET).path("/health");
+                    then.status(500).json_body(json!({"error": "Server error"}));
+                });
+
+                // Create a minimal collection with just the health check
+                let collection_path = temp_dir.path().join("collection.json");
+                let collection_content = json!({
+                    "info": {
+                        "name": "Test Collection",
+                        "schema": "https://schema.getpostman.com/json/collection/v2.1.0/collection.json"
+                    },
+                    "item": [{
+                        "name": "Health Check",
+                        "event": [{
+                            "listen": "test",
+                            "script": {
+                                "exec": [
+                                    "pm.test(\"Status code is 200\", function () {",
+                                    "    pm.response.to.have.status(200);",
+                                    "});"
+                                ],
+                                "type": "text/javascript"
+                            }
+                        }],
+                        "request": {
+                            "method": "GET",
+                            "url": "{{base_url}}/health"
+                        }
+                    }]
+                });
+                fs::write(&collection_path, collection_content.to_string()).unwrap();
+
+                // Create environment file
+                let environment_path = temp_dir.path().join("environment.json");
+                let environment_content = json!({
+                    "id": "test-env",
+                    "name": "Test Environment",
+                    "values": [{
+                        "key": "base_url",
+                        "value": server.base_url(),
+                        "enabled": true,
+                        "type": "default"
+                    }],
+                    "_postman_variable_scope": "environment"
+                });
+                fs::write(&environment_path, environment_content.to_string()).unwrap();
+
+                let config = NewmanConfig {
+                    collection_path: collection_path.to_str().unwrap().to_string(),
+                    environment_path: Some(environment_path.to_str().unwrap().to_string()),
+                    ..Default::default()
+                };
 
-        #[tokio::test]
-        async fn test_openai_http_error_response() {
-            let server = MockServer::start();
+                let probe = Newman::new(config).unwrap();
+                let result = probe.probe().await;
 
-            // Mock failed LLM response
-            let mock = server.mock(|when, then| {
-                when.method(POST).path("/v1/embeddings");
-                then.status(420).json_body(json!({
-                    "error": {"message": "Internal server error"}
-                }));
-            });
+                // Newman should fail if any test fails, naming the failed assertion
+                assert_eq!(
+                    result,
+                    ProbeResult::TestFailure(
+                        "Health Check: Status code is 200 (expected response to have status code 200 but got 500)".to_string()
+                    )
+                );
 
-            let config = Config {
-                server_url: server.base_url(),
-                endpoint_type: Type::OpenAIEmbedding,
-                model_name: "text-embedding-ada-002".to_string(),
-                ..Default::default()
-            };
+                health_mock.assert();
+            }
+
+            #[tokio::test]
+            async fn test_newman_probe_without_environment() {
+                let server = MockServer::start();
+                let temp_dir = TempDir::new().unwrap();
+
+                // Mock endpoint without auth
+                let health_mock = server.mock(|when, then| {
+                    when.method(GET).path("/health");
+                    then.status(200).json_body(json!({"status": "ok"}));
+                });
+
+                // Create collection with hardcoded URL (no environment needed)
+                let collection_path = temp_dir.path().join("collection.json");
+                let collection_content = json!({
+                    "info": {
+                        "name": "Test Collection",
+                        "schema": "https://schema.getpostman.com/json/collection/v2.1.0/collection.json"
+                    },
+                    "item": [{
+                        "name": "Health Check",
+                        "event": [{
+                            "listen": "test",
+                            "script": {
+                                "exec": [
+                                    "pm.test(\"Status code is 200\", function () {",
+                                    "    pm.response.to.have.status(200);",
+                                    "});"
+                                ],
+                                "type": "text/javascript"
+                            }
+                        }],
+                        "request": {
+                            "method": "GET",
+                            "url": format!("{}/health", server.base_url())
+                        }
+                    }]
+                });
+                fs::write(&collection_path, collection_content.to_string()).unwrap();
+
+                let config = NewmanConfig {
+                    collection_path: collection_path.to_str().unwrap().to_string(),
+                    environment_path: None, // No environment file
+                    ..Default::default()
+                };
 
-            let probe = OpenAI::new(config).unwrap();
-            let result = probe.probe().await;
+                let probe = Newman::new(config).unwrap();
+                let result = probe.probe().await;
 
-            match result {
-                ProbeResult::Error(status_code) => {
-                    assert_eq!(status_code, 420);
-                }
-                _ => panic!("Expected HTTP error probe result"),
+                assert_eq!(result, ProbeResult::Success);
+                health_mock.assert();
             }
-
-            mock.assert();
         }
+    }
+}
 
-        #[tokio::test]
-        async fn test_openai_timeout() {
-            let config = Config {
-                server_url: "http://10.255.255.1:12345".to_string(), // Non-routable IP for timeout
-                timeout_seconds: 1,                                  // Very short timeout
-                ..Default::default()
-            };
+// The monitor tests run the default probe and exporter from the command line configuration
+#[cfg(all(test, feature = "cli", feature = "cronitor", feature = "openai"))]
+mod tests {
+    use super::{
+        EXIT_MISCONFIGURED, Error, Export, Monitor, PingState, Probe, ProbeResult, Result,
+        cli::Config, exporters::Cronitor, registry::Registry,
+    };
+    use httpmock::prelude::*;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
 
-            let probe = OpenAI::new(config).unwrap();
-            let result = probe.probe().await;
+    /// Probe defined outside the library's probe types
+    struct StaticProbe(u16);
 
-            assert!(matches!(result, ProbeResult::Timeout));
+    #[async_trait::async_trait]
+    impl Probe for StaticProbe {
+        async fn probe(&self) -> ProbeResult {
+            match self.0 {
+                200 => ProbeResult::Success,
+                status => ProbeResult::Error(status),
+            }
         }
+    }
 
-        #[tokio::test]
-        async fn test_openai_network_error() {
-            let config = Config {
-                server_url: "http://localhost:99999".to_string(), // Invalid port
-                ..Default::default()
-            };
+    /// Exporter recording the pings it receives
+    #[derive(Clone, Default)]
+    struct RecordingExporter(Arc<Mutex<Vec<(PingState, u16)>>>);
 
-            let probe = OpenAI::new(config).unwrap();
-            let result = probe.probe().await;
+    #[async_trait::async_trait]
+    impl Export for RecordingExporter {
+        async fn ping(&self, state: PingState, status_code: u16, _: Option<&str>) -> Result<()> {
+            self.0.lock().unwrap().push((state, status_code));
+            Ok(())
+        }
+    }
 
-            match result {
-                ProbeResult::NetworkError(error) => {
-                    assert!(!error.is_empty());
+    #[tokio::test]
+    async fn test_monitor_probes_all_addresses() {
+        let probed = Arc::new(Mutex::new(Vec::new()));
+        let exporter = RecordingExporter::default();
+        let registry = Registry::empty()
+            .register_probe("static", {
+                let probed = probed.clone();
+                move |config| {
+                    probed.lock().unwrap().push(config.resolve.clone());
+                    Ok(Box::new(StaticProbe(200)))
                 }
-                _ => panic!("Expected network error probe result"),
-            }
-        }
+            })
+            .register_exporter("recording", {
+                let exporter = exporter.clone();
+                move |_| Ok(Box::new(exporter.clone()))
+            });
+        let config = Config {
+            server_url: "http://localhost:8080".to_string(),
+            endpoint_type: "static".to_string(),
+            exporter: "recording".to_string(),
+            resolve: vec![("localhost".to_string(), "10.0.0.1:8080".parse().unwrap())],
+            probe_all_addresses: true,
+            ..Default::default()
+        };
 
-        // Newman probe tests
-        use std::fs;
-        use tempfile::TempDir;
+        let monitor = Monitor::with_registry(config, &registry).unwrap();
 
-        #[test]
-        fn test_newman_probe_creation() {
-            let config = Config {
-                endpoint_type: Type::Newman,
-                collection_path: "test-collection.json".to_string(),
-                environment_path: Some("test-environment.json".to_string()),
-                ..Default::default()
+        let probed = probed.lock().unwrap().clone();
+        assert!(!probed.is_empty());
+        for resolve in probed {
+            let [(host, address)] = resolve.as_slice() else {
+                panic!("expected a single DNS override, got {resolve:?}");
             };
-            let probe = Newman::new(config);
-            assert!(probe.is_ok());
+            assert_eq!(host, "localhost");
+            assert!(address.ip().is_loopback(), "{address}");
         }
+        assert_eq!(monitor.run().await, 0);
+        assert_eq!(
+            *exporter.0.lock().unwrap(),
+            [(PingState::Run, 0), (PingState::Complete, 0)]
+        );
+    }
 
-        #[tokio::test]
-        async fn test_newman_probe_with_mock_endpoints() {
-            let server = MockServer::start();
-            let temp_dir = TempDir::new().unwrap();
-
-            // Mock the endpoints from our test collection
-            let health_mock = server.mock(|when, then| {
-                when.method(GET).path("/health");
-                then.status(200).json_body(json!({"status": "ok"}));
-            });
+    #[tokio::test]
+    async fn test_monitor_builder_with_custom_probe_and_exporter() {
+        let exporter = RecordingExporter::default();
+
+        let monitor = Monitor::builder()
+            .probe(Box::new(StaticProbe(503)))
+            .exporter(Box::new(exporter.clone()))
+            .build()
+            .unwrap();
+        let exit_code = monitor.run().await;
 
-            let user_mock = server.mock(|when, then| {
-                when.method(GET)
-                    .path("/api/v1/users/123")
-                    .header("Authorization", "Bearer test-token-12345");
-                then.status(200)
-                    .json_body(json!({"id": 123, "name": "Test User"}));
-            });
+        assert_eq!(exit_code, 1);
+        assert_eq!(
+            *exporter.0.lock().unwrap(),
+            vec![(PingState::Run, 0), (PingState::Fail, 503)]
+        );
+    }
 
-            let create_mock = server.mock(|when, then| {
-                when.method(POST)
-                    .path("/api/v1/resources")
-                    .header("Authorization", "Bearer test-token-12345")
-                    .header("Content-Type", "application/json");
-                then.status(201).json_body(json!({
-                    "id": 789,
-                    "name": "Test Resource",
-                    "description": "Created at 1234567890",
-                    "active": true
-                }));
-            });
+    #[tokio::test]
+    async fn test_monitor_run_test_failure_message() {
+        struct FailingSteps;
 
-            let delete_mock = server.mock(|when, then| {
-                when.method(DELETE)
-                    .path("/api/v1/resources/456")
-                    .header("Authorization", "Bearer test-token-12345");
-                then.status(204);
-            });
+        #[async_trait::async_trait]
+        impl Probe for FailingSteps {
+            async fn probe(&self) -> ProbeResult {
+                ProbeResult::TestFailure("Create Resource: Status code is 201 (got 500)".into())
+            }
+        }
 
-            // Create test collection file with server base URL
-            let collection_path = temp_dir.path().join("collection.json");
-            let collection_content = fs::read_to_string("test-collection.json")
-                .unwrap_or_else(|_| include_str!("../test-collection.json").to_string());
-            fs::write(&collection_path, collection_content).unwrap();
-
-            // Create test environment file with mock server URL
-            let environment_path = temp_dir.path().join("environment.json");
-            let environment_content = json!({
-                "id": "test-env",
-                "name": "Test Environment",
-                "values": [
-                    {
-                        "key": "base_url",
-                        "value": server.base_url(),
-                        "enabled": true,
-                        "type": "default"
-                    },
-                    {
-                        "key": "api_token",
-                        "value": "test-token-12345",
-                        "enabled": true,
-                        "type": "secret"
-                    }
-                ],
-                "_postman_variable_scope": "environment"
-            });
-            fs::write(&environment_path, environment_content.to_string()).unwrap();
+        let server = MockServer::start();
+        let cronitor_run_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/test-monitor")
+                .query_param("state", "run");
+            then.status(200);
+        });
+        let cronitor_fail_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/test-monitor")
+                .query_param("state", "fail")
+                .query_param("status_code", "1")
+                .query_param("message", "Create Resource: Status code is 201 (got 500)");
+            then.status(200);
+        });
 
-            let config = Config {
-                endpoint_type: Type::Newman,
-                collection_path: collection_path.to_str().unwrap().to_string(),
-                environment_path: Some(environment_path.to_str().unwrap().to_string()),
-                ..Default::default()
-            };
+        let monitor = Monitor::builder()
+            .probe(Box::new(FailingSteps))
+            .exporter(Box::new(
+                Cronitor::builder(server.base_url(), "test-monitor")
+                    .build()
+                    .unwrap(),
+            ))
+            .build()
+            .unwrap();
+
+        assert_eq!(monitor.run().await, 1);
+        cronitor_run_mock.assert();
+        cronitor_fail_mock.assert();
+    }
 
-            let probe = Newman::new(config).unwrap();
-            let result = probe.probe().await;
+    #[tokio::test]
+    async fn test_monitor_run_misconfigured() {
+        let server = MockServer::start();
+        let cronitor_fail_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/test-monitor")
+                .query_param("state", "fail")
+                .query_param("status_code", "78")
+                .query_param(
+                    "message",
+                    "Probe misconfigured: cannot read collection missing.json: \
+                     No such file or directory (os error 2)",
+                );
+            then.status(200);
+        });
+        let config = Config {
+            cronitor_base_url: Some(server.base_url()),
+            endpoint_type: "newman".to_string(),
+            collection_path: "missing.json".to_string(),
+            ..Default::default()
+        };
 
-            // Newman should succeed if all tests pass
-            assert_eq!(result, ProbeResult::Success);
+        let monitor = Monitor::new(config).unwrap();
 
-            // Verify all endpoints were called
-            health_mock.assert();
-            user_mock.assert();
-            create_mock.assert();
-            delete_mock.assert();
-        }
+        assert_eq!(monitor.run().await, EXIT_MISCONFIGURED);
+        cronitor_fail_mock.assert();
+    }
 
-        #[tokio::test]
-        async fn test_newman_probe_with_failed_test() {
-            let server = MockServer::start();
-            let temp_dir = TempDir::new().unwrap();
+    #[test]
+    fn test_monitor_builder_requires_probe() {
+        let result = Monitor::builder()
+            .exporter(Box::new(RecordingExporter::default()))
+            .build();
 
-            // Mock health endpoint to return 500 (will fail the test)
-            let health_mock = server.mock(|when, then| {
-                when.method(GET).path("/health");
-                then.status(500).json_body(json!({"error": "Server error"}));
-            });
+        assert!(matches!(
+            result,
+            Err(Error::InvalidConfig { field: "probe", .. })
+        ));
+    }
 
-            // Create a minimal collection with just the health check
-            let collection_path = temp_dir.path().join("collection.json");
-            let collection_content = json!({
-                "info": {
-                    "name": "Test Collection",
-                    "schema": "https://schema.getpostman.com/json/collection/v2.1.0/collection.json"
-                },
-                "item": [{
-                    "name": "Health Check",
-                    "event": [{
-                        "listen": "test",
-                        "script": {
-                            "exec": [
-                                "pm.test(\"Status code is 200\", function () {",
-                                "    pm.response.to.have.status(200);",
-                                "});"
-                            ],
-                            "type": "text/javascript"
-                        }
-                    }],
-                    "request": {
-                        "method": "GET",
-                        "url": "{{base_url}}/health"
-                    }
-                }]
-            });
-            fs::write(&collection_path, collection_content.to_string()).unwrap();
-
-            // Create environment file
-            let environment_path = temp_dir.path().join("environment.json");
-            let environment_content = json!({
-                "id": "test-env",
-                "name": "Test Environment",
-                "values": [{
-                    "key": "base_url",
-                    "value": server.base_url(),
-                    "enabled": true,
-                    "type": "default"
-                }],
-                "_postman_variable_scope": "environment"
+    #[tokio::test]
+    async fn test_monitor_with_registered_probe_and_exporter() {
+        let exporter = RecordingExporter::default();
+        let pings = exporter.0.clone();
+        let registry = Registry::default()
+            .register_probe("in-house-rpc", |_| Ok(Box::new(StaticProbe(200))))
+            .register_exporter("recording", move |_| {
+                Ok(Box::new(RecordingExporter(pings.clone())))
             });
-            fs::write(&environment_path, environment_content.to_string()).unwrap();
+        let config = Config {
+            endpoint_type: "in-house-rpc".to_string(),
+            exporter: "recording".to_string(),
+            ..Default::default()
+        };
 
-            let config = Config {
-                endpoint_type: Type::Newman,
-                collection_path: collection_path.to_str().unwrap().to_string(),
-                environment_path: Some(environment_path.to_str().unwrap().to_string()),
-                ..Default::default()
-            };
+        let monitor = Monitor::with_registry(config, &registry).unwrap();
 
-            let probe = Newman::new(config).unwrap();
-            let result = probe.probe().await;
+        assert_eq!(monitor.run().await, 0);
+        assert_eq!(
+            *exporter.0.lock().unwrap(),
+            vec![(PingState::Run, 0), (PingState::Complete, 0)]
+        );
+    }
 
-            // Newman should fail if any test fails
-            assert_eq!(result, ProbeResult::Error(1));
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_cli_http_config() {
+        use super::probes::HttpConfig;
 
-            health_mock.assert();
-        }
+        let config = Config {
+            server_url: "http://tokenizer/".to_string(),
+            endpoint_path: Some("/health".to_string()),
+            api_key: Some("secret".to_string()),
+            http_headers: vec![("x-team".to_string(), "ml".to_string())],
+            expected_json: vec![
+                ("$.status".to_string(), "ok".to_string()),
+                ("$.workers".to_string(), "4".to_string()),
+            ],
+            ..Default::default()
+        };
+        let http = HttpConfig::from(&config);
+
+        assert_eq!(http.url, "http://tokenizer/health");
+        assert_eq!(
+            http.headers,
+            [
+                ("Authorization".to_string(), "Bearer secret".to_string()),
+                ("x-team".to_string(), "ml".to_string())
+            ]
+        );
+        assert_eq!(
+            http.expected_json,
+            [
+                ("$.status".to_string(), json!("ok")),
+                ("$.workers".to_string(), json!(4))
+            ]
+        );
+    }
 
-        #[tokio::test]
-        async fn test_newman_probe_without_environment() {
-            let server = MockServer::start();
-            let temp_dir = TempDir::new().unwrap();
+    #[test]
+    fn test_cli_compare_config() {
+        use super::probes::{CompareConfig, Type};
 
-            // Mock endpoint without auth
-            let health_mock = server.mock(|when, then| {
-                when.method(GET).path("/health");
-                then.status(200).json_body(json!({"status": "ok"}));
-            });
+        let config = Config {
+            endpoint_type: "openai-compare".to_string(),
+            server_url: "http://production".to_string(),
+            candidate_server_url: Some("http://canary".to_string()),
+            compare_endpoint_type: "openai-embedding".to_string(),
+            min_embedding_similarity: 0.95,
+            ..Default::default()
+        };
+        let compare = CompareConfig::try_from(&config).unwrap();
 
-            // Create collection with hardcoded URL (no environment needed)
-            let collection_path = temp_dir.path().join("collection.json");
-            let collection_content = json!({
-                "info": {
-                    "name": "Test Collection",
-                    "schema": "https://schema.getpostman.com/json/collection/v2.1.0/collection.json"
-                },
-                "item": [{
-                    "name": "Health Check",
-                    "event": [{
-                        "listen": "test",
-                        "script": {
-                            "exec": [
-                                "pm.test(\"Status code is 200\", function () {",
-                                "    pm.response.to.have.status(200);",
-                                "});"
-                            ],
-                            "type": "text/javascript"
-                        }
-                    }],
-                    "request": {
-                        "method": "GET",
-                        "url": format!("{}/health", server.base_url())
-                    }
-                }]
-            });
-            fs::write(&collection_path, collection_content.to_string()).unwrap();
+        assert_eq!(compare.baseline.server_url, "http://production");
+        assert_eq!(compare.baseline.endpoint_type, Type::OpenAIEmbedding);
+        assert_eq!(compare.candidate_url, "http://canary");
+        assert_eq!(compare.min_similarity, 0.95);
+        assert!(Monitor::new(config.clone()).is_ok());
 
-            let config = Config {
-                endpoint_type: Type::Newman,
-                collection_path: collection_path.to_str().unwrap().to_string(),
-                environment_path: None, // No environment file
-                ..Default::default()
-            };
+        // A mistyped endpoint type isn't compared as a chat completion
+        let config = Config {
+            compare_endpoint_type: "openai-embeddings".to_string(),
+            ..config
+        };
+        assert!(matches!(
+            Monitor::new(config),
+            Err(Error::UnknownProbe { name, .. }) if name == "openai-embeddings"
+        ));
+    }
 
-            let probe = Newman::new(config).unwrap();
-            let result = probe.probe().await;
+    #[test]
+    fn test_cli_bench_subcommand() {
+        use super::{exporters::CronitorConfig, probes::BenchConfig};
+        use clap::Parser;
+
+        // Cronitor's options are only required when monitoring
+        let config = Config::try_parse_from([
+            "ai-vitals",
+            "--server-url",
+            "http://localhost:8000",
+            "--endpoint-type",
+            "openai-embedding",
+            "bench",
+            "--concurrency",
+            "16",
+            "--duration-seconds",
+            "60",
+        ])
+        .unwrap();
+        let bench = BenchConfig::try_from(&config).unwrap();
+
+        assert_eq!(config.cronitor_base_url, None);
+        assert!(matches!(
+            CronitorConfig::try_from(&config),
+            Err(Error::InvalidConfig {
+                field: "cronitor base URL",
+                ..
+            })
+        ));
+        assert_eq!(bench.probe.server_url, "http://localhost:8000");
+        assert_eq!(bench.concurrency, 16);
+        assert_eq!(bench.duration, std::time::Duration::from_secs(60));
+        assert_eq!(bench.max_tokens, None);
+        assert!(Config::try_parse_from(["ai-vitals", "--server-url", "http://localhost"]).is_err());
+
+        // A mistyped endpoint type isn't benchmarked as a chat completion
+        let config = Config {
+            endpoint_type: "openai-embeding".to_string(),
+            ..config
+        };
+        assert!(matches!(
+            BenchConfig::try_from(&config),
+            Err(Error::UnknownProbe { name, .. }) if name == "openai-embeding"
+        ));
+    }
 
-            assert_eq!(result, ProbeResult::Success);
-            health_mock.assert();
+    #[test]
+    fn test_cli_network_options() {
+        use super::probes::NetworkOptions;
+        use clap::Parser;
+
+        let config = Config::try_parse_from([
+            "ai-vitals",
+            "--cronitor-base-url",
+            "https://cronitor.link",
+            "--monitor-name",
+            "replica",
+            "--probe-no-proxy",
+            "*",
+            "--resolve",
+            "api.example.com:443:10.0.3.7,api.example.com:8443:[::1]",
+        ])
+        .unwrap();
+        let network = NetworkOptions::from(&config);
+
+        assert_eq!(network.proxy, None);
+        assert_eq!(network.no_proxy.as_deref(), Some("*"));
+        assert_eq!(
+            network.resolve,
+            [
+                (
+                    "api.example.com".to_string(),
+                    "10.0.3.7:443".parse().unwrap()
+                ),
+                ("api.example.com".to_string(), "[::1]:8443".parse().unwrap())
+            ]
+        );
+        for invalid in ["api.example.com:10.0.3.7", "api.example.com:https:10.0.3.7"] {
+            assert!(
+                Config::try_parse_from([
+                    "ai-vitals",
+                    "--cronitor-base-url",
+                    "https://cronitor.link",
+                    "--monitor-name",
+                    "replica",
+                    "--resolve",
+                    invalid,
+                ])
+                .is_err()
+            );
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::{Monitor, cli::Config};
-    use httpmock::prelude::*;
-    use serde_json::json;
+    #[test]
+    fn test_cli_newman_variable_overrides() {
+        use clap::Parser;
+
+        let config = Config::try_parse_from([
+            "ai-vitals",
+            "--cronitor-base-url",
+            "https://cronitor.link",
+            "--monitor-name",
+            "shared-collection",
+            "--newman-env-vars",
+            "api_token=abc=,base_url=https://staging",
+            "--newman-folders",
+            "Health,Chat",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            config.newman_env_vars,
+            [
+                ("api_token".to_string(), "abc=".to_string()),
+                ("base_url".to_string(), "https://staging".to_string())
+            ]
+        );
+        assert_eq!(config.newman_folders, ["Health", "Chat"]);
+        assert!(
+            Config::try_parse_from([
+                "ai-vitals",
+                "--cronitor-base-url",
+                "https://cronitor.link",
+                "--monitor-name",
+                "shared-collection",
+                "--newman-global-vars",
+                "no-value",
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_registry_has_built_in_names() {
+        let registry = Registry::default();
+
+        assert!(registry.probe_names().any(|name| name == "openai-vision"));
+        assert!(
+            registry
+                .probe_names()
+                .any(|name| name == "openai-determinism")
+        );
+        assert!(registry.probe_names().any(|name| name == "newman"));
+        assert!(registry.exporter_names().any(|name| name == "cronitor"));
+    }
+
+    #[test]
+    fn test_monitor_rejects_unknown_probe_name() {
+        let config = Config {
+            endpoint_type: "does-not-exist".to_string(),
+            ..Default::default()
+        };
+
+        match Monitor::new(config) {
+            Err(Error::UnknownProbe { name, available }) => {
+                assert_eq!(name, "does-not-exist");
+                assert!(available.contains(&"openai-chat-completion".to_string()));
+            }
+            _ => panic!("expected an unknown probe error"),
+        }
+    }
 
     #[tokio::test]
     async fn test_monitor_creation() {
@@ -1067,7 +10185,7 @@ mod tests {
         });
 
         let config = Config {
-            cronitor_base_url: server.base_url(),
+            cronitor_base_url: Some(server.base_url()),
             server_url: server.base_url(),
             ..Default::default()
         };
@@ -1109,7 +10227,7 @@ mod tests {
         });
 
         let config = Config {
-            cronitor_base_url: server.base_url(),
+            cronitor_base_url: Some(server.base_url()),
             server_url: server.base_url(),
             ..Default::default()
         };
@@ -1145,7 +10263,7 @@ mod tests {
         });
 
         let config = Config {
-            cronitor_base_url: server.base_url(),
+            cronitor_base_url: Some(server.base_url()),
             server_url: "http://10.255.255.1:12345".to_string(), // Non-routable for timeout
             timeout_seconds: 1,
             ..Default::default()
@@ -1181,7 +10299,7 @@ mod tests {
         });
 
         let config = Config {
-            cronitor_base_url: server.base_url(),
+            cronitor_base_url: Some(server.base_url()),
             server_url: "http://localhost:99999".to_string(), // Invalid port
             ..Default::default()
         };
@@ -1194,6 +10312,50 @@ mod tests {
         cronitor_fail_mock.assert();
     }
 
+    #[tokio::test]
+    async fn test_monitor_run_invalid_response() {
+        let server = MockServer::start();
+
+        // Mock a chat response that ignores the forced tool call
+        let llm_mock = server.mock(|when, then| {
+            when.method(POST).path("/v1/chat/completions");
+            then.status(200).json_body(
+                json!({"choices": [{"message": {"role": "assistant", "content": "OK"}}]}),
+            );
+        });
+
+        let cronitor_run_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/test-monitor")
+                .query_param("state", "run");
+            then.status(200);
+        });
+
+        let cronitor_fail_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/test-monitor")
+                .query_param("state", "fail")
+                .query_param("status_code", "1")
+                .query_param("message", "Invalid response: response has no tool_calls");
+            then.status(200);
+        });
+
+        let config = Config {
+            cronitor_base_url: Some(server.base_url()),
+            server_url: server.base_url(),
+            endpoint_type: "openai-tool-call".to_string(),
+            ..Default::default()
+        };
+
+        let monitor = Monitor::new(config).unwrap();
+        let exit_code = monitor.run().await;
+
+        assert_eq!(exit_code, 1);
+        llm_mock.assert();
+        cronitor_run_mock.assert();
+        cronitor_fail_mock.assert();
+    }
+
     #[tokio::test]
     async fn test_monitor_cronitor_message_validation() {
         let server = MockServer::start();
@@ -1219,7 +10381,7 @@ mod tests {
         });
 
         let config = Config {
-            cronitor_base_url: server.base_url(),
+            cronitor_base_url: Some(server.base_url()),
             server_url: "http://10.255.255.1:12345".to_string(), // Non-routable for timeout
             timeout_seconds: 1,
             ..Default::default()
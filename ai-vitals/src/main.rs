@@ -1,14 +1,17 @@
-use ai_vitals::{Monitor, cli::Config};
+use ai_vitals::{
+    Monitor,
+    cli::{Command, Config},
+};
 use anyhow::{Context, Result};
 use clap::Parser;
 use std::process::exit;
 use tracing_subscriber::{EnvFilter, fmt, prelude::*};
 
 /// Setup tracing/logging for the application
-fn setup_logging() {
+fn setup_logging(default_filter: &str) {
     let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-        // Default to info level if no RUST_LOG env var is set
-        EnvFilter::new("ai_vitals=info")
+        // Default to the given level if no RUST_LOG env var is set
+        EnvFilter::new(default_filter)
     });
 
     tracing_subscriber::registry()
@@ -19,12 +22,35 @@ fn setup_logging() {
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    setup_logging();
-
     let config = Config::parse();
 
-    let monitor = Monitor::new(config).context("Failed to create monitor")?;
-    let exit_code = monitor.run().await;
+    match &config.command {
+        Some(Command::Bench(_)) => {
+            // Every request would be logged at info level
+            setup_logging("ai_vitals=warn");
+            bench(&config).await
+        }
+        None => {
+            setup_logging("ai_vitals=info");
+
+            let monitor = Monitor::new(config).context("Failed to create monitor")?;
+            let exit_code = monitor.run().await;
+
+            exit(exit_code);
+        }
+    }
+}
+
+#[cfg(feature = "openai")]
+async fn bench(config: &Config) -> Result<()> {
+    use ai_vitals::probes::Bench;
+
+    let bench = Bench::new(config.try_into()?).context("Failed to create benchmark")?;
+    println!("{}", bench.run().await);
+    Ok(())
+}
 
-    exit(exit_code);
+#[cfg(not(feature = "openai"))]
+async fn bench(_config: &Config) -> Result<()> {
+    anyhow::bail!("ai-vitals was built without the openai feature, which bench needs")
 }